@@ -3,20 +3,27 @@ use std::{thread, time};
 use crate::{
     grid::Grid,
     menu::Menu,
-    network::{Network, Update},
+    network::{Hint, Network, Seats, Update},
+    replay::ReplayViewer,
+    settings::Settings,
 };
 
 use macroquad::{
     color::Color,
-    input::{MouseButton, is_mouse_button_pressed, mouse_position},
+    input::{KeyCode, MouseButton, is_key_pressed, is_mouse_button_pressed, mouse_position},
     math::Vec2,
-    miniquad::window::screen_size,
+    miniquad::window::{clipboard_set, screen_size},
     text::draw_text,
+    time::get_time,
     window::{clear_background, next_frame, screen_height, screen_width},
 };
 
 const MIN_WIDTH: f32 = 64.0;
 const MIN_HEIGHT: f32 = 64.0;
+/// Seconds between attempts to reach the server again.
+const RECONNECT_DELAY: f64 = 2.0;
+/// Seconds the window size has to stay put before it is written to the settings file.
+const RESIZE_SETTLE: f64 = 1.0;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ClientErr {
@@ -26,6 +33,24 @@ pub enum ClientErr {
     #[error("Join failed!")]
     JoinFail,
 
+    #[error("The server cannot start the daily challenge right now!")]
+    DailyUnavailable,
+
+    #[error("{0}")]
+    Auth(String),
+
+    #[error("Unexpected server response!")]
+    UnexpectedResponse,
+
+    #[error("Unsupported grid encoding {0}!")]
+    GridVersion(u8),
+
+    #[error("{0}")]
+    Replay(String),
+
+    #[error("Unsupported replay version {0}!")]
+    ReplayVersion(u32),
+
     #[error("Io error: {0}")]
     IO(#[from] std::io::Error),
 }
@@ -35,24 +60,52 @@ pub struct App {
     grid: Option<Grid>,
     network: Network,
     window_size: Vec2,
+    /// Time of the last size change, dragging the window edge changes it every frame.
+    resized_at: f64,
     mouse_pos: Vec2,
     my_turn: bool,
+    hint: Option<Hint>,
+    hint_denied: bool,
+    position_copied: bool,
+    /// Whether the player wants the distance overlay, rooms may still deny it.
+    analysis: bool,
+    analysis_denied: bool,
+    replay: Option<ReplayViewer>,
+    /// Time of the next reconnect attempt while the server is gone.
+    reconnect_at: Option<f64>,
+    /// Seats and free seats of the room while it waits for players.
+    room_seats: Option<(Seats, Seats)>,
+    show_fps: bool,
+    /// What was last written to the settings file.
+    settings: Settings,
 }
 
 impl App {
-    pub fn new() -> Result<Self, ClientErr> {
+    pub fn new(network: Network, settings: Settings) -> Self {
         let mut app = Self {
             grid: None,
             menu: Menu::new(),
-            network: Network::new()?,
+            network,
             window_size: screen_size().into(),
+            resized_at: 0.0,
             mouse_pos: Vec2::new(0.0, 0.0),
             my_turn: false,
+            hint: None,
+            hint_denied: false,
+            position_copied: false,
+            analysis: settings.analysis,
+            analysis_denied: false,
+            replay: None,
+            reconnect_at: None,
+            room_seats: None,
+            show_fps: settings.show_fps,
+            settings,
         };
 
+        app.menu.apply_settings(&app.settings);
         app.menu.refresh_rooms(&mut app.network);
 
-        Ok(app)
+        app
     }
 
     pub fn check_resize(&mut self) {
@@ -73,13 +126,66 @@ impl App {
             if let Some(grid) = &mut self.grid {
                 grid.center();
             }
+            if let Some(viewer) = &mut self.replay {
+                viewer.grid.center();
+            }
 
             self.window_size = current_size;
+            self.resized_at = get_time();
+        }
+    }
+
+    pub fn show_fps(&self) -> bool {
+        self.show_fps
+    }
+
+    /// Writes the settings file whenever one of the remembered preferences changed, the window
+    /// size once it settled.
+    pub fn save_settings(&mut self) {
+        let (window_width, window_height) = if get_time() - self.resized_at >= RESIZE_SETTLE {
+            (self.window_size.x as u32, self.window_size.y as u32)
+        } else {
+            (self.settings.window_width, self.settings.window_height)
+        };
+        let current = Settings {
+            username: self.menu.username.clone(),
+            player_type: self.menu.player_type(),
+            server: self.settings.server.clone(),
+            recent_servers: self.settings.recent_servers.clone(),
+            window_width,
+            window_height,
+            analysis: self.analysis,
+            show_fps: self.show_fps,
+        };
+
+        if current != self.settings {
+            if let Err(e) = current.save() {
+                eprintln!("Could not save the settings [{}]", e);
+            }
+            self.settings = current;
         }
     }
 
     pub fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::F3) {
+            self.show_fps = !self.show_fps;
+        }
+
+        if let Some(viewer) = &mut self.replay {
+            viewer.handle_input();
+            if viewer.closed {
+                self.replay = None;
+                self.menu.visible = true;
+            }
+            return;
+        }
+
+        if self.reconnect_at.is_some() {
+            return;
+        }
+
         self.menu.handle_input(&mut self.network);
+        let mut refresh_analysis = false;
 
         if let Some(grid) = &mut self.grid {
             let current_mouse_pos = mouse_position().into();
@@ -94,31 +200,113 @@ impl App {
                 self.mouse_pos = current_mouse_pos;
             }
 
+            if is_key_pressed(KeyCode::H) && self.my_turn {
+                match self.network.request_hint() {
+                    Ok(Some(hint)) => {
+                        for tile in [hint.tile, hint.mouse].into_iter().flatten() {
+                            grid.suggest(tile);
+                        }
+                        self.hint = Some(hint);
+                    }
+                    Ok(None) => self.hint_denied = true,
+                    Err(e) => eprintln!("Error at request hint [{}]", e),
+                }
+            }
+
+            if is_key_pressed(KeyCode::A) && self.my_turn && !self.analysis_denied {
+                self.analysis = !self.analysis;
+                if !self.analysis {
+                    grid.clear_distances();
+                }
+                refresh_analysis = self.analysis;
+            }
+
+            // Only while it is our turn, otherwise a turn update could arrive mid reply
+            if is_key_pressed(KeyCode::C) && self.my_turn {
+                match self.network.request_position() {
+                    Ok(position) if !position.is_empty() => {
+                        clipboard_set(&position);
+                        self.position_copied = true;
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Error at request position [{}]", e),
+                }
+            }
+
             if is_mouse_button_pressed(MouseButton::Left)
                 && self.my_turn
                 && let Some((y, x)) = grid.get_tile(current_mouse_pos)
-                && let Err(e) = self.network.make_turn(y, x)
             {
-                eprintln!("Error at make turn [{}]", e);
+                // With several mice the first click picks which one moves
+                if grid.is_mouse((y, x)) {
+                    grid.select_mouse((y, x));
+                } else if let Err(e) = self.network.make_turn(y, x, grid.selected_mouse()) {
+                    eprintln!("Error at make turn [{}]", e);
+                }
+            }
+        }
+
+        if refresh_analysis {
+            self.refresh_analysis();
+        }
+    }
+
+    /// Asks the server for the distance map of the board, only on our turn like the hints.
+    fn refresh_analysis(&mut self) {
+        if !self.analysis || self.analysis_denied || !self.my_turn {
+            return;
+        }
+
+        if let Some(grid) = &mut self.grid {
+            match self.network.request_analysis() {
+                Ok(Some(distances)) => grid.set_distances(&distances),
+                Ok(None) => self.analysis_denied = true,
+                Err(e) => eprintln!("Error at request analysis [{}]", e),
             }
         }
     }
 
     pub async fn update_state(&mut self) -> Result<(), ClientErr> {
+        if let Some(replay) = self.menu.replay.take() {
+            self.menu.visible = false;
+            self.replay = Some(ReplayViewer::new(replay).await);
+        }
+
+        if let Some(reconnect_at) = self.reconnect_at {
+            if get_time() >= reconnect_at {
+                self.reconnect();
+            }
+            return Ok(());
+        }
+
         match self.network.check_for_updates() {
             Ok(Update::StartGame) => {
                 self.network.get_opponent_username()?;
+                self.room_seats = None;
+            }
+            Ok(Update::RoomSeats) => {
+                self.room_seats = Some(self.network.read_room_seats()?);
             }
             Ok(Update::YourTurn) => {
-                self.network.request_tiles(&mut self.grid).await?;
+                self.network.sync_tiles(&mut self.grid).await?;
                 self.my_turn = true;
+                self.hint = None;
+                self.position_copied = false;
+                self.refresh_analysis();
             }
             Ok(Update::WaitTurn) => {
-                self.network.request_tiles(&mut self.grid).await?;
+                self.network.sync_tiles(&mut self.grid).await?;
                 self.my_turn = false;
+                self.hint = None;
+                self.position_copied = false;
+                if let Some(grid) = &mut self.grid {
+                    grid.clear_distances();
+                }
             }
             Ok(Update::GameOver) => {
                 self.network.read_tiles(&mut self.grid).await?;
+                let seed = self.network.read_seed()?;
+                let rating_change = self.network.read_rating_change()?;
 
                 clear_background(Color::from_hex(0x3B4953));
                 self.render();
@@ -130,15 +318,33 @@ impl App {
                     86.0,
                     Color::from_hex(0xF54927),
                 );
+                draw_text(
+                    format!("Seed: {}", seed),
+                    screen_width() / 3.5,
+                    screen_height() / 2.0 + 48.0,
+                    36.0,
+                    Color::from_hex(0xEBF4DD),
+                );
+                if let Some((rating, delta)) = rating_change {
+                    draw_text(
+                        format!("Rating: {} ({:+})", rating, delta),
+                        screen_width() / 3.5,
+                        screen_height() / 2.0 + 88.0,
+                        36.0,
+                        Color::from_hex(0xEBF4DD),
+                    );
+                }
 
                 next_frame().await;
 
                 thread::sleep(time::Duration::from_secs(5));
 
                 self.menu.refresh_rooms(&mut self.network);
-                self.menu.visible = true;
-                self.network.room_id = None;
-                self.grid = None;
+                self.leave_game();
+            }
+            Ok(Update::Disconnected) => {
+                eprintln!("Lost the connection to the server");
+                self.reconnect_at = Some(get_time());
                 self.my_turn = false;
             }
             Ok(Update::None) => {}
@@ -148,8 +354,54 @@ impl App {
         Ok(())
     }
 
+    fn leave_game(&mut self) {
+        self.menu.visible = true;
+        self.network.room_id = None;
+        self.grid = None;
+        self.my_turn = false;
+        self.hint = None;
+        self.hint_denied = false;
+        self.position_copied = false;
+        self.analysis_denied = false;
+    }
+
+    /// A running game resumes once the server gave us our seat back, the next turn update
+    /// brings the board up to date.
+    fn reconnect(&mut self) {
+        match self.network.reconnect() {
+            Ok(true) => {
+                self.reconnect_at = None;
+                self.my_turn = false;
+                self.hint = None;
+            }
+            Ok(false) => {
+                self.reconnect_at = None;
+                self.leave_game();
+                self.menu.connection_reset(&mut self.network);
+            }
+            Err(_) => self.reconnect_at = Some(get_time() + RECONNECT_DELAY),
+        }
+    }
+
     pub fn render(&mut self) {
+        if let Some(viewer) = &self.replay {
+            viewer.render();
+            return;
+        }
+
         self.menu.render();
+        if self.reconnect_at.is_some() {
+            let title = "Connection lost, reconnecting...";
+            let title_width = macroquad::text::measure_text(title, None, 32, 1.0).width;
+            draw_text(
+                title,
+                screen_width() * 0.5 - title_width * 0.5,
+                screen_height() - 24.0,
+                32.0,
+                Color::from_hex(0xF54927),
+            );
+        }
+
         if let Some(grid) = &self.grid {
             grid.render();
 
@@ -168,7 +420,7 @@ impl App {
             );
 
             draw_text(
-                &format!(
+                format!(
                     "{} vs {}",
                     self.menu.username, &self.network.opponent_username
                 ),
@@ -177,11 +429,44 @@ impl App {
                 36.0,
                 Color::from_hex(0xEBF4DD),
             );
+
+            let hint_text = match &self.hint {
+                Some(hint) => format!(
+                    "Escape distance: {}, open routes: {}",
+                    hint.escape_distance
+                        .map_or("none".to_string(), |dist| dist.to_string()),
+                    hint.open_routes
+                ),
+                None if self.position_copied => "Position copied to clipboard".to_string(),
+                None if self.hint_denied => "Hints are disabled in this room".to_string(),
+                None if self.my_turn && !self.analysis_denied => {
+                    "Press H for a hint, C to copy the position, A to toggle the analysis"
+                        .to_string()
+                }
+                None if self.my_turn => "Press H for a hint, C to copy the position".to_string(),
+                None => String::new(),
+            };
+            draw_text(
+                &hint_text,
+                screen_width() * 0.20,
+                screen_height() - 24.0,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
         } else if self.network.room_id.is_some() {
-            let title = "Waiting for players (1/2)";
-            let title_width = macroquad::text::measure_text(title, None, 32, 1.0).width;
+            let title = match self.room_seats {
+                Some((seats, free)) => format!(
+                    "Waiting for players [M {}/{} T {}/{}]",
+                    seats.mice - free.mice,
+                    seats.mice,
+                    seats.trappers - free.trappers,
+                    seats.trappers
+                ),
+                None => "Waiting for players".to_string(),
+            };
+            let title_width = macroquad::text::measure_text(&title, None, 32, 1.0).width;
             let x = macroquad::window::screen_width() * 0.5 - title_width * 0.5;
-            draw_text(title, x, 32.0, 32.0, Color::from_hex(0xEBF4DD));
+            draw_text(&title, x, 32.0, 32.0, Color::from_hex(0xEBF4DD));
         }
     }
 }
@@ -10,10 +10,39 @@ use macroquad::{
 pub enum ButtonType {
     Refresh,
     StartGameBot,
+    NewRoom,
+    Teams,
+    Hints,
     EnterText,
+    EnterPassword,
+    Login,
+    Register,
+    EnterSeed,
+    EnterWidth,
+    EnterHeight,
+    EnterWalls,
+    Shape,
+    Rules,
+    Mice,
+    Daily,
+    PlayDaily,
     LeftSelect,
     RightSelect,
     Room,
+    Games,
+    Game,
+    Leaderboard,
+    Profile,
+    EnterAddress,
+    Connect,
+    RecentServer,
+    LanServer,
+    ReplayBack,
+    ReplayPlay,
+    ReplayForward,
+    ReplaySlower,
+    ReplayFaster,
+    ReplayClose,
 }
 
 pub struct Button {
@@ -0,0 +1,275 @@
+use macroquad::{
+    color::Color,
+    input::{
+        KeyCode, MouseButton, get_char_pressed, is_key_pressed, is_mouse_button_pressed,
+        mouse_position,
+    },
+    prelude::Vec2,
+    text::draw_text,
+    time::get_time,
+};
+
+use crate::button::{Button, ButtonType};
+use crate::discovery::Discovery;
+use crate::network::Network;
+
+const MAX_ADDRESS: usize = 64;
+const SCREEN_X: f32 = 96.0;
+const FIELD_Y: f32 = 224.0;
+const RECENT_Y: f32 = 360.0;
+const ROW_HEIGHT: f32 = 56.0;
+const LAN_X: f32 = SCREEN_X + 472.0;
+/// LAN servers get a line of details under their button.
+const LAN_ROW_HEIGHT: f32 = 76.0;
+const MAX_LAN_SERVERS: usize = 6;
+const MAX_SHOWN_NAME: usize = 14;
+/// Seconds between discovery probes.
+const PROBE_INTERVAL: f64 = 2.0;
+
+/// Shown until the client reaches a server. The address can be typed or picked from the servers
+/// used before and the ones found on the local network.
+pub struct ConnectScreen {
+    address: String,
+    error: Option<String>,
+    buttons: [Button; 2],
+    recent: Vec<Button>,
+    discovery: Option<Discovery>,
+    /// One button per discovered server, in the order of the discovery list.
+    lan: Vec<Button>,
+    probed_at: Option<f64>,
+}
+
+impl ConnectScreen {
+    pub fn new(address: &str, recent_servers: &[String]) -> Self {
+        let buttons = [
+            Button::new(
+                ButtonType::EnterAddress,
+                Vec2::new(SCREEN_X, FIELD_Y),
+                Vec2::new(480.0, 48.0),
+                address.to_string(),
+                Color::from_hex(0xB07F23),
+                false,
+            ),
+            Button::new(
+                ButtonType::Connect,
+                Vec2::new(SCREEN_X + 496.0, FIELD_Y),
+                Vec2::new(160.0, 48.0),
+                "Connect".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+        ];
+
+        let recent = recent_servers
+            .iter()
+            .enumerate()
+            .map(|(idx, server)| {
+                Button::new(
+                    ButtonType::RecentServer,
+                    Vec2::new(SCREEN_X, RECENT_Y + ROW_HEIGHT * idx as f32),
+                    Vec2::new(440.0, 48.0),
+                    server.clone(),
+                    Color::from_hex(0x5A7863),
+                    false,
+                )
+            })
+            .collect();
+
+        // Without a socket the screen simply lists no LAN servers
+        let discovery = match Discovery::new() {
+            Ok(discovery) => Some(discovery),
+            Err(e) => {
+                eprintln!("Could not search the local network ({})", e);
+                None
+            }
+        };
+
+        Self {
+            address: address.to_string(),
+            error: None,
+            buttons,
+            recent,
+            discovery,
+            lan: Vec::new(),
+            probed_at: None,
+        }
+    }
+
+    fn update_discovery(&mut self) {
+        let Some(discovery) = &mut self.discovery else {
+            return;
+        };
+
+        let now = get_time();
+        if self.probed_at.is_none_or(|at| now - at >= PROBE_INTERVAL) {
+            if let Err(e) = discovery.probe() {
+                eprintln!("Could not probe the local network ({})", e);
+            }
+            self.probed_at = Some(now);
+        }
+
+        if discovery.poll(now) {
+            self.lan = discovery
+                .servers
+                .iter()
+                .take(MAX_LAN_SERVERS)
+                .enumerate()
+                .map(|(idx, server)| {
+                    let name: String = server.name.chars().take(MAX_SHOWN_NAME).collect();
+                    Button::new(
+                        ButtonType::LanServer,
+                        Vec2::new(LAN_X, RECENT_Y + LAN_ROW_HEIGHT * idx as f32),
+                        Vec2::new(440.0, 48.0),
+                        format!("{} ({} online)", name, server.online),
+                        Color::from_hex(0x5A7863),
+                        false,
+                    )
+                })
+                .collect();
+        }
+    }
+
+    /// Connects to the address, a failure is shown on the screen.
+    pub fn connect(&mut self, address: &str) -> Option<Network> {
+        match Network::new(address) {
+            Ok(network) => Some(network),
+            Err(e) => {
+                self.error = Some(format!("Could not connect to {} ({})", address, e));
+                None
+            }
+        }
+    }
+
+    /// Returns the connection once the player reached a server.
+    pub fn update(&mut self) -> Option<Network> {
+        self.update_discovery();
+
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && !c.is_whitespace() && self.address.chars().count() < MAX_ADDRESS {
+                self.address.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.address.pop();
+        }
+        self.buttons[0].text = self.address.clone();
+
+        let mouse_pos: Vec2 = mouse_position().into();
+        for button in self
+            .buttons
+            .iter_mut()
+            .chain(self.recent.iter_mut())
+            .chain(self.lan.iter_mut())
+        {
+            button.highlighted = button.is_inside(mouse_pos);
+        }
+
+        if is_key_pressed(KeyCode::Enter) {
+            let address = self.address.clone();
+            return self.connect(&address);
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if self.buttons[1].is_inside(mouse_pos) {
+                let address = self.address.clone();
+                return self.connect(&address);
+            }
+
+            if let Some(server) = self.recent.iter().find(|b| b.is_inside(mouse_pos)) {
+                self.address = server.text.clone();
+                let address = self.address.clone();
+                return self.connect(&address);
+            }
+
+            let lan_address = self
+                .lan
+                .iter()
+                .position(|b| b.is_inside(mouse_pos))
+                .zip(self.discovery.as_ref())
+                .and_then(|(idx, discovery)| discovery.servers.get(idx))
+                .map(|server| server.address.clone());
+            if let Some(address) = lan_address {
+                self.address = address.clone();
+                return self.connect(&address);
+            }
+        }
+
+        None
+    }
+
+    pub fn render(&self) {
+        draw_text(
+            "Trap the mouse",
+            SCREEN_X,
+            128.0,
+            64.0,
+            Color::from_hex(0xEBF4DD),
+        );
+        draw_text(
+            "Server address:",
+            SCREEN_X,
+            FIELD_Y - 16.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+
+        for button in &self.buttons {
+            button.render();
+        }
+
+        if let Some(error) = &self.error {
+            draw_text(
+                error,
+                SCREEN_X,
+                FIELD_Y + 88.0,
+                28.0,
+                Color::from_hex(0xF54927),
+            );
+        }
+
+        if !self.recent.is_empty() {
+            draw_text(
+                "Recent servers:",
+                SCREEN_X,
+                RECENT_Y - 16.0,
+                32.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+        for button in &self.recent {
+            button.render();
+        }
+
+        draw_text(
+            "Local network:",
+            LAN_X,
+            RECENT_Y - 16.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+        if self.lan.is_empty() {
+            draw_text(
+                "Searching...",
+                LAN_X,
+                RECENT_Y + 32.0,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+
+        let servers = self.discovery.iter().flat_map(|d| d.servers.iter());
+        for (idx, (button, server)) in self.lan.iter().zip(servers).enumerate() {
+            button.render();
+            draw_text(
+                format!(
+                    "{}  v{}  {} playing",
+                    server.address, server.version, server.playing
+                ),
+                LAN_X,
+                RECENT_Y + LAN_ROW_HEIGHT * idx as f32 + 66.0,
+                20.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+    }
+}
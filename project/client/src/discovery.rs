@@ -0,0 +1,121 @@
+use hexgrid::discovery::{ANNOUNCE, DISCOVERY_PORT, MAGIC, MAX_SERVER_NAME, PROBE, PROBE_SIZE};
+use std::{
+    io::{self, Read},
+    net::{SocketAddr, UdpSocket},
+};
+
+/// Seconds a server stays listed after its last answer.
+const SERVER_TIMEOUT: f64 = 6.0;
+
+/// A server that answered a probe on the local network.
+pub struct LanServer {
+    pub address: String,
+    pub name: String,
+    pub version: String,
+    pub online: u32,
+    pub playing: u32,
+    seen: f64,
+}
+
+pub struct Discovery {
+    socket: UdpSocket,
+    pub servers: Vec<LanServer>,
+}
+
+impl Discovery {
+    pub fn new() -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            servers: Vec::new(),
+        })
+    }
+
+    pub fn probe(&self) -> io::Result<()> {
+        let mut data = MAGIC.to_vec();
+        data.push(PROBE);
+        data.resize(PROBE_SIZE, 0);
+        self.socket
+            .send_to(&data, ("255.255.255.255", DISCOVERY_PORT))?;
+        Ok(())
+    }
+
+    /// Takes in the answers that arrived and forgets servers that went quiet, returns whether
+    /// the list changed.
+    pub fn poll(&mut self, now: f64) -> bool {
+        let mut changed = false;
+        let mut buf = [0u8; 512];
+
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+            let Some(server) = read_announcement(&buf[..len], from, now) else {
+                continue;
+            };
+
+            match self
+                .servers
+                .iter_mut()
+                .find(|known| known.address == server.address)
+            {
+                Some(known) => {
+                    changed |= known.name != server.name
+                        || known.online != server.online
+                        || known.playing != server.playing;
+                    *known = server;
+                }
+                None => {
+                    self.servers.push(server);
+                    changed = true;
+                }
+            }
+        }
+
+        let count = self.servers.len();
+        self.servers
+            .retain(|server| now - server.seen < SERVER_TIMEOUT);
+        changed || count != self.servers.len()
+    }
+}
+
+fn read_announcement(mut data: &[u8], from: SocketAddr, now: f64) -> Option<LanServer> {
+    let mut header = [0u8; 5];
+    data.read_exact(&mut header).ok()?;
+    if !header.starts_with(MAGIC) || header[4] != ANNOUNCE {
+        return None;
+    }
+
+    let mut portb = [0u8; 2];
+    data.read_exact(&mut portb).ok()?;
+    let name = read_text(&mut data)?;
+    let version = read_text(&mut data)?;
+    let mut countb = [0u8; 4];
+    data.read_exact(&mut countb).ok()?;
+    let online = u32::from_le_bytes(countb);
+    data.read_exact(&mut countb).ok()?;
+    let playing = u32::from_le_bytes(countb);
+
+    // The game port can differ from the discovery port, the address is where the answer came from
+    Some(LanServer {
+        address: SocketAddr::new(from.ip(), u16::from_le_bytes(portb)).to_string(),
+        name,
+        version,
+        online,
+        playing,
+        seen: now,
+    })
+}
+
+fn read_text(data: &mut &[u8]) -> Option<String> {
+    let mut lenb = [0u8; 4];
+    data.read_exact(&mut lenb).ok()?;
+    let len = u32::from_le_bytes(lenb) as usize;
+    if len > MAX_SERVER_NAME {
+        return None;
+    }
+
+    let mut text = vec![0u8; len];
+    data.read_exact(&mut text).ok()?;
+    String::from_utf8(text).ok()
+}
@@ -1,20 +1,26 @@
+use hexgrid::{offset_neighbours, offset_to_pixel, pixel_to_offset, zobrist_hash};
 use macroquad::{
     color::Color,
     prelude::Vec2,
     shapes::draw_poly,
+    text::{draw_text, measure_text},
     texture::{DrawTextureParams, Texture2D, draw_texture_ex, load_texture},
     window::{screen_height, screen_width},
 };
 
 const DEFAULT_HEX_RADIUS: f32 = 32.0;
 const HEX_OUTLINE_THINKNESS: f32 = 6.0;
+const GRID_MARGIN: f32 = 64.0;
+/// Below this hex radius the distance numbers are no longer readable.
+const MIN_LABEL_RADIUS: f32 = 12.0;
 
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq)]
-enum Entity {
+pub enum Entity {
     None,
     Mouse,
     Wall,
+    Void,
 }
 impl From<u8> for Entity {
     fn from(value: u8) -> Self {
@@ -22,6 +28,7 @@ impl From<u8> for Entity {
             0 => Entity::None,
             1 => Entity::Mouse,
             2 => Entity::Wall,
+            3 => Entity::Void,
             _ => Entity::None,
         }
     }
@@ -31,6 +38,10 @@ struct Tile {
     pos: Vec2,
     color: Color,
     highlight: bool,
+    suggested: bool,
+    selected: bool,
+    escape: bool,
+    distance: Option<u32>,
     holder: Entity,
 }
 
@@ -40,6 +51,10 @@ impl Tile {
             pos,
             color,
             highlight: false,
+            suggested: false,
+            selected: false,
+            escape: false,
+            distance: None,
             holder: Entity::None,
         }
     }
@@ -48,46 +63,79 @@ impl Tile {
         self.holder = entity;
     }
 
-    fn get_pos(&self) -> Vec2 {
-        self.pos
-    }
-
     fn toggle_highlight(&mut self, value: bool) {
         self.highlight = value;
     }
 
-    fn render(&self, offset: Vec2, mouse_tex: &Option<Texture2D>) {
+    fn render(&self, offset: Vec2, radius: f32, mouse_tex: &Option<Texture2D>) {
+        // Cells outside of the board shape are not drawn at all
+        if self.holder == Entity::Void {
+            return;
+        }
+
         let pos = self.pos + offset;
+        let scale = radius / DEFAULT_HEX_RADIUS;
         let darker = Color::new(
             self.color.r - 0.1,
             self.color.g - 0.1,
             self.color.b - 0.1,
             1.0,
         );
-        draw_poly(pos.x, pos.y, 6, DEFAULT_HEX_RADIUS, 90.0, darker);
+        draw_poly(pos.x, pos.y, 6, radius, 90.0, darker);
         draw_poly(
             pos.x,
             pos.y,
             6,
-            DEFAULT_HEX_RADIUS - HEX_OUTLINE_THINKNESS,
+            radius - HEX_OUTLINE_THINKNESS * scale,
             90.0,
             if self.highlight {
                 Color::new(1.0, 0.5, 0.5, 1.0)
+            } else if self.selected {
+                Color::from_hex(0xF2C94C)
+            } else if self.suggested {
+                Color::from_hex(0x8FD3FF)
+            } else if self.escape {
+                Color::from_hex(0x6FCF97)
+            } else if let Some(dist) = self.distance {
+                // Tiles close to the edge glow red
+                let heat = 0.6 / (dist as f32 + 1.0);
+                Color::new(
+                    self.color.r + (0.95 - self.color.r) * heat,
+                    self.color.g * (1.0 - heat),
+                    self.color.b * (1.0 - heat),
+                    1.0,
+                )
             } else {
                 self.color
             },
         );
 
+        if let Some(dist) = self.distance
+            && self.holder == Entity::None
+            && radius >= MIN_LABEL_RADIUS
+        {
+            let label = dist.to_string();
+            let size = radius * 0.9;
+            let dims = measure_text(&label, None, size as u16, 1.0);
+            draw_text(
+                &label,
+                pos.x - dims.width / 2.0,
+                pos.y + dims.offset_y / 2.0,
+                size,
+                Color::from_hex(0x3B4953),
+            );
+        }
+
         match self.holder {
             Entity::Mouse => {
                 if let Some(tex) = mouse_tex {
                     draw_texture_ex(
                         tex,
-                        pos.x - 32.0,
-                        pos.y - 32.0,
+                        pos.x - radius,
+                        pos.y - radius,
                         Color::from_hex(0xFFFFFF),
                         DrawTextureParams {
-                            dest_size: Some(Vec2::new(64.0, 64.0)),
+                            dest_size: Some(Vec2::new(2.0 * radius, 2.0 * radius)),
                             ..Default::default()
                         },
                     );
@@ -96,10 +144,10 @@ impl Tile {
             Entity::Wall => {
                 let color = Color::from_hex(0x964B00);
                 let darker = Color::new(color.r - 0.1, color.g - 0.1, color.b - 0.1, 1.0);
-                draw_poly(pos.x, pos.y, 6, 24.0, 90.0, darker);
-                draw_poly(pos.x, pos.y, 6, 12.0, 90.0, color);
+                draw_poly(pos.x, pos.y, 6, 24.0 * scale, 90.0, darker);
+                draw_poly(pos.x, pos.y, 6, 12.0 * scale, 90.0, color);
             }
-            Entity::None => {}
+            Entity::None | Entity::Void => {}
         }
     }
 }
@@ -109,18 +157,14 @@ pub struct Grid {
     height: usize,
     tiles: Vec<Vec<Tile>>,
     center: Vec2,
+    radius: f32,
     highlighted: Option<(usize, usize)>,
+    selected: Option<(usize, usize)>,
     mouse_tex: Option<Texture2D>,
 }
 
 impl Grid {
     pub fn new(width: usize, height: usize) -> Self {
-        let hex_width = DEFAULT_HEX_RADIUS * 3.0_f32.sqrt();
-        let hex_height = DEFAULT_HEX_RADIUS * 1.5;
-
-        let center = Vec2::from((screen_width(), screen_height())) / 2.0
-            - Self::get_grid_size(width, height) / 2.0;
-
         let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
         let tiles: Vec<Vec<Tile>> = (0..height)
             .map(|i| {
@@ -129,78 +173,65 @@ impl Grid {
                         let diff = ((cx - j as f32).abs() + (cy - i as f32).abs()) / 16.0;
                         let base_color = Color::new(0.8 - diff, 0.8, 0.8 - diff, 1.0);
 
-                        Tile::new(
-                            Vec2::new(
-                                i as f32 * hex_width
-                                    + if j % 2 == 1 { hex_width / 2.0 } else { 0.0 },
-                                j as f32 * hex_height,
-                            ),
-                            base_color,
-                        )
+                        Tile::new(Vec2::ZERO, base_color)
                     })
                     .collect()
             })
             .collect();
 
-        Self {
+        let mut grid = Self {
             width,
             height,
-            center,
+            center: Vec2::ZERO,
+            radius: DEFAULT_HEX_RADIUS,
             highlighted: None,
+            selected: None,
             tiles,
             mouse_tex: None,
-        }
+        };
+        grid.center();
+
+        grid
     }
 
     pub async fn load_textures(&mut self) {
         self.mouse_tex = load_texture("assets/mouse.png").await.ok();
     }
 
+    /// Shrinks the hexes until the whole board fits the window, then centers it.
     pub fn center(&mut self) {
-        self.center = Vec2::from((screen_width(), screen_height())) / 2.0
-            - Self::get_grid_size(self.width, self.height) / 2.0;
+        let available = Vec2::from((screen_width(), screen_height())) - 2.0 * GRID_MARGIN;
+        self.radius = DEFAULT_HEX_RADIUS
+            .min(available.x / (3.0_f32.sqrt() * (self.height as f32 + 0.5)))
+            .min(available.y / (1.5 * (self.width as f32 - 1.0) + 2.0))
+            .max(1.0);
+
+        for (i, line) in self.tiles.iter_mut().enumerate() {
+            for (j, tile) in line.iter_mut().enumerate() {
+                tile.pos = offset_to_pixel(i, j, self.radius).into();
+            }
+        }
+
+        self.center =
+            Vec2::from((screen_width(), screen_height())) / 2.0 - self.get_grid_size() / 2.0;
     }
 
-    fn get_grid_size(width: usize, height: usize) -> Vec2 {
-        let hex_width = DEFAULT_HEX_RADIUS * 3.0_f32.sqrt();
-        let hex_height = DEFAULT_HEX_RADIUS * 1.5;
+    /// Board rows are laid out left to right, so the screen width holds `height` hexes.
+    fn get_grid_size(&self) -> Vec2 {
+        let hex_width = self.radius * 3.0_f32.sqrt();
+        let hex_height = self.radius * 1.5;
 
         Vec2::new(
-            hex_width * width as f32 - hex_width * 0.5,
-            hex_height * (height - 1) as f32,
+            hex_width * self.height as f32 - hex_width * 0.5,
+            hex_height * (self.width - 1) as f32,
         )
     }
 
     pub fn get_tile(&self, mut pos: Vec2) -> Option<(usize, usize)> {
         pos -= self.center;
 
-        let hex_width = DEFAULT_HEX_RADIUS * 3.0_f32.sqrt();
-        let hex_height = DEFAULT_HEX_RADIUS * 1.5;
-
-        let j = (pos.y / hex_height).round() as i32;
-        if j < 0 || j as usize >= self.height {
-            return None;
-        }
-
-        let offset = if j % 2 == 1 { hex_width * 0.5 } else { 0.0 };
-        let i = ((pos.x - offset) / hex_width).round() as i32;
-        if i < 0 || i as usize >= self.width {
-            return None;
-        }
-
-        let i = i as usize;
-        let j = j as usize;
-
-        let pos = pos - self.tiles[i][j].get_pos();
-        if pos.x.abs() > hex_width / 2.0 {
-            return None;
-        }
-
-        if pos.y.abs() > DEFAULT_HEX_RADIUS {
-            return None;
-        }
-
-        if pos.y.abs() * (hex_width / 2.0 / DEFAULT_HEX_RADIUS) + pos.x.abs() > hex_width / 2.0 {
+        let (i, j) = pixel_to_offset(pos.x, pos.y, self.radius)?;
+        if i >= self.height || j >= self.width || self.tiles[i][j].holder == Entity::Void {
             return None;
         }
 
@@ -222,14 +253,144 @@ impl Grid {
         }
     }
 
+    pub fn suggest(&mut self, (i, j): (usize, usize)) {
+        if i < self.tiles.len() && j < self.tiles[i].len() {
+            self.tiles[i][j].suggested = true;
+        }
+    }
+
+    pub fn is_mouse(&self, (i, j): (usize, usize)) -> bool {
+        self.tiles[i][j].holder == Entity::Mouse
+    }
+
+    /// Marks the mouse that moves with the next click, clicking it again clears the choice.
+    pub fn select_mouse(&mut self, (i, j): (usize, usize)) {
+        if let Some((si, sj)) = self.selected.take() {
+            self.tiles[si][sj].selected = false;
+            if (si, sj) == (i, j) {
+                return;
+            }
+        }
+
+        self.tiles[i][j].selected = true;
+        self.selected = Some((i, j));
+    }
+
+    pub fn selected_mouse(&self) -> Option<(usize, usize)> {
+        self.selected
+    }
+
     pub fn place_entity(&mut self, y: usize, x: usize, entity: u8) {
         self.tiles[y][x].set_holder(Entity::from(entity));
     }
 
+    fn holder(&self, (i, j): (usize, usize)) -> Option<Entity> {
+        self.tiles.get(i)?.get(j).map(|tile| tile.holder)
+    }
+
+    /// Plays a wall sent by the server, false when it does not fit the local board.
+    pub fn apply_wall(&mut self, (i, j): (usize, usize)) -> bool {
+        if self.holder((i, j)) != Some(Entity::None) {
+            return false;
+        }
+
+        self.tiles[i][j].set_holder(Entity::Wall);
+        true
+    }
+
+    /// Plays a mouse move sent by the server, false when it does not fit the local board.
+    pub fn apply_mouse(&mut self, from: (usize, usize), to: (usize, usize)) -> bool {
+        if self.holder(from) != Some(Entity::Mouse) || self.holder(to) != Some(Entity::None) {
+            return false;
+        }
+
+        self.tiles[from.0][from.1].set_holder(Entity::None);
+        self.tiles[to.0][to.1].set_holder(Entity::Mouse);
+        true
+    }
+
+    /// Shows how many moves each tile is from the edge and the shortest ways out of every mouse.
+    pub fn set_distances(&mut self, distances: &[Vec<Option<u32>>]) {
+        for (line, dists) in self.tiles.iter_mut().zip(distances) {
+            for (tile, &dist) in line.iter_mut().zip(dists) {
+                tile.distance = dist;
+                tile.escape = false;
+            }
+        }
+
+        let mice: Vec<(usize, usize)> = (0..self.height)
+            .flat_map(|i| (0..self.width).map(move |j| (i, j)))
+            .filter(|&pos| self.is_mouse(pos))
+            .collect();
+
+        for mouse in mice {
+            // Walk downhill from the closest neighbours, every step losing one move
+            let first = self.neighbours(mouse);
+            let Some(best) = first
+                .iter()
+                .filter_map(|&(i, j)| self.tiles[i][j].distance)
+                .min()
+            else {
+                continue;
+            };
+
+            let mut frontier: Vec<(usize, usize)> = first
+                .into_iter()
+                .filter(|&(i, j)| self.tiles[i][j].distance == Some(best))
+                .collect();
+
+            while let Some((i, j)) = frontier.pop() {
+                if self.tiles[i][j].escape {
+                    continue;
+                }
+                self.tiles[i][j].escape = true;
+
+                if let Some(dist) = self.tiles[i][j].distance.filter(|&dist| dist > 0) {
+                    frontier.extend(
+                        self.neighbours((i, j))
+                            .into_iter()
+                            .filter(|&(ni, nj)| self.tiles[ni][nj].distance == Some(dist - 1)),
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn clear_distances(&mut self) {
+        for tile in self.tiles.iter_mut().flatten() {
+            tile.distance = None;
+            tile.escape = false;
+        }
+    }
+
+    fn neighbours(&self, (i, j): (usize, usize)) -> Vec<(usize, usize)> {
+        offset_neighbours(i as i32, j as i32)
+            .into_iter()
+            .filter(|&(ni, nj)| {
+                ni >= 0 && nj >= 0 && (ni as usize) < self.height && (nj as usize) < self.width
+            })
+            .map(|(ni, nj)| (ni as usize, nj as usize))
+            .collect()
+    }
+
+    /// Drops the hint and the chosen mouse of the previous turn.
+    pub fn new_turn(&mut self) {
+        for tile in self.tiles.iter_mut().flatten() {
+            tile.suggested = false;
+            tile.selected = false;
+        }
+        self.selected = None;
+    }
+
+    /// Same hash as the server keeps for its board.
+    pub fn zobrist(&self) -> u64 {
+        zobrist_hash(self.tiles.iter().flatten().map(|tile| tile.holder as u8))
+    }
+
     pub fn render(&self) {
         for line in self.tiles.iter() {
             for tile in line {
-                tile.render(self.center, &self.mouse_tex);
+                tile.render(self.center, self.radius, &self.mouse_tex);
             }
         }
     }
@@ -1,17 +1,27 @@
 mod app;
 mod button;
+mod connect;
+mod discovery;
 mod grid;
 mod menu;
 mod network;
 mod notification;
+mod replay;
+mod settings;
 
 use crate::app::App;
+use crate::connect::ConnectScreen;
+use crate::replay::Replay;
+use crate::settings::Settings;
 use macroquad::prelude::*;
 
 fn get_conf() -> Conf {
+    // The rest of the settings is loaded by the app, the window has to exist first
+    let settings = Settings::load();
+
     Conf {
-        window_width: 1048,
-        window_height: 840,
+        window_width: settings.window_width as i32,
+        window_height: settings.window_height as i32,
         window_title: "Trap the Mouse".to_owned(),
         ..Default::default()
     }
@@ -21,32 +31,50 @@ fn get_conf() -> Conf {
 async fn main() {
     rand::srand(macroquad::miniquad::date::now() as _);
 
-    match App::new() {
-        Ok(mut app) => {
-            app.menu.load_textures().await;
+    let mut settings = Settings::load();
+    let mut screen = ConnectScreen::new(&settings.server, &settings.recent_servers);
+
+    // The last server is tried right away, the connection screen only shows when it is down
+    let mut connection = screen.connect(&settings.server);
+    let network = loop {
+        if let Some(network) = connection.take() {
+            break network;
+        }
+
+        clear_background(Color::from_hex(0x3B4953));
+        connection = screen.update();
+        screen.render();
+        next_frame().await
+    };
 
-            loop {
-                clear_background(Color::from_hex(0x3B4953));
-                app.check_resize();
-                if let Err(e) = app.update_state().await {
-                    eprintln!("Error [{}]", e);
-                }
-                app.handle_input();
+    settings.remember_server(network.address());
+    if let Err(e) = settings.save() {
+        eprintln!("Could not save the settings [{}]", e);
+    }
+
+    let mut app = App::new(network, settings);
+    app.menu.load_textures().await;
 
-                app.render();
+    // A replay file given on the command line opens right away
+    if let Some(path) = std::env::args().nth(1) {
+        match Replay::load(&path) {
+            Ok(replay) => app.menu.replay = Some(replay),
+            Err(e) => eprintln!("Could not load the replay {} ({})", path, e),
+        }
+    }
 
-                draw_text(
-                    (get_fps().to_string() + " fps").as_str(),
-                    16.0,
-                    32.0,
-                    32.0,
-                    WHITE,
-                );
-                next_frame().await
-            }
+    loop {
+        clear_background(Color::from_hex(0x3B4953));
+        app.check_resize();
+        if let Err(e) = app.update_state().await {
+            eprintln!("Error [{}]", e);
         }
-        Err(e) => loop {
-            clear_background(Color::from_hex(0x3B4953));
+        app.handle_input();
+        app.save_settings();
+
+        app.render();
+
+        if app.show_fps() {
             draw_text(
                 (get_fps().to_string() + " fps").as_str(),
                 16.0,
@@ -54,14 +82,7 @@ async fn main() {
                 32.0,
                 WHITE,
             );
-            draw_text(
-                format!("Could not connect to server ({})", e).as_str(),
-                16.0,
-                screen_height() * 0.5,
-                24.0,
-                WHITE,
-            );
-            next_frame().await
-        },
+        }
+        next_frame().await
     }
 }
@@ -12,17 +12,80 @@ use macroquad::{
 };
 
 use crate::button::{Button, ButtonType};
-use crate::network::Network;
+use crate::network::{
+    BoardSettings, BoardShape, Leaderboard, Network, Profile, Ratings, Rules, Seats,
+};
 use crate::notification::NotificaitonsManager;
+use crate::replay::{REPLAY_DIR, Replay};
+use crate::settings::Settings;
 
 const MENU_OFFSET: f32 = 64.0;
 const START_ROOMS_Y: f32 = 286.0;
 const ROOM_HEIGHT: f32 = 64.0;
 
 const MAX_USERNAME: usize = 10;
+const MAX_PASSWORD: usize = 32;
+const MAX_SEED: usize = 19;
+const MAX_BOARD_DIGITS: usize = 3;
+const MAX_MICE: u8 = 4;
+
+/// Mice against trappers.
+const TEAM_MODES: [Seats; 4] = [
+    Seats {
+        mice: 1,
+        trappers: 1,
+    },
+    Seats {
+        mice: 2,
+        trappers: 2,
+    },
+    Seats {
+        mice: 1,
+        trappers: 2,
+    },
+    Seats {
+        mice: 2,
+        trappers: 1,
+    },
+];
+
+const RULE_PRESETS: [(&str, Rules); 4] = [
+    (
+        "Standard",
+        Rules {
+            walls_per_turn: 1,
+            mouse_steps: 1,
+            no_wall_near_mouse: false,
+        },
+    ),
+    (
+        "Two walls",
+        Rules {
+            walls_per_turn: 2,
+            mouse_steps: 1,
+            no_wall_near_mouse: false,
+        },
+    ),
+    (
+        "Fast mouse",
+        Rules {
+            walls_per_turn: 1,
+            mouse_steps: 2,
+            no_wall_near_mouse: false,
+        },
+    ),
+    (
+        "Keep away",
+        Rules {
+            walls_per_turn: 1,
+            mouse_steps: 1,
+            no_wall_near_mouse: true,
+        },
+    ),
+];
 
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum PlayerType {
     Mouse,
     Wall,
@@ -33,13 +96,45 @@ struct Room {
     button: Button,
 }
 
+struct ArchivedGame {
+    id: [u8; 16],
+    button: Button,
+}
+
+struct Daily {
+    day: u64,
+    entries: Vec<(String, u32)>,
+    play_button: Button,
+}
+
 pub struct Menu {
     pub visible: bool,
     pub username: String,
-    writing_mode: bool,
+    password: String,
+    /// Set once logged in, the username can no longer be edited.
+    account: Option<String>,
+    ratings: Ratings,
+    writing: Option<ButtonType>,
+    seed: String,
+    board_width: String,
+    board_height: String,
+    board_walls: String,
+    shapes: Vec<BoardShape>,
+    shape: usize,
+    rules: usize,
+    mice: u8,
+    teams: usize,
+    /// Whether rooms we create allow hints.
+    hints: bool,
     player_type: PlayerType,
     rooms: Vec<Room>,
-    buttons: [Button; 5],
+    daily: Option<Daily>,
+    games: Option<Vec<ArchivedGame>>,
+    leaderboard: Option<Leaderboard>,
+    profile: Option<Profile>,
+    /// Replay picked in the menu, the app opens it.
+    pub replay: Option<Replay>,
+    buttons: [Button; 22],
     mouse_tex: Option<Texture2D>,
     notifications: NotificaitonsManager,
 }
@@ -63,6 +158,30 @@ impl Menu {
                 Color::from_hex(0xB07F23),
                 true,
             ),
+            Button::new(
+                ButtonType::NewRoom,
+                Vec2::new(-10.0 * MENU_OFFSET - 12.0, START_ROOMS_Y - 64.0 + 8.0),
+                Vec2::new(164.0, ROOM_HEIGHT - 32.0),
+                "New Room".to_string(),
+                Color::from_hex(0xB07F23),
+                true,
+            ),
+            Button::new(
+                ButtonType::Teams,
+                Vec2::new(-11.75 * MENU_OFFSET - 12.0, START_ROOMS_Y - 64.0 + 8.0),
+                Vec2::new(92.0, ROOM_HEIGHT - 32.0),
+                "1v1".to_string(),
+                Color::from_hex(0x6498D99),
+                true,
+            ),
+            Button::new(
+                ButtonType::Hints,
+                Vec2::new(-13.75 * MENU_OFFSET - 12.0, START_ROOMS_Y - 64.0 + 8.0),
+                Vec2::new(120.0, ROOM_HEIGHT - 32.0),
+                "No hints".to_string(),
+                Color::from_hex(0x6498D99),
+                true,
+            ),
             Button::new(
                 ButtonType::EnterText,
                 Vec2::new(MENU_OFFSET + 32.0, START_ROOMS_Y - 128.0),
@@ -71,6 +190,110 @@ impl Menu {
                 Color::from_hex(0xB07F23),
                 false,
             ),
+            Button::new(
+                ButtonType::EnterPassword,
+                Vec2::new(MENU_OFFSET + 344.0, START_ROOMS_Y - 128.0),
+                Vec2::new(200.0, ROOM_HEIGHT - 32.0),
+                String::new(),
+                Color::from_hex(0xB07F23),
+                false,
+            ),
+            Button::new(
+                ButtonType::Login,
+                Vec2::new(MENU_OFFSET + 556.0, START_ROOMS_Y - 128.0),
+                Vec2::new(112.0, ROOM_HEIGHT - 32.0),
+                "Login".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::Register,
+                Vec2::new(MENU_OFFSET + 680.0, START_ROOMS_Y - 128.0),
+                Vec2::new(136.0, ROOM_HEIGHT - 32.0),
+                "Register".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::EnterWidth,
+                Vec2::new(MENU_OFFSET + 32.0, START_ROOMS_Y + 244.0),
+                Vec2::new(64.0, ROOM_HEIGHT - 32.0),
+                "11".to_string(),
+                Color::from_hex(0xB07F23),
+                false,
+            ),
+            Button::new(
+                ButtonType::EnterHeight,
+                Vec2::new(MENU_OFFSET + 128.0, START_ROOMS_Y + 244.0),
+                Vec2::new(64.0, ROOM_HEIGHT - 32.0),
+                "11".to_string(),
+                Color::from_hex(0xB07F23),
+                false,
+            ),
+            Button::new(
+                ButtonType::EnterWalls,
+                Vec2::new(MENU_OFFSET + 300.0, START_ROOMS_Y + 244.0),
+                Vec2::new(64.0, ROOM_HEIGHT - 32.0),
+                "5".to_string(),
+                Color::from_hex(0xB07F23),
+                false,
+            ),
+            Button::new(
+                ButtonType::EnterSeed,
+                Vec2::new(MENU_OFFSET + 32.0, START_ROOMS_Y + 322.0),
+                Vec2::new(300.0, ROOM_HEIGHT - 32.0),
+                String::new(),
+                Color::from_hex(0xB07F23),
+                false,
+            ),
+            Button::new(
+                ButtonType::Shape,
+                Vec2::new(MENU_OFFSET + 32.0, START_ROOMS_Y + 434.0),
+                Vec2::new(180.0, ROOM_HEIGHT - 32.0),
+                "Rectangle".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::Rules,
+                Vec2::new(MENU_OFFSET + 224.0, START_ROOMS_Y + 434.0),
+                Vec2::new(180.0, ROOM_HEIGHT - 32.0),
+                RULE_PRESETS[0].0.to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::Daily,
+                Vec2::new(MENU_OFFSET + 32.0, START_ROOMS_Y + 378.0),
+                Vec2::new(180.0, ROOM_HEIGHT - 32.0),
+                "Daily".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::Games,
+                Vec2::new(MENU_OFFSET + 224.0, START_ROOMS_Y + 378.0),
+                Vec2::new(180.0, ROOM_HEIGHT - 32.0),
+                "My Games".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::Leaderboard,
+                Vec2::new(MENU_OFFSET + 32.0, START_ROOMS_Y + 490.0),
+                Vec2::new(180.0, ROOM_HEIGHT - 32.0),
+                "Rankings".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
+            Button::new(
+                ButtonType::Profile,
+                Vec2::new(MENU_OFFSET + 224.0, START_ROOMS_Y + 490.0),
+                Vec2::new(180.0, ROOM_HEIGHT - 32.0),
+                "Profile".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
             Button::new(
                 ButtonType::LeftSelect,
                 Vec2::new(MENU_OFFSET + 8.0, START_ROOMS_Y + 128.0),
@@ -87,40 +310,116 @@ impl Menu {
                 Color::from_hex(0x6498D99),
                 false,
             ),
+            Button::new(
+                ButtonType::Mice,
+                Vec2::new(MENU_OFFSET + 232.0, START_ROOMS_Y + 128.0),
+                Vec2::new(160.0, 56.0),
+                "1 mouse".to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            ),
         ];
 
         Self {
             visible: true,
-            writing_mode: false,
+            writing: None,
+            seed: String::new(),
+            board_width: String::from("11"),
+            board_height: String::from("11"),
+            board_walls: String::from("5"),
+            shapes: vec![BoardShape::Rectangle, BoardShape::Hexagon],
+            shape: 0,
+            rules: 0,
+            mice: 1,
+            teams: 0,
+            hints: false,
             username: String::from("Guest"),
+            password: String::new(),
+            account: None,
             player_type: PlayerType::Mouse,
             rooms: Vec::new(),
+            ratings: Ratings { mouse: 0, wall: 0 },
+            daily: None,
+            games: None,
+            leaderboard: None,
+            profile: None,
+            replay: None,
             buttons,
             mouse_tex: None,
             notifications: NotificaitonsManager::new(),
         }
     }
 
+    pub fn player_type(&self) -> PlayerType {
+        self.player_type
+    }
+
+    /// Restores the name and side of the last session.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.username = settings.username.chars().take(MAX_USERNAME).collect();
+        self.player_type = settings.player_type;
+
+        for b in self.buttons.iter_mut() {
+            if b.button_type == ButtonType::EnterText {
+                b.text = self.username.clone();
+            }
+        }
+    }
+
     pub async fn load_textures(&mut self) {
         self.mouse_tex = load_texture("assets/mouse.png").await.ok();
     }
 
     pub fn refresh_rooms(&mut self, network: &mut Network) {
         self.rooms.clear();
+        self.close_panels();
+
+        match network.request_ratings() {
+            Ok(ratings) => self.ratings = ratings,
+            Err(e) => self
+                .notifications
+                .add(format!("Could not request ratings ({})", e), false),
+        }
 
-        match network.request_rooms() {
+        // The server lists the rooms closest to our rating first
+        match network.request_rooms(self.player_type) {
             Ok(rooms) => {
-                for (idx, (room_id, player_count)) in rooms.iter().enumerate() {
+                for (idx, room) in rooms.iter().enumerate() {
                     self.rooms.push(Room {
-                        room_id: *room_id,
+                        room_id: room.room_id,
                         button: Button::new(
                             ButtonType::Room,
                             Vec2::new(
-                                -8.0 * MENU_OFFSET,
+                                -8.75 * MENU_OFFSET,
                                 START_ROOMS_Y + (ROOM_HEIGHT + 8.0) * idx as f32,
                             ),
-                            Vec2::new(400.0, ROOM_HEIGHT),
-                            format!("\t\t\tRoom {}\t({}/2) players", idx + 1, player_count),
+                            Vec2::new(480.0, ROOM_HEIGHT),
+                            format!(
+                                "Room {} [M {}/{}{} T {}/{}{}] {}x{}, {} walls{}{}{}{}",
+                                idx + 1,
+                                room.seats.mice - room.free.mice,
+                                room.seats.mice,
+                                Self::rating_label(room.ratings.mouse),
+                                room.seats.trappers - room.free.trappers,
+                                room.seats.trappers,
+                                Self::rating_label(room.ratings.wall),
+                                room.settings.width,
+                                room.settings.height,
+                                room.settings.walls,
+                                match &room.settings.shape {
+                                    BoardShape::Rectangle => String::new(),
+                                    shape => format!(", {}", shape.name()),
+                                },
+                                match room.settings.rules.describe() {
+                                    rules if rules.is_empty() => rules,
+                                    rules => format!(", {}", rules),
+                                },
+                                match room.settings.mice {
+                                    1 => String::new(),
+                                    mice => format!(", {} mice", mice),
+                                },
+                                if room.settings.hints { ", hints" } else { "" }
+                            ),
                             Color::from_hex(0x5A7863),
                             true,
                         ),
@@ -131,6 +430,245 @@ impl Menu {
                 .notifications
                 .add(format!("Could not request rooms ({})", e), false),
         }
+
+        self.refresh_shapes(network);
+    }
+
+    fn rating_label(rating: u32) -> String {
+        match rating {
+            0 => String::new(),
+            rating => format!(" ({})", rating),
+        }
+    }
+
+    /// The built in shapes always come first, custom boards depend on the server.
+    fn refresh_shapes(&mut self, network: &mut Network) {
+        let selected = self.shapes[self.shape].clone();
+
+        self.shapes = vec![BoardShape::Rectangle, BoardShape::Hexagon];
+        match network.request_boards() {
+            Ok(names) => self.shapes.extend(names.into_iter().map(BoardShape::Mask)),
+            Err(e) => self
+                .notifications
+                .add(format!("Could not request boards ({})", e), false),
+        }
+
+        self.shape = self
+            .shapes
+            .iter()
+            .position(|shape| *shape == selected)
+            .unwrap_or(0);
+        self.update_shape_button();
+    }
+
+    fn next_shape(&mut self) {
+        self.shape = (self.shape + 1) % self.shapes.len();
+        self.update_shape_button();
+    }
+
+    fn update_shape_button(&mut self) {
+        if let Some(b) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.button_type == ButtonType::Shape)
+        {
+            b.text = self.shapes[self.shape].name().to_string();
+        }
+    }
+
+    fn next_teams(&mut self) {
+        self.teams = (self.teams + 1) % TEAM_MODES.len();
+        let seats = TEAM_MODES[self.teams];
+        if let Some(b) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.button_type == ButtonType::Teams)
+        {
+            b.text = format!("{}v{}", seats.mice, seats.trappers);
+        }
+    }
+
+    fn toggle_hints(&mut self) {
+        self.hints = !self.hints;
+        if let Some(b) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.button_type == ButtonType::Hints)
+        {
+            b.text = if self.hints { "Hints" } else { "No hints" }.to_string();
+        }
+    }
+
+    fn next_mice(&mut self) {
+        self.mice = self.mice % MAX_MICE + 1;
+        if let Some(b) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.button_type == ButtonType::Mice)
+        {
+            b.text = match self.mice {
+                1 => "1 mouse".to_string(),
+                mice => format!("{} mice", mice),
+            };
+        }
+    }
+
+    fn next_rules(&mut self) {
+        self.rules = (self.rules + 1) % RULE_PRESETS.len();
+        if let Some(b) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.button_type == ButtonType::Rules)
+        {
+            b.text = RULE_PRESETS[self.rules].0.to_string();
+        }
+    }
+
+    /// The daily board, games, rankings and profile all take the place of the room list.
+    fn close_panels(&mut self) {
+        self.daily = None;
+        self.games = None;
+        self.leaderboard = None;
+        self.profile = None;
+    }
+
+    fn toggle_daily(&mut self, network: &mut Network) {
+        let was_open = self.daily.is_some();
+        self.close_panels();
+        if was_open {
+            return;
+        }
+
+        match network.request_daily() {
+            Ok((day, entries)) => {
+                self.daily = Some(Daily {
+                    day,
+                    entries,
+                    play_button: Button::new(
+                        ButtonType::PlayDaily,
+                        Vec2::new(-8.75 * MENU_OFFSET, START_ROOMS_Y),
+                        Vec2::new(480.0, ROOM_HEIGHT),
+                        "Play today's board".to_string(),
+                        Color::from_hex(0xB07F23),
+                        true,
+                    ),
+                })
+            }
+            Err(e) => self
+                .notifications
+                .add(format!("Could not request daily challenge ({})", e), false),
+        }
+    }
+
+    fn toggle_games(&mut self, network: &mut Network) {
+        let was_open = self.games.is_some();
+        self.close_panels();
+        if was_open {
+            return;
+        }
+
+        match network.request_recent_games() {
+            Ok(games) => {
+                let games = games
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, game)| ArchivedGame {
+                        id: game.id,
+                        button: Button::new(
+                            ButtonType::Game,
+                            Vec2::new(
+                                -8.75 * MENU_OFFSET,
+                                START_ROOMS_Y + (ROOM_HEIGHT + 8.0) * idx as f32,
+                            ),
+                            Vec2::new(480.0, ROOM_HEIGHT),
+                            format!(
+                                "{} vs {}: {} ({})",
+                                match game.side {
+                                    PlayerType::Mouse => "Mouse",
+                                    PlayerType::Wall => "Wall",
+                                },
+                                game.opponents,
+                                if game.won { "won" } else { "lost" },
+                                game.reason
+                            ),
+                            Color::from_hex(0x5A7863),
+                            true,
+                        ),
+                    })
+                    .collect();
+                self.games = Some(games);
+            }
+            Err(e) => self
+                .notifications
+                .add(format!("Could not request your games ({})", e), false),
+        }
+    }
+
+    fn toggle_leaderboard(&mut self, network: &mut Network) {
+        let was_open = self.leaderboard.is_some();
+        self.close_panels();
+        if was_open {
+            return;
+        }
+
+        match network.request_leaderboard() {
+            Ok(leaderboard) => self.leaderboard = Some(leaderboard),
+            Err(e) => self
+                .notifications
+                .add(format!("Could not request the rankings ({})", e), false),
+        }
+    }
+
+    /// Our own statistics, the server knows which name we play under.
+    fn toggle_profile(&mut self, network: &mut Network) {
+        let was_open = self.profile.is_some();
+        self.close_panels();
+        if was_open {
+            return;
+        }
+
+        match network.request_profile("") {
+            Ok(profile) => self.profile = Some(profile),
+            Err(e) => self
+                .notifications
+                .add(format!("Could not request your profile ({})", e), false),
+        }
+    }
+
+    /// Downloads the game, keeps a copy in the replay folder and hands it to the viewer.
+    fn open_game(&mut self, id: &[u8; 16], network: &mut Network) {
+        let text = match network.request_game(id) {
+            Ok(Some(text)) => text,
+            Ok(None) => {
+                self.notifications
+                    .add("The server does not know this game".to_string(), false);
+                return;
+            }
+            Err(e) => {
+                self.notifications
+                    .add(format!("Could not download the game ({})", e), false);
+                return;
+            }
+        };
+
+        match Replay::parse(&text) {
+            Ok(replay) => {
+                let path = std::path::Path::new(REPLAY_DIR).join(format!("{}.replay", replay.id));
+                if let Err(e) =
+                    std::fs::create_dir_all(REPLAY_DIR).and_then(|_| std::fs::write(&path, &text))
+                {
+                    self.notifications
+                        .add(format!("Could not save the replay ({})", e), false);
+                }
+
+                self.games = None;
+                self.replay = Some(replay);
+                self.visible = false;
+            }
+            Err(e) => self
+                .notifications
+                .add(format!("Could not read the replay ({})", e), false),
+        }
     }
 
     pub fn handle_input(&mut self, network: &mut Network) {
@@ -138,8 +676,8 @@ impl Menu {
             return;
         }
 
-        if self.writing_mode {
-            self.handle_writing();
+        if let Some(field) = self.writing {
+            self.handle_writing(field);
             return;
         }
 
@@ -154,10 +692,17 @@ impl Menu {
         for room in self.rooms.iter_mut() {
             room.button.highlighted = room.button.is_inside(mouse_pos);
         }
+        if let Some(daily) = &mut self.daily {
+            daily.play_button.highlighted = daily.play_button.is_inside(mouse_pos);
+        }
+        for game in self.games.iter_mut().flatten() {
+            game.button.highlighted = game.button.is_inside(mouse_pos);
+        }
 
         if is_mouse_button_pressed(MouseButton::Left) {
             let mut clicked: Option<ButtonType> = None;
-            let mut room_id: Option<[u8; 16]> = None;
+            // Id of the clicked room or archived game
+            let mut target_id: Option<[u8; 16]> = None;
             for button in &self.buttons {
                 if button.is_inside(mouse_pos) {
                     clicked = Some(button.button_type);
@@ -165,79 +710,172 @@ impl Menu {
                 }
             }
 
-            if clicked.is_none() {
+            if let Some(daily) = &self.daily {
+                if clicked.is_none() && daily.play_button.is_inside(mouse_pos) {
+                    clicked = Some(daily.play_button.button_type);
+                }
+            } else if let Some(games) = &self.games {
+                if clicked.is_none()
+                    && let Some(game) = games.iter().find(|game| game.button.is_inside(mouse_pos))
+                {
+                    clicked = Some(game.button.button_type);
+                    target_id = Some(game.id);
+                }
+            } else if clicked.is_none() && self.leaderboard.is_none() && self.profile.is_none() {
                 for room in &self.rooms {
                     if room.button.is_inside(mouse_pos) {
                         clicked = Some(room.button.button_type);
-                        room_id = Some(room.room_id);
+                        target_id = Some(room.room_id);
                         break;
                     }
                 }
             }
 
             if let Some(button_type) = clicked {
-                self.handle_button(button_type, room_id, network);
+                self.handle_button(button_type, target_id, network);
             }
         }
     }
 
-    pub fn handle_writing(&mut self) {
+    pub fn handle_writing(&mut self, field: ButtonType) {
+        let (mut text, max_len) = match field {
+            ButtonType::EnterSeed => (self.seed.clone(), MAX_SEED),
+            ButtonType::EnterWidth => (self.board_width.clone(), MAX_BOARD_DIGITS),
+            ButtonType::EnterHeight => (self.board_height.clone(), MAX_BOARD_DIGITS),
+            ButtonType::EnterWalls => (self.board_walls.clone(), MAX_BOARD_DIGITS),
+            ButtonType::EnterPassword => (self.password.clone(), MAX_PASSWORD),
+            _ => (self.username.clone(), MAX_USERNAME),
+        };
         let mut update = false;
 
         while let Some(c) = get_char_pressed() {
-            if !c.is_control() && self.username.chars().count() < MAX_USERNAME {
-                self.username.push(c);
+            let allowed = match field {
+                ButtonType::EnterText | ButtonType::EnterPassword => !c.is_control(),
+                _ => c.is_ascii_digit(),
+            };
+
+            if allowed && text.chars().count() < max_len {
+                text.push(c);
                 update = true;
             }
         }
 
         if is_key_pressed(KeyCode::Backspace) {
-            self.username.pop();
+            text.pop();
             update = true;
         }
 
         if is_key_pressed(KeyCode::Enter) {
-            self.writing_mode = false;
-            if let Some(b) = &mut self
-                .buttons
-                .iter_mut()
-                .find(|b| b.button_type == ButtonType::EnterText)
-            {
+            self.writing = None;
+            if let Some(b) = &mut self.buttons.iter_mut().find(|b| b.button_type == field) {
                 b.color = Color::from_hex(0xB07F23);
             }
         }
 
-        if update
-            && let Some(b) = &mut self
-                .buttons
-                .iter_mut()
-                .find(|b| b.button_type == ButtonType::EnterText)
-        {
-            b.text = self.username.clone();
+        if update {
+            if let Some(b) = &mut self.buttons.iter_mut().find(|b| b.button_type == field) {
+                b.text = match field {
+                    ButtonType::EnterPassword => "*".repeat(text.chars().count()),
+                    _ => text.clone(),
+                };
+            }
+
+            match field {
+                ButtonType::EnterSeed => self.seed = text,
+                ButtonType::EnterWidth => self.board_width = text,
+                ButtonType::EnterHeight => self.board_height = text,
+                ButtonType::EnterWalls => self.board_walls = text,
+                ButtonType::EnterPassword => self.password = text,
+                _ => self.username = text,
+            }
+        }
+    }
+
+    /// An empty seed field lets the server pick a random board, the server validates the rest.
+    /// Hexagons use the width as their diameter and custom boards ignore the size.
+    fn get_settings(&self) -> BoardSettings {
+        BoardSettings {
+            width: self.board_width.parse().unwrap_or(0),
+            height: self.board_height.parse().unwrap_or(0),
+            walls: self.board_walls.parse().unwrap_or(0),
+            shape: self.shapes[self.shape].clone(),
+            rules: RULE_PRESETS[self.rules].1,
+            mice: self.mice,
+            seed: self.seed.parse().ok(),
+            hints: self.hints,
         }
     }
 
     fn handle_button(
         &mut self,
         button_type: ButtonType,
-        room_id: Option<[u8; 16]>,
+        target_id: Option<[u8; 16]>,
         network: &mut Network,
     ) {
         match button_type {
             ButtonType::Refresh => self.refresh_rooms(network),
             ButtonType::StartGameBot => {
-                match network.start_room_bot(&self.player_type, &self.username) {
+                match network.start_room_bot(
+                    &self.player_type,
+                    &self.username,
+                    &self.get_settings(),
+                ) {
                     Ok(()) => self.visible = false,
                     Err(e) => self
                         .notifications
                         .add(format!("Could not start new bot room ({})", e), false),
                 }
             }
-            ButtonType::EnterText => self.writing_mode = true,
+            ButtonType::NewRoom => {
+                match network.create_room(
+                    &self.player_type,
+                    &self.username,
+                    &self.get_settings(),
+                    TEAM_MODES[self.teams],
+                ) {
+                    Ok(()) => self.visible = false,
+                    Err(e) => self
+                        .notifications
+                        .add(format!("Could not create new room ({})", e), false),
+                }
+            }
+            // The account decides the name once logged in
+            ButtonType::EnterText | ButtonType::EnterPassword if self.account.is_some() => {}
+            ButtonType::EnterText
+            | ButtonType::EnterPassword
+            | ButtonType::EnterSeed
+            | ButtonType::EnterWidth
+            | ButtonType::EnterHeight
+            | ButtonType::EnterWalls => self.writing = Some(button_type),
+            ButtonType::Login if self.account.is_some() => self.logout(network),
+            ButtonType::Login | ButtonType::Register => self.authenticate(button_type, network),
+            ButtonType::Shape => self.next_shape(),
+            ButtonType::Rules => self.next_rules(),
+            ButtonType::Mice => self.next_mice(),
+            ButtonType::Teams => self.next_teams(),
+            ButtonType::Hints => self.toggle_hints(),
+            ButtonType::Daily => self.toggle_daily(network),
+            ButtonType::PlayDaily => match network.start_daily(&self.username) {
+                Ok(()) => {
+                    self.daily = None;
+                    self.visible = false;
+                }
+                Err(e) => self
+                    .notifications
+                    .add(format!("Could not start daily challenge ({})", e), false),
+            },
+            ButtonType::Games => self.toggle_games(network),
+            ButtonType::Leaderboard => self.toggle_leaderboard(network),
+            ButtonType::Profile => self.toggle_profile(network),
+            ButtonType::Game => {
+                if let Some(id) = target_id {
+                    self.open_game(&id, network);
+                }
+            }
             ButtonType::LeftSelect => self.swap_player_type(),
             ButtonType::RightSelect => self.swap_player_type(),
             ButtonType::Room => {
-                if let Some(rid) = room_id {
+                if let Some(rid) = target_id {
                     match network.join_room(&rid, &self.player_type, &self.username) {
                         Ok(()) => self.visible = false,
                         Err(e) => self
@@ -246,7 +884,76 @@ impl Menu {
                     }
                 }
             }
+            ButtonType::ReplayBack
+            | ButtonType::ReplayPlay
+            | ButtonType::ReplayForward
+            | ButtonType::ReplaySlower
+            | ButtonType::ReplayFaster
+            | ButtonType::ReplayClose
+            | ButtonType::EnterAddress
+            | ButtonType::Connect
+            | ButtonType::RecentServer
+            | ButtonType::LanServer => {}
+        }
+    }
+
+    fn authenticate(&mut self, button_type: ButtonType, network: &mut Network) {
+        let result = if button_type == ButtonType::Register {
+            network.register(&self.username, &self.password)
+        } else {
+            network.login(&self.username, &self.password)
+        };
+
+        match result {
+            Ok(name) => {
+                self.notifications
+                    .add(format!("Logged in as {}", name), true);
+                self.set_account(Some(name));
+                self.refresh_rooms(network);
+            }
+            Err(e) => self.notifications.add(e.to_string(), false),
+        }
+    }
+
+    fn logout(&mut self, network: &mut Network) {
+        match network.logout() {
+            Ok(()) => {
+                self.set_account(None);
+                self.refresh_rooms(network);
+            }
+            Err(e) => self
+                .notifications
+                .add(format!("Could not log out ({})", e), false),
+        }
+    }
+
+    /// A new connection starts out as a guest without a room.
+    pub fn connection_reset(&mut self, network: &mut Network) {
+        if self.account.is_some() {
+            self.notifications
+                .add("Reconnected, please log in again".to_string(), false);
         }
+        self.set_account(None);
+        self.refresh_rooms(network);
+    }
+
+    fn set_account(&mut self, account: Option<String>) {
+        if let Some(name) = &account {
+            self.username = name.clone();
+        }
+        self.password.clear();
+
+        for b in self.buttons.iter_mut() {
+            match b.button_type {
+                ButtonType::EnterText => b.text = self.username.clone(),
+                ButtonType::EnterPassword => b.text.clear(),
+                ButtonType::Login if account.is_some() => b.text = "Logout".to_string(),
+                ButtonType::Login => b.text = "Login".to_string(),
+                _ => {}
+            }
+        }
+
+        self.account = account;
     }
 
     fn swap_player_type(&mut self) {
@@ -281,7 +988,7 @@ impl Menu {
         );
 
         draw_text(
-            "\t\t\tEnter your username:",
+            "\t\t\tEnter your username (and password to log in):",
             MENU_OFFSET,
             START_ROOMS_Y - 132.0,
             32.0,
@@ -324,8 +1031,9 @@ impl Menu {
                 }
             };
 
+            let rating = Self::rating_label(self.ratings.get(self.player_type));
             draw_text(
-                &("Play as: ".to_owned() + text),
+                format!("Play as: {}{}", text, rating),
                 MENU_OFFSET + 32.0,
                 START_ROOMS_Y + 24.0,
                 32.0,
@@ -333,6 +1041,36 @@ impl Menu {
             );
         }
 
+        draw_text(
+            "Board size and walls:",
+            MENU_OFFSET + 32.0,
+            START_ROOMS_Y + 236.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+        draw_text(
+            "x",
+            MENU_OFFSET + 104.0,
+            START_ROOMS_Y + 268.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+        draw_text(
+            "walls",
+            MENU_OFFSET + 208.0,
+            START_ROOMS_Y + 268.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+
+        draw_text(
+            "Seed (empty = random):",
+            MENU_OFFSET + 32.0,
+            START_ROOMS_Y + 314.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+
         draw_rectangle(
             MENU_OFFSET + 16.0,
             START_ROOMS_Y - 64.0,
@@ -353,10 +1091,171 @@ impl Menu {
             button.render();
         }
 
-        for room in self.rooms.iter() {
-            room.button.render();
+        if let Some(daily) = &self.daily {
+            Self::render_daily(daily);
+        } else if let Some(games) = &self.games {
+            if games.is_empty() {
+                draw_text(
+                    "No finished games yet",
+                    screen_width() - 8.75 * MENU_OFFSET,
+                    START_ROOMS_Y + 32.0,
+                    28.0,
+                    Color::from_hex(0xEBF4DD),
+                );
+            }
+            for game in games {
+                game.button.render();
+            }
+        } else if let Some(leaderboard) = &self.leaderboard {
+            Self::render_leaderboard(leaderboard, self.player_type);
+        } else if let Some(profile) = &self.profile {
+            Self::render_profile(profile);
+        } else {
+            for room in self.rooms.iter() {
+                room.button.render();
+            }
         }
 
         self.notifications.render();
     }
+
+    fn render_daily(daily: &Daily) {
+        daily.play_button.render();
+
+        let x = screen_width() - 8.75 * MENU_OFFSET;
+        let mut y = START_ROOMS_Y + ROOM_HEIGHT + 48.0;
+        draw_text(
+            format!("Day #{} - fewest walls", daily.day),
+            x,
+            y,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+
+        if daily.entries.is_empty() {
+            y += 36.0;
+            draw_text(
+                "Nobody trapped the mouse yet",
+                x,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+
+        for (idx, (username, walls)) in daily.entries.iter().enumerate() {
+            y += 32.0;
+            draw_text(
+                format!("{}. {}", idx + 1, username),
+                x,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+            draw_text(
+                format!("{} walls", walls),
+                x + 300.0,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+    }
+
+    /// Only the role picked to play as, the side selection switches between the two lists.
+    fn render_leaderboard(leaderboard: &Leaderboard, player_type: PlayerType) {
+        let (title, entries) = match player_type {
+            PlayerType::Mouse => ("Top mice", &leaderboard.mice),
+            PlayerType::Wall => ("Top trappers", &leaderboard.walls),
+        };
+
+        let x = screen_width() - 8.75 * MENU_OFFSET;
+        let mut y = START_ROOMS_Y + 32.0;
+        draw_text(title, x, y, 32.0, Color::from_hex(0xEBF4DD));
+
+        if entries.is_empty() {
+            y += 36.0;
+            draw_text(
+                "Nobody played a rated game yet",
+                x,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+
+        for (idx, entry) in entries.iter().enumerate() {
+            y += 32.0;
+            draw_text(
+                format!("{}. {}", idx + 1, entry.name),
+                x,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+            draw_text(
+                entry.rating.to_string(),
+                x + 250.0,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+            draw_text(
+                format!("{} games", entry.games),
+                x + 340.0,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+    }
+
+    fn render_profile(profile: &Profile) {
+        let x = screen_width() - 8.75 * MENU_OFFSET;
+        let mut y = START_ROOMS_Y + 32.0;
+        draw_text(&profile.name, x, y, 32.0, Color::from_hex(0xEBF4DD));
+
+        for (idx, (role, player_type)) in [("Mouse", PlayerType::Mouse), ("Wall", PlayerType::Wall)]
+            .into_iter()
+            .enumerate()
+        {
+            let rating = match profile.ratings.get(player_type) {
+                0 => "unrated".to_string(),
+                rating => format!("rated {}", rating),
+            };
+
+            y += 36.0;
+            draw_text(
+                format!(
+                    "{}: {}, won {} of {}",
+                    role, rating, profile.wins[idx], profile.games[idx]
+                ),
+                x,
+                y,
+                28.0,
+                Color::from_hex(0xEBF4DD),
+            );
+        }
+
+        y += 36.0;
+        draw_text(
+            format!("Longest winning streak: {}", profile.longest_streak),
+            x,
+            y,
+            28.0,
+            Color::from_hex(0xEBF4DD),
+        );
+
+        y += 36.0;
+        draw_text(
+            match profile.average_moves_to_trap {
+                Some(average) => format!("Average walls to trap: {:.1}", average),
+                None => "Average walls to trap: -".to_string(),
+            },
+            x,
+            y,
+            28.0,
+            Color::from_hex(0xEBF4DD),
+        );
+    }
 }
@@ -1,12 +1,16 @@
 use crate::app::ClientErr;
-use crate::grid::Grid;
+use crate::grid::{Entity, Grid};
 use crate::menu::PlayerType;
+use hexgrid::MAX_BOARD_SIZE;
 use std::{
-    io::{Read, Write},
+    io::{ErrorKind, Read, Write},
     net::{TcpStream, ToSocketAddrs},
     time::Duration,
 };
 
+/// Grid encoding version this client understands.
+const GRID_ENCODING_VERSION: u8 = 1;
+
 #[repr(u8)]
 pub enum Protocol {
     RequestRooms,
@@ -20,6 +24,30 @@ pub enum Protocol {
     WaitTurn,
     YourTurn,
     GameOver,
+    RequestHint,
+    Hint,
+    HintDenied,
+    CreateRoom,
+    StartDaily,
+    RequestDaily,
+    RequestBoards,
+    RequestPosition,
+    RequestAnalysis,
+    Analysis,
+    AnalysisDenied,
+    Register,
+    Login,
+    Logout,
+    AuthSuccess,
+    AuthFail,
+    RequestRatings,
+    RequestRecentGames,
+    RequestGame,
+    RequestLeaderboard,
+    RequestProfile,
+    RequestSession,
+    Rejoin,
+    RoomSeats,
 }
 
 pub enum Update {
@@ -28,35 +56,225 @@ pub enum Update {
     YourTurn,
     WaitTurn,
     GameOver,
+    /// Seats of the room we wait in changed, read them with `read_room_seats`.
+    RoomSeats,
+    /// The server closed the connection.
+    Disconnected,
+}
+
+pub struct Hint {
+    pub tile: Option<(usize, usize)>,
+    /// Mouse that should take the step, `None` for wall hints.
+    pub mouse: Option<(usize, usize)>,
+    pub escape_distance: Option<u32>,
+    pub open_routes: u32,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum BoardShape {
+    Rectangle,
+    Hexagon,
+    Mask(String),
+}
+
+impl BoardShape {
+    fn id(&self) -> u8 {
+        match self {
+            BoardShape::Rectangle => 0,
+            BoardShape::Hexagon => 1,
+            BoardShape::Mask(_) => 2,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            BoardShape::Rectangle => "Rectangle",
+            BoardShape::Hexagon => "Hexagon",
+            BoardShape::Mask(name) => name,
+        }
+    }
+}
+
+/// House rules of a room, one wall, one step and walls anywhere is the standard game.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Rules {
+    pub walls_per_turn: u8,
+    pub mouse_steps: u8,
+    pub no_wall_near_mouse: bool,
+}
+
+impl Rules {
+    /// Short summary of everything that differs from the standard game.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.walls_per_turn > 1 {
+            parts.push(format!("{} walls/turn", self.walls_per_turn));
+        }
+        if self.mouse_steps > 1 {
+            parts.push(format!("{} steps", self.mouse_steps));
+        }
+        if self.no_wall_near_mouse {
+            parts.push("keep away".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Clone)]
+pub struct BoardSettings {
+    pub width: u32,
+    pub height: u32,
+    pub walls: u32,
+    pub mice: u8,
+    pub shape: BoardShape,
+    pub rules: Rules,
+    pub seed: Option<u64>,
+    /// Whether players of the room may ask for hints.
+    pub hints: bool,
+}
+
+/// How many players sit on each side of a room.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Seats {
+    pub mice: u8,
+    pub trappers: u8,
+}
+
+/// Mouse and wall rating, zero when unrated.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Ratings {
+    pub mouse: u32,
+    pub wall: u32,
+}
+
+impl Ratings {
+    pub fn get(&self, player_type: PlayerType) -> u32 {
+        match player_type {
+            PlayerType::Mouse => self.mouse,
+            PlayerType::Wall => self.wall,
+        }
+    }
+}
+
+pub struct RoomInfo {
+    pub room_id: RoomId,
+    pub seats: Seats,
+    pub free: Seats,
+    pub settings: BoardSettings,
+    /// Average rating of the logged in players seated on each side.
+    pub ratings: Ratings,
+}
+
+/// A finished game of ours in the server archive.
+pub struct GameSummary {
+    pub id: [u8; 16],
+    pub won: bool,
+    pub reason: &'static str,
+    pub side: PlayerType,
+    pub opponents: String,
+}
+
+/// A player on the leaderboard of one role.
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub rating: u32,
+    pub games: u32,
+}
+
+/// Best players per role.
+pub struct Leaderboard {
+    pub mice: Vec<LeaderboardEntry>,
+    pub walls: Vec<LeaderboardEntry>,
+}
+
+/// Results of a player over all archived games, counters are per role.
+pub struct Profile {
+    pub name: String,
+    /// Zero when unrated in the role.
+    pub ratings: Ratings,
+    pub games: [u32; 2],
+    pub wins: [u32; 2],
+    pub longest_streak: u32,
+    pub average_moves_to_trap: Option<f32>,
 }
 
 type RoomId = [u8; 16];
-type RoomData = Vec<(RoomId, u8)>;
+type RoomData = Vec<RoomInfo>;
+type DailyData = (u64, Vec<(String, u32)>);
 
 pub struct Network {
     stream: TcpStream,
+    address: String,
+    /// Lets us take our seat back when the server restarts mid game.
+    session: [u8; 16],
     pub room_id: Option<RoomId>,
     pub opponent_username: String,
 }
 
 impl Network {
-    pub fn new() -> Result<Self, ClientErr> {
-        if let Ok(mut addrs) = "127.0.0.1:1922".to_socket_addrs()
+    pub fn new(address: &str) -> Result<Self, ClientErr> {
+        let mut network = Self {
+            stream: Self::connect(address)?,
+            address: address.to_string(),
+            session: [0u8; 16],
+            room_id: None,
+            opponent_username: String::new(),
+        };
+        network.session = network.request_session()?;
+
+        Ok(network)
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn connect(address: &str) -> Result<TcpStream, ClientErr> {
+        if let Ok(mut addrs) = address.to_socket_addrs()
             && let Some(addr) = addrs.next()
         {
-            let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(5))?;
-            Ok(Self {
-                stream,
-                room_id: None,
-                opponent_username: String::new(),
-            })
+            Ok(TcpStream::connect_timeout(&addr, Duration::from_secs(5))?)
         } else {
             Err(ClientErr::JoinFail)
         }
     }
 
-    pub fn request_rooms(&mut self) -> Result<RoomData, ClientErr> {
-        self.stream.write_all(&[Protocol::RequestRooms as u8])?;
+    fn request_session(&mut self) -> Result<[u8; 16], ClientErr> {
+        self.stream.write_all(&[Protocol::RequestSession as u8])?;
+
+        let mut session = [0u8; 16];
+        self.stream.read_exact(&mut session)?;
+        Ok(session)
+    }
+
+    /// Opens a new connection and, when we were in a room, asks for our seat back. Returns
+    /// whether the seat was ours again, otherwise we start over as a new guest.
+    pub fn reconnect(&mut self) -> Result<bool, ClientErr> {
+        self.stream = Self::connect(&self.address)?;
+
+        if self.room_id.is_some() {
+            let mut data: Vec<u8> = vec![Protocol::Rejoin as u8];
+            data.extend(self.session);
+            self.stream.write_all(&data)?;
+
+            let mut responseb = [0u8; 1];
+            self.stream.read_exact(&mut responseb)?;
+            if responseb[0] == Protocol::JoinSuccess as u8 {
+                let mut room_idb = [0u8; 16];
+                self.stream.read_exact(&mut room_idb)?;
+                self.room_id = Some(room_idb);
+                return Ok(true);
+            }
+        }
+
+        self.room_id = None;
+        self.session = self.request_session()?;
+        Ok(false)
+    }
+
+    pub fn request_rooms(&mut self, player_type: PlayerType) -> Result<RoomData, ClientErr> {
+        self.stream
+            .write_all(&[Protocol::RequestRooms as u8, player_type as u8])?;
 
         let mut room_countb = [0u8; 4];
         self.stream.read_exact(&mut room_countb)?;
@@ -71,24 +289,188 @@ impl Network {
             let mut player_countb = [0u8; 1];
             self.stream.read_exact(&mut player_countb)?;
 
-            let player_count = u8::from_le_bytes(player_countb);
-            room_data.push((room_idb, player_count));
+            let mut seatsb = [0u8; 4];
+            self.stream.read_exact(&mut seatsb)?;
+
+            let mut values = [0u32; 3];
+            for value in values.iter_mut() {
+                let mut bytes = [0u8; 4];
+                self.stream.read_exact(&mut bytes)?;
+                *value = u32::from_le_bytes(bytes);
+            }
+            let [width, height, walls] = values;
+
+            let mut shapeb = [0u8; 1];
+            self.stream.read_exact(&mut shapeb)?;
+            let name = self.read_string()?;
+            let shape = match shapeb[0] {
+                0 => BoardShape::Rectangle,
+                1 => BoardShape::Hexagon,
+                _ => BoardShape::Mask(name),
+            };
+
+            let mut rulesb = [0u8; 3];
+            self.stream.read_exact(&mut rulesb)?;
+
+            let mut miceb = [0u8; 1];
+            self.stream.read_exact(&mut miceb)?;
+
+            let mut hintsb = [0u8; 1];
+            self.stream.read_exact(&mut hintsb)?;
+
+            let ratings = self.read_ratings()?;
+
+            room_data.push(RoomInfo {
+                room_id: room_idb,
+                seats: Seats {
+                    mice: seatsb[0],
+                    trappers: seatsb[1],
+                },
+                free: Seats {
+                    mice: seatsb[2],
+                    trappers: seatsb[3],
+                },
+                settings: BoardSettings {
+                    width,
+                    height,
+                    walls,
+                    mice: miceb[0],
+                    shape,
+                    rules: Rules {
+                        walls_per_turn: rulesb[0],
+                        mouse_steps: rulesb[1],
+                        no_wall_near_mouse: rulesb[2] & 1 != 0,
+                    },
+                    seed: None,
+                    hints: hintsb[0] != 0,
+                },
+                ratings,
+            });
         }
 
         Ok(room_data)
     }
 
+    /// Names of the custom board shapes the server offers.
+    pub fn request_boards(&mut self) -> Result<Vec<String>, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestBoards as u8])?;
+
+        let mut countb = [0u8; 4];
+        self.stream.read_exact(&mut countb)?;
+
+        (0..u32::from_le_bytes(countb))
+            .map(|_| self.read_string())
+            .collect()
+    }
+
+    /// Current position of the room in the game notation.
+    pub fn request_position(&mut self) -> Result<String, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestPosition as u8])?;
+        self.read_string()
+    }
+
+    fn read_string(&mut self) -> Result<String, ClientErr> {
+        let mut lenb = [0u8; 4];
+        self.stream.read_exact(&mut lenb)?;
+        let len = u32::from_le_bytes(lenb) as usize;
+
+        let mut bytes = vec![0u8; len];
+        self.stream.read_exact(&mut bytes)?;
+
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
     pub fn start_room_bot(
         &mut self,
         player_type: &PlayerType,
         username: &str,
+        settings: &BoardSettings,
+    ) -> Result<(), ClientErr> {
+        self.new_room(
+            Protocol::StartRoomBot,
+            player_type,
+            username,
+            settings,
+            None,
+        )
+    }
+
+    pub fn create_room(
+        &mut self,
+        player_type: &PlayerType,
+        username: &str,
+        settings: &BoardSettings,
+        seats: Seats,
+    ) -> Result<(), ClientErr> {
+        self.new_room(
+            Protocol::CreateRoom,
+            player_type,
+            username,
+            settings,
+            Some(seats),
+        )
+    }
+
+    fn new_room(
+        &mut self,
+        command: Protocol,
+        player_type: &PlayerType,
+        username: &str,
+        settings: &BoardSettings,
+        seats: Option<Seats>,
     ) -> Result<(), ClientErr> {
         if username.chars().count() == 0 {
             return Err(ClientErr::InvalidUsername);
         }
 
-        self.stream.write_all(&[Protocol::StartRoomBot as u8])?;
+        self.stream.write_all(&[command as u8])?;
         self.stream.write_all(&[*player_type as u8])?;
+        self.stream.write_all(&[settings.seed.is_some() as u8])?;
+        self.stream
+            .write_all(&settings.seed.unwrap_or(0).to_le_bytes())?;
+        self.stream.write_all(&settings.width.to_le_bytes())?;
+        self.stream.write_all(&settings.height.to_le_bytes())?;
+        self.stream.write_all(&settings.walls.to_le_bytes())?;
+        self.stream.write_all(&[settings.shape.id()])?;
+        if let BoardShape::Mask(name) = &settings.shape {
+            self.stream.write_all(&(name.len() as u32).to_le_bytes())?;
+            self.stream.write_all(name.as_bytes())?;
+        }
+        self.stream.write_all(&[
+            settings.rules.walls_per_turn,
+            settings.rules.mouse_steps,
+            settings.rules.no_wall_near_mouse as u8,
+        ])?;
+        self.stream.write_all(&[settings.mice])?;
+        if let Some(seats) = seats {
+            self.stream.write_all(&[seats.mice, seats.trappers])?;
+            self.stream.write_all(&[settings.hints as u8])?;
+        }
+        self.stream
+            .write_all(&(username.len() as u32).to_le_bytes())?;
+        self.stream.write_all(username.as_bytes())?;
+
+        let mut responseb = [0u8; 1];
+        self.stream.read_exact(&mut responseb)?;
+
+        if responseb[0] == Protocol::JoinSuccess as u8 {
+            let mut room_idb = [0u8; 16];
+            self.stream.read_exact(&mut room_idb)?;
+
+            self.room_id = Some(room_idb);
+        } else if responseb[0] == Protocol::JoinFail as u8 {
+            return Err(ClientErr::JoinFail);
+        }
+
+        Ok(())
+    }
+
+    pub fn start_daily(&mut self, username: &str) -> Result<(), ClientErr> {
+        if username.chars().count() == 0 {
+            return Err(ClientErr::InvalidUsername);
+        }
+
+        self.stream.write_all(&[Protocol::StartDaily as u8])?;
         self.stream
             .write_all(&(username.len() as u32).to_le_bytes())?;
         self.stream.write_all(username.as_bytes())?;
@@ -101,11 +483,208 @@ impl Network {
             self.stream.read_exact(&mut room_idb)?;
 
             self.room_id = Some(room_idb);
+            Ok(())
+        } else if responseb[0] == Protocol::JoinFail as u8 {
+            Err(ClientErr::DailyUnavailable)
+        } else {
+            Err(ClientErr::UnexpectedResponse)
         }
+    }
+
+    /// Creates an account and logs into it, returns the name the server registered.
+    pub fn register(&mut self, username: &str, password: &str) -> Result<String, ClientErr> {
+        self.authenticate(Protocol::Register, username, password)
+    }
 
+    /// Returns the registered spelling of the account name.
+    pub fn login(&mut self, username: &str, password: &str) -> Result<String, ClientErr> {
+        self.authenticate(Protocol::Login, username, password)
+    }
+
+    pub fn logout(&mut self) -> Result<(), ClientErr> {
+        self.stream.write_all(&[Protocol::Logout as u8])?;
         Ok(())
     }
 
+    /// Latest games played under our current name, newest first.
+    pub fn request_recent_games(&mut self) -> Result<Vec<GameSummary>, ClientErr> {
+        self.stream
+            .write_all(&[Protocol::RequestRecentGames as u8])?;
+
+        let mut countb = [0u8; 4];
+        self.stream.read_exact(&mut countb)?;
+
+        let mut games = Vec::new();
+        for _ in 0..u32::from_le_bytes(countb) {
+            let mut id = [0u8; 16];
+            self.stream.read_exact(&mut id)?;
+
+            // Start time, the list is already sorted by it
+            let mut startedb = [0u8; 8];
+            self.stream.read_exact(&mut startedb)?;
+
+            let mut resultb = [0u8; 3];
+            self.stream.read_exact(&mut resultb)?;
+            let [winner, reason, side] = resultb;
+
+            games.push(GameSummary {
+                id,
+                won: winner == side,
+                reason: match reason {
+                    0 => "trapped",
+                    1 => "escaped",
+                    _ => "forfeit",
+                },
+                side: if side == 0 {
+                    PlayerType::Mouse
+                } else {
+                    PlayerType::Wall
+                },
+                opponents: self.read_string()?,
+            });
+        }
+
+        Ok(games)
+    }
+
+    /// Replay file of an archived game, `None` when the server does not know it.
+    pub fn request_game(&mut self, id: &[u8; 16]) -> Result<Option<String>, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestGame as u8])?;
+        self.stream.write_all(id)?;
+
+        let replay = self.read_string()?;
+        Ok((!replay.is_empty()).then_some(replay))
+    }
+
+    pub fn request_leaderboard(&mut self) -> Result<Leaderboard, ClientErr> {
+        self.stream
+            .write_all(&[Protocol::RequestLeaderboard as u8])?;
+
+        let mice = self.read_leaderboard_entries()?;
+        let walls = self.read_leaderboard_entries()?;
+        Ok(Leaderboard { mice, walls })
+    }
+
+    fn read_leaderboard_entries(&mut self) -> Result<Vec<LeaderboardEntry>, ClientErr> {
+        let mut entries = Vec::new();
+        for _ in 0..self.read_u32()? {
+            entries.push(LeaderboardEntry {
+                name: self.read_string()?,
+                rating: self.read_u32()?,
+                games: self.read_u32()?,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Statistics of a player, an empty name asks for our own.
+    pub fn request_profile(&mut self, name: &str) -> Result<Profile, ClientErr> {
+        let mut data: Vec<u8> = vec![Protocol::RequestProfile as u8];
+        data.extend((name.len() as u32).to_le_bytes());
+        data.extend(name.as_bytes());
+        self.stream.write_all(&data)?;
+
+        let name = self.read_string()?;
+        let mut values = [0u32; 6];
+        for value in values.iter_mut() {
+            *value = self.read_u32()?;
+        }
+        let [mouse, mouse_games, mouse_wins, wall, wall_games, wall_wins] = values;
+        let longest_streak = self.read_u32()?;
+        // Tenths of a move, the maximum until the player trapped the mice once
+        let average = self.read_u32()?;
+
+        Ok(Profile {
+            name,
+            ratings: Ratings { mouse, wall },
+            games: [mouse_games, wall_games],
+            wins: [mouse_wins, wall_wins],
+            longest_streak,
+            average_moves_to_trap: (average != u32::MAX).then(|| average as f32 / 10.0),
+        })
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ClientErr> {
+        let mut bytes = [0u8; 4];
+        self.stream.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Our own ratings, zeros while playing as a guest.
+    pub fn request_ratings(&mut self) -> Result<Ratings, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestRatings as u8])?;
+        self.read_ratings()
+    }
+
+    fn read_ratings(&mut self) -> Result<Ratings, ClientErr> {
+        let mut values = [0u32; 2];
+        for value in values.iter_mut() {
+            let mut bytes = [0u8; 4];
+            self.stream.read_exact(&mut bytes)?;
+            *value = u32::from_le_bytes(bytes);
+        }
+
+        let [mouse, wall] = values;
+        Ok(Ratings { mouse, wall })
+    }
+
+    fn authenticate(
+        &mut self,
+        command: Protocol,
+        username: &str,
+        password: &str,
+    ) -> Result<String, ClientErr> {
+        let mut data: Vec<u8> = vec![command as u8];
+        data.extend((username.len() as u32).to_le_bytes());
+        data.extend(username.as_bytes());
+        data.extend((password.len() as u32).to_le_bytes());
+        data.extend(password.as_bytes());
+        self.stream.write_all(&data)?;
+
+        let mut responseb = [0u8; 1];
+        self.stream.read_exact(&mut responseb)?;
+        let text = self.read_string()?;
+
+        match responseb[0] {
+            x if x == Protocol::AuthSuccess as u8 => Ok(text),
+            x if x == Protocol::AuthFail as u8 => Err(ClientErr::Auth(text)),
+            _ => Err(ClientErr::UnexpectedResponse),
+        }
+    }
+
+    pub fn request_daily(&mut self) -> Result<DailyData, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestDaily as u8])?;
+
+        let mut dayb = [0u8; 8];
+        self.stream.read_exact(&mut dayb)?;
+        let day = u64::from_le_bytes(dayb);
+
+        let mut countb = [0u8; 4];
+        self.stream.read_exact(&mut countb)?;
+        let count = u32::from_le_bytes(countb);
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let mut lenb = [0u8; 4];
+            self.stream.read_exact(&mut lenb)?;
+            let len = u32::from_le_bytes(lenb) as usize;
+
+            let mut usernameb = vec![0u8; len];
+            self.stream.read_exact(&mut usernameb)?;
+
+            let mut wallsb = [0u8; 4];
+            self.stream.read_exact(&mut wallsb)?;
+
+            entries.push((
+                String::from_utf8_lossy(&usernameb).to_string(),
+                u32::from_le_bytes(wallsb),
+            ));
+        }
+
+        Ok((day, entries))
+    }
+
     pub fn join_room(
         &mut self,
         room_id: &RoomId,
@@ -145,31 +724,103 @@ impl Network {
         Ok(())
     }
 
+    /// Reads the board hash and the moves of a turn notification. The moves are played on the
+    /// local board, which is downloaded again when missing or when its hash no longer matches.
+    pub async fn sync_tiles(&mut self, grid: &mut Option<Grid>) -> Result<(), ClientErr> {
+        let mut hashb = [0u8; 8];
+        self.stream.read_exact(&mut hashb)?;
+        let hash = u64::from_le_bytes(hashb);
+
+        let mut countb = [0u8; 4];
+        self.stream.read_exact(&mut countb)?;
+
+        let mut moves = Vec::new();
+        for _ in 0..u32::from_le_bytes(countb) {
+            let mut kindb = [0u8; 1];
+            self.stream.read_exact(&mut kindb)?;
+
+            // Walls are 0, mouse moves 1 and also carry the tile the mouse left
+            let to = self.read_tile()?;
+            let from = if kindb[0] == 1 {
+                self.read_tile()?
+            } else {
+                None
+            };
+            moves.push((kindb[0], to, from));
+        }
+
+        let in_sync = grid.as_mut().is_some_and(|grid| {
+            grid.new_turn();
+
+            let applied = moves.iter().all(|&mv| match mv {
+                (0, Some(to), _) => grid.apply_wall(to),
+                (1, Some(to), Some(from)) => grid.apply_mouse(from, to),
+                _ => false,
+            });
+            applied && grid.zobrist() == hash
+        });
+
+        if !in_sync {
+            if grid.is_some() {
+                eprintln!("Board out of sync, downloading it again");
+            }
+            self.request_tiles(grid).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tile coordinates as two u32, `u32::MAX` marks an unknown tile.
+    fn read_tile(&mut self) -> Result<Option<(usize, usize)>, ClientErr> {
+        let mut bytes = [0u8; 4];
+        self.stream.read_exact(&mut bytes)?;
+        let y = u32::from_le_bytes(bytes);
+
+        self.stream.read_exact(&mut bytes)?;
+        let x = u32::from_le_bytes(bytes);
+
+        Ok((y != u32::MAX && x != u32::MAX).then_some((y as usize, x as usize)))
+    }
+
+    /// Decodes the versioned grid encoding, see `Grid::as_bytes` on the server.
     pub async fn read_tiles(&mut self, grid: &mut Option<Grid>) -> Result<(), ClientErr> {
-        let mut sizeb = [0u8; 4];
-        self.stream.read_exact(&mut sizeb)?;
-        let width = u32::from_le_bytes(sizeb) as usize;
+        let mut versionb = [0u8; 1];
+        self.stream.read_exact(&mut versionb)?;
+        if versionb[0] != GRID_ENCODING_VERSION {
+            return Err(ClientErr::GridVersion(versionb[0]));
+        }
 
-        self.stream.read_exact(&mut sizeb)?;
-        let height = u32::from_le_bytes(sizeb) as usize;
+        let (width, height) = self.read_board_size()?;
+        let bitset_len = (width * height).div_ceil(8);
 
-        self.stream.read_exact(&mut sizeb)?;
-        let tile_count = u32::from_le_bytes(sizeb) as usize;
+        let mut flagsb = [0u8; 1];
+        self.stream.read_exact(&mut flagsb)?;
 
         let mut new_grid = Grid::new(width, height);
-        let mut byte = [0u8; 1];
+        let mut bitset = vec![0u8; bitset_len];
 
-        for _ in 0..tile_count {
-            self.stream.read_exact(&mut byte)?;
-            let y = byte[0] as usize;
+        let mut layers = vec![Entity::Wall];
+        if flagsb[0] & 1 != 0 {
+            layers.insert(0, Entity::Void);
+        }
 
-            self.stream.read_exact(&mut byte)?;
-            let x = byte[0] as usize;
+        for entity in layers {
+            self.stream.read_exact(&mut bitset)?;
+            for i in 0..width * height {
+                if bitset[i / 8] & (1 << (i % 8)) != 0 {
+                    new_grid.place_entity(i / width, i % width, entity as u8);
+                }
+            }
+        }
 
-            self.stream.read_exact(&mut byte)?;
-            let entity = byte[0];
+        for _ in 0..self.read_varint()? {
+            let y = self.read_varint()? as usize;
+            let x = self.read_varint()? as usize;
+            if y >= height || x >= width {
+                return Err(ClientErr::UnexpectedResponse);
+            }
 
-            new_grid.place_entity(y, x, entity);
+            new_grid.place_entity(y, x, Entity::Mouse as u8);
         }
 
         new_grid.load_textures().await;
@@ -177,14 +828,126 @@ impl Network {
         Ok(())
     }
 
-    pub fn make_turn(&mut self, y: usize, x: usize) -> Result<(), ClientErr> {
+    fn read_varint(&mut self) -> Result<u64, ClientErr> {
+        let mut value = 0u64;
+        let mut byte = [0u8; 1];
+
+        for shift in (0..64).step_by(7) {
+            self.stream.read_exact(&mut byte)?;
+            value |= ((byte[0] & 0x7F) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(ClientErr::UnexpectedResponse)
+    }
+
+    /// Width and height of a board, refused before anything is allocated for a bigger one.
+    fn read_board_size(&mut self) -> Result<(usize, usize), ClientErr> {
+        let (width, height) = (self.read_varint()?, self.read_varint()?);
+        let sides = 1..=MAX_BOARD_SIZE as u64;
+        if !sides.contains(&width) || !sides.contains(&height) {
+            return Err(ClientErr::UnexpectedResponse);
+        }
+
+        Ok((width as usize, height as usize))
+    }
+
+    pub fn read_seed(&mut self) -> Result<u64, ClientErr> {
+        let mut seedb = [0u8; 8];
+        self.stream.read_exact(&mut seedb)?;
+
+        Ok(u64::from_le_bytes(seedb))
+    }
+
+    /// New rating and its change after the game, `None` for unrated games.
+    pub fn read_rating_change(&mut self) -> Result<Option<(u32, i32)>, ClientErr> {
+        let mut ratedb = [0u8; 1];
+        self.stream.read_exact(&mut ratedb)?;
+        if ratedb[0] == 0 {
+            return Ok(None);
+        }
+
+        let mut ratingb = [0u8; 4];
+        self.stream.read_exact(&mut ratingb)?;
+        let mut deltab = [0u8; 4];
+        self.stream.read_exact(&mut deltab)?;
+
+        Ok(Some((
+            u32::from_le_bytes(ratingb),
+            i32::from_le_bytes(deltab),
+        )))
+    }
+
+    /// `mouse` picks which mouse moves, without it the server moves the first one that can.
+    pub fn make_turn(
+        &mut self,
+        y: usize,
+        x: usize,
+        mouse: Option<(usize, usize)>,
+    ) -> Result<(), ClientErr> {
+        let (mouse_y, mouse_x) = mouse.map_or((u32::MAX, u32::MAX), |(i, j)| (i as u32, j as u32));
+
         self.stream.write_all(&[Protocol::Turn as u8])?;
         self.stream.write_all(&(y as u32).to_le_bytes())?;
         self.stream.write_all(&(x as u32).to_le_bytes())?;
+        self.stream.write_all(&mouse_y.to_le_bytes())?;
+        self.stream.write_all(&mouse_x.to_le_bytes())?;
 
         Ok(())
     }
 
+    pub fn request_hint(&mut self) -> Result<Option<Hint>, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestHint as u8])?;
+
+        let mut responseb = [0u8; 1];
+        self.stream.read_exact(&mut responseb)?;
+        match responseb[0] {
+            x if x == Protocol::Hint as u8 => {}
+            x if x == Protocol::HintDenied as u8 => return Ok(None),
+            _ => return Err(ClientErr::UnexpectedResponse),
+        }
+
+        let mut values = [0u32; 6];
+        for value in values.iter_mut() {
+            let mut bytes = [0u8; 4];
+            self.stream.read_exact(&mut bytes)?;
+            *value = u32::from_le_bytes(bytes);
+        }
+
+        let [y, x, mouse_y, mouse_x, escape_distance, open_routes] = values;
+        Ok(Some(Hint {
+            tile: (y != u32::MAX).then_some((y as usize, x as usize)),
+            mouse: (mouse_y != u32::MAX).then_some((mouse_y as usize, mouse_x as usize)),
+            escape_distance: (escape_distance != u32::MAX).then_some(escape_distance),
+            open_routes,
+        }))
+    }
+
+    /// Distance of every tile to the edge, `None` when the room has the analysis turned off.
+    pub fn request_analysis(&mut self) -> Result<Option<Vec<Vec<Option<u32>>>>, ClientErr> {
+        self.stream.write_all(&[Protocol::RequestAnalysis as u8])?;
+
+        let mut responseb = [0u8; 1];
+        self.stream.read_exact(&mut responseb)?;
+        match responseb[0] {
+            x if x == Protocol::Analysis as u8 => {}
+            x if x == Protocol::AnalysisDenied as u8 => return Ok(None),
+            _ => return Err(ClientErr::UnexpectedResponse),
+        }
+
+        let (width, height) = self.read_board_size()?;
+
+        // Zero marks a tile without a way out, everything else is the distance plus one
+        let mut map = vec![vec![None; width]; height];
+        for dist in map.iter_mut().flatten() {
+            *dist = self.read_varint()?.checked_sub(1).map(|dist| dist as u32);
+        }
+
+        Ok(Some(map))
+    }
+
     pub fn get_opponent_username(&mut self) -> Result<(), ClientErr> {
         let mut lenb = [0u8; 4];
         self.stream.read_exact(&mut lenb)?;
@@ -197,12 +960,30 @@ impl Network {
         Ok(())
     }
 
+    /// Seats and free seats of the room, like the lobby lists them.
+    pub fn read_room_seats(&mut self) -> Result<(Seats, Seats), ClientErr> {
+        let mut seatsb = [0u8; 4];
+        self.stream.read_exact(&mut seatsb)?;
+
+        let [mice, free_mice, trappers, free_trappers] = seatsb;
+        if free_mice > mice || free_trappers > trappers {
+            return Err(ClientErr::UnexpectedResponse);
+        }
+        Ok((
+            Seats { mice, trappers },
+            Seats {
+                mice: free_mice,
+                trappers: free_trappers,
+            },
+        ))
+    }
+
     pub fn check_for_updates(&mut self) -> Result<Update, ClientErr> {
         self.stream.set_nonblocking(true)?;
 
         let mut byte = [0u8; 1];
         let result = match self.stream.peek(&mut byte) {
-            Ok(0) => Ok(Update::None),
+            Ok(0) => Ok(Update::Disconnected),
             Ok(_) => {
                 let mut update_typeb = [0u8; 1];
                 self.stream.read_exact(&mut update_typeb)?;
@@ -212,9 +993,18 @@ impl Network {
                     x if x == Protocol::YourTurn as u8 => Ok(Update::YourTurn),
                     x if x == Protocol::WaitTurn as u8 => Ok(Update::WaitTurn),
                     x if x == Protocol::GameOver as u8 => Ok(Update::GameOver),
+                    x if x == Protocol::RoomSeats as u8 => Ok(Update::RoomSeats),
                     _ => Ok(Update::None),
                 }
             }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+                ) =>
+            {
+                Ok(Update::Disconnected)
+            }
             Err(e) => Err(ClientErr::from(e)),
         };
 
@@ -0,0 +1,476 @@
+//! Replay files and the viewer stepping through them.
+//!
+//! A replay is a text file starting with `replay <version>`, followed by one game record of the
+//! server archive (see the server `archive` module for every line). The viewer needs the
+//! `position`, `player`, `move` and `result` lines, everything else is only shown or skipped:
+//!
+//! ```text
+//! replay 1
+//! game 3145fd30-6de3-4c8f-8aab-d71c6796d09d
+//! seed 6237002031179348968
+//! position 5x5 5/1#3/2M2/5/5 w
+//! player w alice
+//! player m bob
+//! move 1250 #1,2
+//! move 3000 2,2>2,1
+//! result w trapped
+//! end
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use hexgrid::{MAX_BOARD_SIZE, offset_distance};
+use macroquad::{
+    color::Color,
+    input::{KeyCode, MouseButton, is_key_pressed, is_mouse_button_pressed, mouse_position},
+    prelude::Vec2,
+    text::draw_text,
+    time::get_time,
+    window::{screen_height, screen_width},
+};
+
+use crate::app::ClientErr;
+use crate::button::{Button, ButtonType};
+use crate::grid::{Entity, Grid};
+use crate::menu::PlayerType;
+
+pub const REPLAY_VERSION: u32 = 1;
+pub const REPLAY_DIR: &str = "replays";
+/// Moves per second the viewer can play at.
+const SPEEDS: [f64; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+const DEFAULT_SPEED: usize = 2;
+
+#[derive(Clone, Copy)]
+enum ReplayMove {
+    Wall((usize, usize)),
+    Mouse {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+}
+
+pub struct Replay {
+    pub id: String,
+    seed: Option<u64>,
+    players: Vec<(PlayerType, String)>,
+    result: Option<(PlayerType, String)>,
+    width: usize,
+    height: usize,
+    tiles: Vec<Vec<Entity>>,
+    /// Moves with the milliseconds since the start of the game.
+    moves: Vec<(u64, ReplayMove)>,
+}
+
+fn invalid(line: &str) -> ClientErr {
+    ClientErr::Replay(format!("Invalid replay line '{}'", line))
+}
+
+fn parse_side(text: &str) -> Option<PlayerType> {
+    match text {
+        "m" => Some(PlayerType::Mouse),
+        "w" => Some(PlayerType::Wall),
+        _ => None,
+    }
+}
+
+fn parse_coords(text: &str) -> Option<(usize, usize)> {
+    let (y, x) = text.split_once(',')?;
+    Some((y.parse().ok()?, x.parse().ok()?))
+}
+
+/// `<width>x<height> <rows> <side>`, rows use runs of free tiles, `#`, `M` and `-`.
+fn parse_position(text: &str) -> Option<(usize, usize, Vec<Vec<Entity>>)> {
+    let mut parts = text.split(' ');
+    let (width, height) = parts.next()?.split_once('x')?;
+    let (width, height): (usize, usize) = (width.parse().ok()?, height.parse().ok()?);
+    if width > MAX_BOARD_SIZE || height > MAX_BOARD_SIZE {
+        return None;
+    }
+
+    let rows: Vec<&str> = parts.next()?.split('/').collect();
+    if rows.len() != height {
+        return None;
+    }
+
+    let mut tiles = Vec::with_capacity(height);
+    for row in rows {
+        let mut line = Vec::with_capacity(width);
+        let mut free: usize = 0;
+
+        for c in row.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                free = free.checked_mul(10)?.checked_add(digit as usize)?;
+                continue;
+            }
+            // The run and the tile after it both have to fit in the row
+            if line.len().checked_add(free)? >= width {
+                return None;
+            }
+            line.extend(std::iter::repeat_n(Entity::None, free));
+            free = 0;
+
+            line.push(match c {
+                '#' => Entity::Wall,
+                'M' => Entity::Mouse,
+                '-' => Entity::Void,
+                _ => return None,
+            });
+        }
+
+        if line.len().checked_add(free)? != width {
+            return None;
+        }
+        line.extend(std::iter::repeat_n(Entity::None, free));
+        tiles.push(line);
+    }
+
+    Some((width, height, tiles))
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ClientErr> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ClientErr> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+        let header = lines.next().unwrap_or("");
+        let version = header
+            .strip_prefix("replay ")
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| invalid(header))?;
+        if version != REPLAY_VERSION {
+            return Err(ClientErr::ReplayVersion(version));
+        }
+
+        let mut id = String::new();
+        let mut seed = None;
+        let mut position = None;
+        let mut players = Vec::new();
+        let mut result = None;
+        let mut moves = Vec::new();
+
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "game" => id = value.to_string(),
+                "seed" => seed = value.parse().ok(),
+                "position" => position = Some(parse_position(value).ok_or_else(|| invalid(line))?),
+                "player" => {
+                    let (side, name) = value
+                        .split_once(' ')
+                        .and_then(|(side, name)| Some((parse_side(side)?, name.to_string())))
+                        .ok_or_else(|| invalid(line))?;
+                    players.push((side, name));
+                }
+                "move" => {
+                    let (time, mv) = value.split_once(' ').ok_or_else(|| invalid(line))?;
+                    let time = time.parse::<u64>().map_err(|_| invalid(line))?;
+                    moves.push((time, mv));
+                }
+                "result" => {
+                    let (side, reason) = value.split_once(' ').ok_or_else(|| invalid(line))?;
+                    let side = parse_side(side).ok_or_else(|| invalid(line))?;
+                    result = Some((side, reason.to_string()));
+                }
+                "end" => break,
+                _ => {}
+            }
+        }
+
+        let (width, height, tiles) =
+            position.ok_or_else(|| ClientErr::Replay("The replay has no position".to_string()))?;
+        let moves = Self::resolve_moves(tiles.clone(), &moves)?;
+
+        Ok(Self {
+            id,
+            seed,
+            players,
+            result,
+            width,
+            height,
+            tiles,
+            moves,
+        })
+    }
+
+    /// Plays the moves once to check them and to find the mouse of moves without an origin.
+    fn resolve_moves(
+        mut tiles: Vec<Vec<Entity>>,
+        moves: &[(u64, &str)],
+    ) -> Result<Vec<(u64, ReplayMove)>, ClientErr> {
+        let holder = |tiles: &[Vec<Entity>], (y, x): (usize, usize)| {
+            tiles.get(y).and_then(|line| line.get(x)).copied()
+        };
+
+        let mut resolved = Vec::with_capacity(moves.len());
+        for &(time, text) in moves {
+            let invalid_move = || ClientErr::Replay(format!("Invalid move '{}'", text));
+
+            let mv = if let Some(wall) = text.strip_prefix('#') {
+                let tile = parse_coords(wall).ok_or_else(invalid_move)?;
+                if holder(&tiles, tile) != Some(Entity::None) {
+                    return Err(invalid_move());
+                }
+                tiles[tile.0][tile.1] = Entity::Wall;
+                ReplayMove::Wall(tile)
+            } else {
+                let (from, to) = text.split_once('>').ok_or_else(invalid_move)?;
+                let to = parse_coords(to).ok_or_else(invalid_move)?;
+                let from = if from.is_empty() {
+                    // Without an origin the closest mouse moves
+                    (0..tiles.len())
+                        .flat_map(|y| (0..tiles[y].len()).map(move |x| (y, x)))
+                        .filter(|&tile| holder(&tiles, tile) == Some(Entity::Mouse))
+                        .min_by_key(|&tile| offset_distance(tile, to))
+                        .ok_or_else(invalid_move)?
+                } else {
+                    parse_coords(from).ok_or_else(invalid_move)?
+                };
+
+                if holder(&tiles, from) != Some(Entity::Mouse)
+                    || holder(&tiles, to) != Some(Entity::None)
+                {
+                    return Err(invalid_move());
+                }
+                tiles[from.0][from.1] = Entity::None;
+                tiles[to.0][to.1] = Entity::Mouse;
+                ReplayMove::Mouse { from, to }
+            };
+
+            resolved.push((time, mv));
+        }
+
+        Ok(resolved)
+    }
+
+    fn side_names(&self, side: PlayerType) -> String {
+        let names: Vec<&str> = self
+            .players
+            .iter()
+            .filter(|(player_side, _)| *player_side == side)
+            .map(|(_, name)| name.as_str())
+            .collect();
+
+        if names.is_empty() {
+            "?".to_string()
+        } else {
+            names.join(" & ")
+        }
+    }
+}
+
+/// Steps through a replay on the regular board, forwards, backwards or on its own.
+pub struct ReplayViewer {
+    replay: Replay,
+    pub grid: Grid,
+    /// Number of moves shown on the board.
+    step: usize,
+    playing: bool,
+    speed: usize,
+    last_step: f64,
+    pub closed: bool,
+    buttons: [Button; 6],
+}
+
+impl ReplayViewer {
+    pub async fn new(replay: Replay) -> Self {
+        let mut grid = Grid::new(replay.width, replay.height);
+        for (y, line) in replay.tiles.iter().enumerate() {
+            for (x, &entity) in line.iter().enumerate() {
+                grid.place_entity(y, x, entity as u8);
+            }
+        }
+        grid.load_textures().await;
+
+        let button = |button_type, x: f32, width: f32, text: &str| {
+            Button::new(
+                button_type,
+                Vec2::new(x, 48.0),
+                Vec2::new(width, 40.0),
+                text.to_string(),
+                Color::from_hex(0x6498D99),
+                false,
+            )
+        };
+        let buttons = [
+            button(ButtonType::ReplayBack, 16.0, 48.0, "<"),
+            button(ButtonType::ReplayPlay, 72.0, 96.0, "Play"),
+            button(ButtonType::ReplayForward, 176.0, 48.0, ">"),
+            button(ButtonType::ReplaySlower, 240.0, 48.0, "-"),
+            button(ButtonType::ReplayFaster, 296.0, 48.0, "+"),
+            button(ButtonType::ReplayClose, 360.0, 112.0, "Close"),
+        ];
+
+        Self {
+            replay,
+            grid,
+            step: 0,
+            playing: false,
+            speed: DEFAULT_SPEED,
+            last_step: 0.0,
+            closed: false,
+            buttons,
+        }
+    }
+
+    fn forward(&mut self) -> bool {
+        let Some(&(_, mv)) = self.replay.moves.get(self.step) else {
+            return false;
+        };
+
+        match mv {
+            ReplayMove::Wall(tile) => {
+                self.grid.apply_wall(tile);
+            }
+            ReplayMove::Mouse { from, to } => {
+                self.grid.apply_mouse(from, to);
+            }
+        }
+        self.step += 1;
+        true
+    }
+
+    fn back(&mut self) {
+        if self.step == 0 {
+            return;
+        }
+
+        self.step -= 1;
+        match self.replay.moves[self.step].1 {
+            ReplayMove::Wall((y, x)) => self.grid.place_entity(y, x, Entity::None as u8),
+            ReplayMove::Mouse { from, to } => {
+                self.grid.apply_mouse(to, from);
+            }
+        }
+    }
+
+    fn toggle_play(&mut self) {
+        // Playing from the last move starts over
+        if !self.playing && self.step == self.replay.moves.len() {
+            while self.step > 0 {
+                self.back();
+            }
+        }
+
+        self.playing = !self.playing;
+        self.last_step = get_time();
+    }
+
+    fn handle_button(&mut self, button_type: ButtonType) {
+        match button_type {
+            ButtonType::ReplayBack => {
+                self.playing = false;
+                self.back();
+            }
+            ButtonType::ReplayForward => {
+                self.playing = false;
+                self.forward();
+            }
+            ButtonType::ReplayPlay => self.toggle_play(),
+            ButtonType::ReplaySlower => self.speed = self.speed.saturating_sub(1),
+            ButtonType::ReplayFaster => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            ButtonType::ReplayClose => self.closed = true,
+            _ => {}
+        }
+    }
+
+    pub fn handle_input(&mut self) {
+        let mouse_pos: Vec2 = mouse_position().into();
+        for button in self.buttons.iter_mut() {
+            button.highlighted = button.is_inside(mouse_pos);
+        }
+
+        let mut clicked = [
+            (KeyCode::Left, ButtonType::ReplayBack),
+            (KeyCode::Right, ButtonType::ReplayForward),
+            (KeyCode::Space, ButtonType::ReplayPlay),
+            (KeyCode::Down, ButtonType::ReplaySlower),
+            (KeyCode::Up, ButtonType::ReplayFaster),
+            (KeyCode::Escape, ButtonType::ReplayClose),
+        ]
+        .into_iter()
+        .find(|(key, _)| is_key_pressed(*key))
+        .map(|(_, button_type)| button_type);
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            clicked = clicked.or_else(|| {
+                self.buttons
+                    .iter()
+                    .find(|button| button.is_inside(mouse_pos))
+                    .map(|button| button.button_type)
+            });
+        }
+
+        if let Some(button_type) = clicked {
+            self.handle_button(button_type);
+        }
+
+        if self.playing && get_time() - self.last_step >= 1.0 / SPEEDS[self.speed] {
+            self.last_step = get_time();
+            self.playing = self.forward();
+        }
+
+        for button in self.buttons.iter_mut() {
+            if button.button_type == ButtonType::ReplayPlay {
+                button.text = if self.playing { "Pause" } else { "Play" }.to_string();
+            }
+        }
+    }
+
+    pub fn render(&self) {
+        self.grid.render();
+
+        for button in &self.buttons {
+            button.render();
+        }
+
+        let replay = &self.replay;
+        draw_text(
+            format!(
+                "{} (mouse) vs {} (trap)",
+                replay.side_names(PlayerType::Mouse),
+                replay.side_names(PlayerType::Wall)
+            ),
+            screen_width() * 0.45,
+            32.0,
+            32.0,
+            Color::from_hex(0xEBF4DD),
+        );
+
+        let elapsed = self
+            .step
+            .checked_sub(1)
+            .map_or(0, |idx| replay.moves[idx].0 / 1000);
+        let mut status = format!(
+            "Move {}/{}  {}:{:02}  {} moves/s",
+            self.step,
+            replay.moves.len(),
+            elapsed / 60,
+            elapsed % 60,
+            SPEEDS[self.speed]
+        );
+        if let Some(seed) = replay.seed {
+            status += &format!("  Seed: {}", seed);
+        }
+        if self.step == replay.moves.len()
+            && let Some((winner, reason)) = &replay.result
+        {
+            let winner = match winner {
+                PlayerType::Mouse => "The mouse wins",
+                PlayerType::Wall => "The trapper wins",
+            };
+            status += &format!("  {} ({})", winner, reason);
+        }
+
+        draw_text(
+            &status,
+            screen_width() * 0.20,
+            screen_height() - 24.0,
+            28.0,
+            Color::from_hex(0xEBF4DD),
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Preferences of the player, kept as `key = value` lines in the user's config directory.
+//! Unknown keys and broken values are skipped so older and newer clients can share the file.
+
+use std::{env, fs, path::PathBuf};
+
+use crate::app::ClientErr;
+use crate::menu::PlayerType;
+
+pub const DEFAULT_SERVER: &str = "127.0.0.1:1922";
+const SETTINGS_DIR: &str = "trap-the-mouse";
+const SETTINGS_FILE: &str = "settings.txt";
+const MAX_RECENT_SERVERS: usize = 5;
+/// Smallest window restored from the settings, anything less is likely a mistake.
+const MIN_WINDOW: u32 = 320;
+
+#[derive(Clone, PartialEq)]
+pub struct Settings {
+    pub username: String,
+    pub player_type: PlayerType,
+    /// The server of the last session.
+    pub server: String,
+    /// Servers connected to before, most recent first.
+    pub recent_servers: Vec<String>,
+    pub window_width: u32,
+    pub window_height: u32,
+    /// Distance overlay while playing.
+    pub analysis: bool,
+    pub show_fps: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            username: "Guest".to_string(),
+            player_type: PlayerType::Mouse,
+            server: DEFAULT_SERVER.to_string(),
+            recent_servers: Vec::new(),
+            window_width: 1048,
+            window_height: 904,
+            analysis: true,
+            show_fps: true,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME` when set, otherwise the usual place of the platform.
+fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(dir.into());
+    }
+
+    if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(SETTINGS_DIR).join(SETTINGS_FILE))
+    }
+
+    /// Falls back to the defaults when there is no settings file yet.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map_or_else(Self::default, |text| Self::parse(&text))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim() {
+                "username" if !value.is_empty() => settings.username = value.to_string(),
+                "role" => match value {
+                    "mouse" => settings.player_type = PlayerType::Mouse,
+                    "wall" => settings.player_type = PlayerType::Wall,
+                    _ => {}
+                },
+                "server" if !value.is_empty() => settings.server = value.to_string(),
+                "recent_server" if settings.recent_servers.len() < MAX_RECENT_SERVERS => {
+                    settings.recent_servers.push(value.to_string())
+                }
+                "window_width" => {
+                    if let Ok(width) = value.parse::<u32>() {
+                        settings.window_width = width.max(MIN_WINDOW);
+                    }
+                }
+                "window_height" => {
+                    if let Ok(height) = value.parse::<u32>() {
+                        settings.window_height = height.max(MIN_WINDOW);
+                    }
+                }
+                "analysis" => {
+                    if let Ok(analysis) = value.parse() {
+                        settings.analysis = analysis;
+                    }
+                }
+                "show_fps" => {
+                    if let Ok(show_fps) = value.parse() {
+                        settings.show_fps = show_fps;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        settings
+    }
+
+    pub fn format(&self) -> String {
+        let role = match self.player_type {
+            PlayerType::Mouse => "mouse",
+            PlayerType::Wall => "wall",
+        };
+
+        let mut lines = vec![
+            format!("username = {}", self.username),
+            format!("role = {}", role),
+            format!("server = {}", self.server),
+            format!("window_width = {}", self.window_width),
+            format!("window_height = {}", self.window_height),
+            format!("analysis = {}", self.analysis),
+            format!("show_fps = {}", self.show_fps),
+        ];
+        for server in &self.recent_servers {
+            lines.push(format!("recent_server = {}", server));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Makes the server the one to try first next time.
+    pub fn remember_server(&mut self, address: &str) {
+        self.server = address.to_string();
+        self.recent_servers.retain(|server| server != address);
+        self.recent_servers.insert(0, address.to_string());
+        self.recent_servers.truncate(MAX_RECENT_SERVERS);
+    }
+
+    pub fn save(&self) -> Result<(), ClientErr> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.format())?;
+
+        Ok(())
+    }
+}
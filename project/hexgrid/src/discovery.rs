@@ -0,0 +1,13 @@
+//! Layout of the LAN discovery datagrams, the client sends the probes and the server answers
+//! them.
+
+pub const DISCOVERY_PORT: u16 = 1923;
+/// Starts every discovery datagram, anything else arriving on the port is ignored.
+pub const MAGIC: &[u8; 4] = b"TTMD";
+pub const PROBE: u8 = 0;
+pub const ANNOUNCE: u8 = 1;
+pub const MAX_SERVER_NAME: usize = 64;
+/// Header, port, name and version, then the two player counts.
+pub const MAX_ANNOUNCEMENT: usize = MAGIC.len() + 1 + 2 + 2 * (4 + MAX_SERVER_NAME) + 4 + 4;
+/// Servers ignore probes smaller than their largest answer.
+pub const PROBE_SIZE: usize = MAX_ANNOUNCEMENT;
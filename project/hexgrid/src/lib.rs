@@ -0,0 +1,167 @@
+//! Hex geometry shared by the server and the client, along with the board hash both of them
+//! have to compute the same way and the layout of the discovery datagrams.
+//!
+//! Boards are stored in offset coordinates `(row, col)` where odd columns are shifted down by half
+//! a cell. The client draws pointy-top hexes with board columns as screen rows, so a column is a
+//! horizontal line of hexes on screen.
+
+pub mod discovery;
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// Largest board width or height, both sides refuse anything bigger before allocating it.
+pub const MAX_BOARD_SIZE: usize = 1024;
+
+/// Axial coordinates, `q` follows the board columns and `r` the rows.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl Hex {
+    pub fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    pub fn from_offset(row: i32, col: i32) -> Self {
+        Self {
+            q: col,
+            r: row - (col - (col & 1)) / 2,
+        }
+    }
+
+    pub fn to_offset(self) -> (i32, i32) {
+        (self.r + (self.q - (self.q & 1)) / 2, self.q)
+    }
+
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Self {
+        debug_assert_eq!(q + r + s, 0);
+        Self { q, r }
+    }
+
+    pub fn to_cube(self) -> (i32, i32, i32) {
+        (self.q, self.r, -self.q - self.r)
+    }
+
+    /// Same order as [`offset_neighbours`].
+    pub fn neighbours(self) -> [Hex; 6] {
+        let (row, col) = self.to_offset();
+        offset_neighbours(row, col).map(|(r, c)| Hex::from_offset(r, c))
+    }
+
+    pub fn distance(self, other: Hex) -> u32 {
+        let (aq, ar, as_) = self.to_cube();
+        let (bq, br, bs) = other.to_cube();
+        ((aq - bq).abs() + (ar - br).abs() + (as_ - bs).abs()) as u32 / 2
+    }
+
+    /// All hexes exactly `radius` steps away, the center itself for a radius of 0.
+    pub fn ring(self, radius: u32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![self];
+        }
+
+        // Walk around the ring starting from the corner straight "up" in axial space
+        const DIRECTIONS: [(i32, i32); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+        let radius = radius as i32;
+        let mut hex = Hex::new(
+            self.q + DIRECTIONS[4].0 * radius,
+            self.r + DIRECTIONS[4].1 * radius,
+        );
+
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        for (dq, dr) in DIRECTIONS {
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = Hex::new(hex.q + dq, hex.r + dr);
+            }
+        }
+        ring
+    }
+
+    /// Every hex within `radius` steps, ring by ring.
+    pub fn spiral(self, radius: u32) -> Vec<Hex> {
+        (0..=radius).flat_map(|k| self.ring(k)).collect()
+    }
+
+    /// Center of the hex on screen, relative to the center of `(0, 0)`.
+    pub fn to_pixel(self, radius: f32) -> (f32, f32) {
+        let x = radius * SQRT_3 * (self.r as f32 + self.q as f32 / 2.0);
+        let y = radius * 1.5 * self.q as f32;
+        (x, y)
+    }
+
+    /// The hex containing the point, the inverse of [`Hex::to_pixel`].
+    pub fn from_pixel(x: f32, y: f32, radius: f32) -> Self {
+        let q = y / (1.5 * radius);
+        let r = x / (radius * SQRT_3) - q / 2.0;
+        Self::round(q, r, -q - r)
+    }
+
+    fn round(q: f32, r: f32, s: f32) -> Self {
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+        // Fix the coordinate with the biggest rounding error so q + r + s stays 0
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+
+        Self::new(rq as i32, rr as i32)
+    }
+}
+
+/// Offsets of the six neighbours of a cell, they depend on the parity of the column.
+pub fn offset_neighbours(row: i32, col: i32) -> [(i32, i32); 6] {
+    let diagonal = if col & 1 == 0 { -1 } else { 1 };
+    [
+        (row - 1, col),
+        (row + 1, col),
+        (row, col - 1),
+        (row, col + 1),
+        (row + diagonal, col - 1),
+        (row + diagonal, col + 1),
+    ]
+}
+
+pub fn offset_distance(a: (usize, usize), b: (usize, usize)) -> u32 {
+    Hex::from_offset(a.0 as i32, a.1 as i32).distance(Hex::from_offset(b.0 as i32, b.1 as i32))
+}
+
+pub fn offset_to_pixel(row: usize, col: usize, radius: f32) -> (f32, f32) {
+    Hex::from_offset(row as i32, col as i32).to_pixel(radius)
+}
+
+/// Picks the cell under a point, `None` when it falls before the first row or column.
+pub fn pixel_to_offset(x: f32, y: f32, radius: f32) -> Option<(usize, usize)> {
+    let (row, col) = Hex::from_pixel(x, y, radius).to_offset();
+    (row >= 0 && col >= 0).then_some((row as usize, col as usize))
+}
+
+/// The splitmix64 step, spreads nearby values over the whole range.
+pub fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Zobrist key of an entity, by its wire id, on the tile with the given row major index. The keys
+/// are derived rather than drawn from a table, free tiles (id 0) hash to zero.
+pub fn zobrist_key(index: usize, entity: u8) -> u64 {
+    if entity == 0 {
+        return 0;
+    }
+    splitmix64((index as u64) << 2 | entity as u64)
+}
+
+/// Hash of a whole board given its entity ids in row major order.
+pub fn zobrist_hash(entities: impl IntoIterator<Item = u8>) -> u64 {
+    entities
+        .into_iter()
+        .enumerate()
+        .fold(0, |hash, (i, entity)| hash ^ zobrist_key(i, entity))
+}
@@ -0,0 +1,92 @@
+use hexgrid::{Hex, offset_neighbours, offset_to_pixel, pixel_to_offset};
+
+const SIZE: i32 = 24;
+const RADIUS: f32 = 32.0;
+
+fn cells() -> impl Iterator<Item = (i32, i32)> {
+    (0..SIZE).flat_map(|row| (0..SIZE).map(move |col| (row, col)))
+}
+
+#[test]
+fn offset_round_trips() {
+    for (row, col) in cells() {
+        let hex = Hex::from_offset(row, col);
+        assert_eq!(hex.to_offset(), (row, col));
+
+        let (q, r, s) = hex.to_cube();
+        assert_eq!(q + r + s, 0);
+        assert_eq!(Hex::from_cube(q, r, s), hex);
+    }
+}
+
+#[test]
+fn neighbours_are_one_step_away() {
+    for (row, col) in cells() {
+        let hex = Hex::from_offset(row, col);
+        let neighbours = hex.neighbours();
+
+        for (idx, other) in neighbours.iter().enumerate() {
+            assert_eq!(hex.distance(*other), 1);
+            assert!(!neighbours[idx + 1..].contains(other));
+        }
+        assert_eq!(hex.ring(1).len(), 6);
+        assert!(hex.ring(1).iter().all(|h| neighbours.contains(h)));
+    }
+}
+
+#[test]
+fn rings_have_six_k_hexes() {
+    let center = Hex::from_offset(SIZE / 2, SIZE / 2);
+    for radius in 1..8 {
+        let ring = center.ring(radius);
+        assert_eq!(ring.len(), 6 * radius as usize);
+        assert!(ring.iter().all(|h| center.distance(*h) == radius));
+    }
+    assert_eq!(center.spiral(3).len(), 1 + 6 + 12 + 18);
+}
+
+#[test]
+fn picking_finds_the_drawn_cell() {
+    for (row, col) in cells() {
+        let (x, y) = offset_to_pixel(row as usize, col as usize, RADIUS);
+
+        // Anywhere inside the inscribed circle belongs to the cell
+        for step in 0..12 {
+            let angle = step as f32 * std::f32::consts::TAU / 12.0;
+            let inner = RADIUS * 0.8;
+            let picked = pixel_to_offset(x + inner * angle.cos(), y + inner * angle.sin(), RADIUS);
+            assert_eq!(picked, Some((row as usize, col as usize)));
+        }
+    }
+}
+
+/// The server moves the mouse with `offset_neighbours` while the client lays out and picks tiles
+/// from pixels, both have to agree on which tiles touch.
+#[test]
+fn server_adjacency_matches_client_layout() {
+    let spacing = RADIUS * 3.0_f32.sqrt();
+
+    for (row, col) in cells() {
+        let (x, y) = offset_to_pixel(row as usize, col as usize, RADIUS);
+        let neighbours = offset_neighbours(row, col);
+
+        for (other_row, other_col) in cells() {
+            if (other_row, other_col) == (row, col) {
+                continue;
+            }
+
+            let (ox, oy) = offset_to_pixel(other_row as usize, other_col as usize, RADIUS);
+            let touching = ((ox - x).hypot(oy - y) - spacing).abs() < 0.01;
+            assert_eq!(touching, neighbours.contains(&(other_row, other_col)));
+
+            if touching {
+                // Just past the shared edge the client picks the neighbour
+                let (px, py) = (x + (ox - x) * 0.6, y + (oy - y) * 0.6);
+                assert_eq!(
+                    pixel_to_offset(px, py, RADIUS),
+                    Some((other_row as usize, other_col as usize))
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+
+use crate::archive::BOT_NAME;
+
+pub const ACCOUNTS_FILE: &str = "accounts.txt";
+pub const MIN_NAME: usize = 3;
+pub const MAX_NAME: usize = 16;
+pub const MIN_PASSWORD: usize = 6;
+pub const MAX_PASSWORD: usize = 64;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountErr {
+    #[error("Names need {MIN_NAME} to {MAX_NAME} letters, digits, '-' or '_'")]
+    InvalidName,
+
+    #[error("Passwords need {MIN_PASSWORD} to {MAX_PASSWORD} bytes")]
+    InvalidPassword,
+
+    #[error("The name {0} is already registered")]
+    Taken(String),
+
+    #[error("Wrong name or password")]
+    WrongCredentials,
+
+    #[error("Broken account entry '{0}'")]
+    Corrupt(String),
+
+    #[error("Hashing failed ({0})")]
+    Hash(argon2::password_hash::Error),
+
+    #[error("Io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Registered players, one `name<TAB>argon2 hash` line each. The hash string carries its own
+/// salt and parameters so old entries keep working if the defaults change.
+pub struct Accounts {
+    path: PathBuf,
+    hashes: HashMap<String, String>,
+}
+
+impl Accounts {
+    /// A missing file is an empty store, it gets created with the first registration.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, AccountErr> {
+        let path = path.into();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut hashes = HashMap::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, hash) = line
+                .split_once('\t')
+                .ok_or_else(|| AccountErr::Corrupt(line.to_string()))?;
+            hashes.insert(name.to_string(), hash.to_string());
+        }
+
+        Ok(Self { path, hashes })
+    }
+
+    /// Names are unique regardless of case so nobody can pose as "Alice" by registering "alice".
+    pub fn find(&self, name: &str) -> Option<(&str, &str)> {
+        self.hashes
+            .iter()
+            .find(|(registered, _)| registered.eq_ignore_ascii_case(name))
+            .map(|(name, hash)| (name.as_str(), hash.as_str()))
+    }
+
+    /// Stores an account whose password was hashed with `hash_password`.
+    pub fn insert(&mut self, name: &str, hash: String) -> Result<(), AccountErr> {
+        validate_name(name)?;
+        // The bot's games are archived under its name
+        if name.eq_ignore_ascii_case(BOT_NAME) {
+            return Err(AccountErr::Taken(BOT_NAME.to_string()));
+        }
+        if let Some((registered, _)) = self.find(name) {
+            return Err(AccountErr::Taken(registered.to_string()));
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}\t{}", name, hash)?;
+
+        self.hashes.insert(name.to_string(), hash);
+        Ok(())
+    }
+}
+
+pub fn validate_name(name: &str) -> Result<(), AccountErr> {
+    let valid = (MIN_NAME..=MAX_NAME).contains(&name.chars().count())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AccountErr::InvalidName)
+    }
+}
+
+/// Salted argon2 hash in the PHC string format. Slow on purpose, keep it out of the state lock.
+pub fn hash_password(password: &str) -> Result<String, AccountErr> {
+    if !(MIN_PASSWORD..=MAX_PASSWORD).contains(&password.len()) {
+        return Err(AccountErr::InvalidPassword);
+    }
+
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>()).map_err(AccountErr::Hash)?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(AccountErr::Hash)
+}
+
+pub fn verify_password(hash: &str, password: &str) -> Result<(), AccountErr> {
+    let hash = PasswordHash::new(hash).map_err(AccountErr::Hash)?;
+
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .map_err(|_| AccountErr::WrongCredentials)
+}
@@ -0,0 +1,124 @@
+use crate::grid::Grid;
+use crate::room::PlayerType;
+use hexgrid::offset_distance;
+use rand::{Rng, RngCore};
+
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn mouse_move(&self, grid: &Grid, rng: &mut dyn RngCore) -> Option<(usize, usize)>;
+
+    fn wall_move(&self, grid: &Grid, rng: &mut dyn RngCore) -> Option<(usize, usize)>;
+
+    fn choose(
+        &self,
+        grid: &Grid,
+        role: PlayerType,
+        rng: &mut dyn RngCore,
+    ) -> Option<(usize, usize)> {
+        match role {
+            PlayerType::Mouse => self.mouse_move(grid, rng),
+            PlayerType::Wall => self.wall_move(grid, rng),
+        }
+    }
+}
+
+/// Picks any free tile next to the mouse, for both roles.
+pub struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn mouse_move(&self, grid: &Grid, rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+        pick(&grid.mouse_moves(), rng)
+    }
+
+    /// Falls back to any legal tile when the rules keep walls away from the mouse.
+    fn wall_move(&self, grid: &Grid, rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+        let near: Vec<(usize, usize)> = grid
+            .mouse_moves()
+            .into_iter()
+            .filter(|&(y, x)| grid.can_place_wall(y, x))
+            .collect();
+
+        pick(&near, rng).or_else(|| pick(&grid.wall_moves(), rng))
+    }
+}
+
+fn pick(tiles: &[(usize, usize)], rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+    if tiles.is_empty() {
+        None
+    } else {
+        Some(tiles[rng.random_range(0..tiles.len())])
+    }
+}
+
+/// The mouse follows the shortest path to the edge, the trapper blocks that step.
+pub struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn mouse_move(&self, grid: &Grid, _rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+        let dist_map = grid.get_distance_map();
+
+        grid.mouse_moves()
+            .into_iter()
+            .min_by_key(|&(i, j)| dist_map[i][j])
+    }
+
+    /// When that step cannot take a wall, blocks the closest legal tile to a mouse that is
+    /// nearest to the edge.
+    fn wall_move(&self, grid: &Grid, rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+        if let Some((y, x)) = self.mouse_move(grid, rng)
+            && grid.can_place_wall(y, x)
+        {
+            return Some((y, x));
+        }
+
+        let dist_map = grid.get_distance_map();
+        grid.wall_moves().into_iter().min_by_key(|&(i, j)| {
+            let near = grid
+                .mice()
+                .iter()
+                .map(|&m| offset_distance((i, j), m))
+                .min();
+            (near, dist_map[i][j])
+        })
+    }
+}
+
+pub struct Evaluation {
+    pub escape_distance: Option<u32>,
+    pub open_routes: u32,
+}
+
+/// Escape distance of the closest mouse and how many neighbours of the mice still lead to the
+/// edge.
+pub fn evaluate(grid: &Grid) -> Evaluation {
+    let dist_map = grid.get_distance_map();
+
+    let open_routes = grid
+        .mice()
+        .iter()
+        .flat_map(|&(mi, mj)| grid.get_valid_neighbours(mi, mj))
+        .filter(|&(i, j)| dist_map[i][j] != i32::MAX)
+        .count() as u32;
+
+    Evaluation {
+        escape_distance: grid.escape_distance(),
+        open_routes,
+    }
+}
+
+pub fn strategies() -> Vec<Box<dyn Strategy>> {
+    vec![Box::new(RandomStrategy), Box::new(GreedyStrategy)]
+}
+
+pub fn find_strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    strategies().into_iter().find(|s| s.name() == name)
+}
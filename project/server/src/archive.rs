@@ -0,0 +1,323 @@
+//! Finished games, appended to a plain text log. Every game is a block of lines:
+//!
+//! ```text
+//! game <id>
+//! started <unix time in ms>
+//! seed <seed>
+//! rules <walls per turn> <mouse steps> <keep away 0|1>
+//! position <initial position in notation>
+//! player <m|w> <name>
+//! move <ms since the start> <move in notation>
+//! result <winning side m|w> <trapped|escaped|forfeit>
+//! end
+//! ```
+//!
+//! There is one `player` line per seat, the bot of a practice game is called `Bot`. Logged in
+//! players are listed under their account, guests under their name followed by ` (guest)`. Account
+//! names have no spaces, so a guest can never take over the history of an account or the bot.
+//!
+//! A replay file is a single game record preceded by a `replay <version>` line, currently
+//! `replay 1`. Other tools may produce replays too: moves are played in order from the `position`
+//! (the trapper moves first), mouse moves should name the mouse they move and readers skip lines
+//! they do not know.
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use uuid::Uuid;
+
+use crate::notation::{Move, format_move, parse_move};
+use crate::room::PlayerType;
+use crate::rules::Rules;
+
+pub const ARCHIVE_FILE: &str = "games.txt";
+pub const BOT_NAME: &str = "Bot";
+pub const GUEST_SUFFIX: &str = " (guest)";
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArchiveErr {
+    #[error("Broken game record at '{0}'")]
+    Corrupt(String),
+
+    #[error("Io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EndReason {
+    Trapped,
+    Escaped,
+    /// The side of the player who left the running game.
+    Forfeit(PlayerType),
+}
+
+impl EndReason {
+    pub fn winner(&self) -> PlayerType {
+        match self {
+            EndReason::Trapped => PlayerType::Wall,
+            EndReason::Escaped => PlayerType::Mouse,
+            EndReason::Forfeit(side) => side.opponent(),
+        }
+    }
+
+    /// Wire id, the same order as the names in the archive.
+    pub fn id(&self) -> u8 {
+        match self {
+            EndReason::Trapped => 0,
+            EndReason::Escaped => 1,
+            EndReason::Forfeit(_) => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            EndReason::Trapped => "trapped",
+            EndReason::Escaped => "escaped",
+            EndReason::Forfeit(_) => "forfeit",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameRecord {
+    pub id: Uuid,
+    /// Unix time in milliseconds.
+    pub started: u64,
+    pub seed: u64,
+    pub rules: Rules,
+    pub position: String,
+    pub players: Vec<(PlayerType, String)>,
+    /// Moves with the milliseconds since the start of the game.
+    pub moves: Vec<(u64, Move)>,
+    pub reason: EndReason,
+}
+
+/// Marks a name as belonging to a guest, once.
+pub fn guest_name(name: &str) -> String {
+    if name.ends_with(GUEST_SUFFIX) {
+        name.to_string()
+    } else {
+        format!("{}{}", name, GUEST_SUFFIX)
+    }
+}
+
+pub fn side_char(side: PlayerType) -> char {
+    match side {
+        PlayerType::Mouse => 'm',
+        PlayerType::Wall => 'w',
+    }
+}
+
+pub fn parse_side(text: &str) -> Option<PlayerType> {
+    match text {
+        "m" => Some(PlayerType::Mouse),
+        "w" => Some(PlayerType::Wall),
+        _ => None,
+    }
+}
+
+impl GameRecord {
+    pub fn format(&self) -> String {
+        let mut lines = vec![
+            format!("game {}", self.id),
+            format!("started {}", self.started),
+            format!("seed {}", self.seed),
+            format!(
+                "rules {} {} {}",
+                self.rules.walls_per_turn,
+                self.rules.mouse_steps,
+                self.rules.no_wall_near_mouse as u8
+            ),
+            format!("position {}", self.position),
+        ];
+
+        for (side, name) in &self.players {
+            // Names are free text, they only have to stay on their line
+            let name = name.replace(['\n', '\r'], " ");
+            lines.push(format!("player {} {}", side_char(*side), name));
+        }
+        for (time, mv) in &self.moves {
+            lines.push(format!("move {} {}", time, format_move(mv)));
+        }
+        lines.push(format!(
+            "result {} {}",
+            side_char(self.reason.winner()),
+            self.reason.name()
+        ));
+        lines.push("end".to_string());
+
+        lines.join("\n") + "\n"
+    }
+
+    /// The game as a replay file.
+    pub fn format_replay(&self) -> String {
+        format!("replay {}\n{}", REPLAY_VERSION, self.format())
+    }
+
+    pub fn parse_replay(text: &str) -> Result<Self, ArchiveErr> {
+        let (header, record) = text.split_once('\n').unwrap_or((text, ""));
+        if header.trim_end() != format!("replay {}", REPLAY_VERSION) {
+            return Err(ArchiveErr::Corrupt(header.to_string()));
+        }
+
+        Self::parse(record)
+    }
+
+    /// Reads a single game, from its `game` line up to `end`.
+    pub fn parse(text: &str) -> Result<Self, ArchiveErr> {
+        let mut records = parse_records(text)?;
+        match (records.pop(), records.is_empty()) {
+            (Some(record), true) => Ok(record),
+            _ => Err(ArchiveErr::Corrupt(
+                text.lines().next().unwrap_or("").to_string(),
+            )),
+        }
+    }
+
+    fn parse_lines(lines: &[&str]) -> Option<Self> {
+        let mut id = None;
+        let mut started = None;
+        let mut seed = None;
+        let mut rules = None;
+        let mut position = None;
+        let mut reason = None;
+        let mut players = Vec::new();
+        let mut moves = Vec::new();
+
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "game" => id = Some(Uuid::parse_str(value).ok()?),
+                "started" => started = Some(value.parse().ok()?),
+                "seed" => seed = Some(value.parse().ok()?),
+                "rules" => {
+                    let values: Vec<u8> = value
+                        .split(' ')
+                        .map(|v| v.parse().ok())
+                        .collect::<Option<_>>()?;
+                    let [walls_per_turn, mouse_steps, keep_away] = values.try_into().ok()?;
+                    rules = Some(Rules {
+                        walls_per_turn,
+                        mouse_steps,
+                        no_wall_near_mouse: keep_away != 0,
+                    });
+                }
+                "position" => position = Some(value.to_string()),
+                "player" => {
+                    let (side, name) = value.split_once(' ')?;
+                    players.push((parse_side(side)?, name.to_string()));
+                }
+                "move" => {
+                    let (time, mv) = value.split_once(' ')?;
+                    moves.push((time.parse().ok()?, parse_move(mv).ok()?));
+                }
+                "result" => {
+                    let (winner, name) = value.split_once(' ')?;
+                    let winner = parse_side(winner)?;
+                    reason = Some(match name {
+                        "trapped" => EndReason::Trapped,
+                        "escaped" => EndReason::Escaped,
+                        "forfeit" => EndReason::Forfeit(winner.opponent()),
+                        _ => return None,
+                    });
+                }
+                "end" => {}
+                _ => return None,
+            }
+        }
+
+        Some(Self {
+            id: id?,
+            started: started?,
+            seed: seed?,
+            rules: rules?,
+            position: position?,
+            players,
+            moves,
+            reason: reason?,
+        })
+    }
+}
+
+/// Splits a log into its games, every game has to be closed by an `end` line.
+pub fn parse_records(text: &str) -> Result<Vec<GameRecord>, ArchiveErr> {
+    let mut records = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        lines.push(line);
+        if line == "end" {
+            let record = GameRecord::parse_lines(&lines)
+                .ok_or_else(|| ArchiveErr::Corrupt(lines[0].to_string()))?;
+            records.push(record);
+            lines.clear();
+        }
+    }
+
+    match lines.first() {
+        Some(line) => Err(ArchiveErr::Corrupt(line.to_string())),
+        None => Ok(records),
+    }
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+pub struct Archive {
+    path: PathBuf,
+    games: Vec<GameRecord>,
+}
+
+impl Archive {
+    /// A missing file is an empty archive.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, ArchiveErr> {
+        let path = path.into();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            games: parse_records(&text)?,
+            path,
+        })
+    }
+
+    /// Appends the game in a single write so a crash cannot leave half a record behind.
+    pub fn record(&mut self, game: GameRecord) -> Result<(), ArchiveErr> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(game.format().as_bytes())?;
+
+        self.games.push(game);
+        Ok(())
+    }
+
+    /// Every archived game, oldest first.
+    pub fn games(&self) -> &[GameRecord] {
+        &self.games
+    }
+
+    pub fn find(&self, id: &Uuid) -> Option<&GameRecord> {
+        self.games.iter().find(|game| game.id == *id)
+    }
+
+    /// Latest games the player took part in, newest first.
+    pub fn recent(&self, name: &str, count: usize) -> Vec<&GameRecord> {
+        self.games
+            .iter()
+            .rev()
+            .filter(|game| game.players.iter().any(|(_, player)| player == name))
+            .take(count)
+            .collect()
+    }
+}
@@ -0,0 +1,39 @@
+use server::solver::{Solver, describe, parse_position};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("Usage: solve <position file> [max nodes]");
+        std::process::exit(1);
+    };
+
+    let mut solver = match args.next().map(|n| n.parse::<u64>()) {
+        Some(Ok(max_nodes)) => Solver::new(max_nodes),
+        Some(Err(e)) => {
+            eprintln!("Invalid node limit ({})", e);
+            std::process::exit(1);
+        }
+        None => Solver::default(),
+    };
+
+    let (grid, to_move) = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse_position(&text))
+    {
+        Ok(position) => position,
+        Err(e) => {
+            eprintln!("Failed reading position {} ({})", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    match solver.solve(&grid, to_move) {
+        Some(solution) => {
+            println!("{}", describe(&solution.outcome, to_move));
+            if let Some((y, x)) = solution.best_move {
+                println!("Best move: row {}, column {}", y, x);
+            }
+        }
+        None => println!("Unknown, search stopped after {} nodes", solver.nodes()),
+    }
+}
@@ -0,0 +1,113 @@
+use server::ai::{find_strategy, strategies};
+use server::room::RoomSettings;
+use server::shape::Boards;
+use server::tournament::{MatchStats, Options, run, write_csv, write_json};
+use std::fs;
+
+fn usage() -> String {
+    let names: Vec<&str> = strategies().iter().map(|s| s.name()).collect();
+    format!(
+        "Usage: tournament [--games N] [--seed S] [--width W] [--height H] [--walls N] \
+         [--strategies a,b,..] [--csv FILE] [--json FILE]\nAvailable strategies: {}",
+        names.join(", ")
+    )
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        games: 1000,
+        seed: 0,
+        width: 11,
+        height: 11,
+        walls: 5,
+        strategies: strategies(),
+        csv: None,
+        json: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+
+        match arg.as_str() {
+            "--games" => options.games = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--seed" => options.seed = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--width" => options.width = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--height" => options.height = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--walls" => options.walls = value()?.parse().map_err(|e| format!("{}", e))?,
+            "--strategies" => {
+                options.strategies = value()?
+                    .split(',')
+                    .map(|name| find_strategy(name).ok_or(format!("Unknown strategy {}", name)))
+                    .collect::<Result<_, _>>()?
+            }
+            "--csv" => options.csv = Some(value()?),
+            "--json" => options.json = Some(value()?),
+            "--help" | "-h" => return Err(usage()),
+            _ => return Err(format!("Unknown argument {}\n{}", arg, usage())),
+        }
+    }
+
+    RoomSettings {
+        width: options.width,
+        height: options.height,
+        walls: options.walls,
+        ..Default::default()
+    }
+    .validate(&Boards::default())
+    .map_err(|e| e.to_string())?;
+
+    if options.strategies.len() < 2 {
+        return Err("At least two strategies are needed".to_string());
+    }
+
+    Ok(options)
+}
+
+fn print_report(results: &[MatchStats]) {
+    println!(
+        "{:<12} {:<12} {:<6} {:>7} {:>7} {:>18} {:>9}",
+        "strategy", "opponent", "role", "games", "win %", "95% CI", "avg len"
+    );
+
+    for stats in results {
+        let (low, high) = stats.confidence_interval();
+        println!(
+            "{:<12} {:<12} {:<6} {:>7} {:>6.1}% {:>8.1}% - {:>5.1}% {:>9.2}",
+            stats.strategy,
+            stats.opponent,
+            stats.role_name(),
+            stats.games,
+            stats.win_rate() * 100.0,
+            low * 100.0,
+            high * 100.0,
+            stats.avg_length()
+        );
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let results = run(&options);
+    print_report(&results);
+
+    if let Some(path) = &options.csv
+        && let Err(e) = fs::File::create(path).and_then(|mut file| write_csv(&mut file, &results))
+    {
+        eprintln!("Failed writing csv file {} ({})", path, e);
+    }
+
+    if let Some(path) = &options.json
+        && let Err(e) =
+            fs::File::create(path).and_then(|mut file| write_json(&mut file, &options, &results))
+    {
+        eprintln!("Failed writing json file {} ({})", path, e);
+    }
+}
@@ -0,0 +1,437 @@
+//! Server configuration. Settings are read from the config file first, then from environment
+//! variables and finally from command line flags, each overriding the ones before. The file is a
+//! small subset of TOML: `key = value` lines with strings, integers, booleans and lists of
+//! strings, grouped under `[section]` headers.
+//!
+//! ```toml
+//! bind = "0.0.0.0:1922"
+//!
+//! [board]           # public rooms opened by the server
+//! width = 11
+//! height = 11
+//! walls = 5
+//!
+//! [rooms]
+//! idle_public = 2   # empty public rooms kept open
+//! boards = "boards" # custom board files, relative to this file
+//!
+//! [limits]          # 0 for no limit
+//! max_rooms = 0
+//! max_users = 0
+//!
+//! [timeouts]        # in seconds
+//! rejoin = 120
+//! snapshot = 5
+//!
+//! [ai]
+//! mouse = "greedy"  # "none" turns the bot off for that side
+//! wall = "random"
+//! hints = true
+//!
+//! [discovery]       # answers LAN probes from private addresses
+//! enabled = true
+//! name = "Trap the Mouse"
+//! port = 1923
+//! ```
+
+use std::fs;
+use std::io::ErrorKind;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::ai::{find_strategy, strategies};
+use crate::discovery::{DISCOVERY_PORT, MAX_SERVER_NAME};
+use crate::room::{PlayerType, RoomSettings, SettingsErr};
+use crate::shape::{BOARDS_DIR, BoardShape, Boards, ShapeErr};
+
+pub const CONFIG_FILE: &str = "server.toml";
+pub const DEFAULT_BIND: &str = "0.0.0.0:1922";
+pub const BIND_ENV: &str = "TRAP_BIND";
+pub const CONFIG_ENV: &str = "TRAP_CONFIG";
+pub const DEFAULT_SERVER_NAME: &str = "Trap the Mouse";
+
+pub const USAGE: &str = "Usage: server [--config <file>] [--bind <address:port>]
+
+  --config <file>   Config file, defaults to server.toml ($TRAP_CONFIG)
+  --bind <address>  Address and port to listen on, defaults to 0.0.0.0:1922 ($TRAP_BIND)
+  --help            Show this message";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigErr {
+    #[error("{0}:{1}: {2}")]
+    Syntax(String, usize, String),
+
+    #[error("Unknown setting '{0}'")]
+    UnknownKey(String),
+
+    #[error("'{0}' must be {1}")]
+    InvalidValue(String, &'static str),
+
+    #[error("Unknown flag '{0}', see --help")]
+    UnknownFlag(String),
+
+    #[error("Missing value after {0}")]
+    MissingValue(String),
+
+    #[error("Cannot bind to '{0}', expected an address and a port")]
+    InvalidBind(String),
+
+    #[error("Invalid [board] settings: {0}")]
+    Board(#[from] SettingsErr),
+
+    #[error("Unknown AI '{0}', expected one of: {1}")]
+    UnknownAi(String, String),
+
+    #[error("The server name must have between 1 and {MAX_SERVER_NAME} bytes")]
+    InvalidName,
+
+    #[error("Cannot load the boards in {0} ({1})")]
+    Boards(String, ShapeErr),
+
+    #[error("Cannot read {0} ({1})")]
+    IO(String, std::io::Error),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn as_str(&self, key: &str) -> Result<&str, ConfigErr> {
+        match self {
+            Value::Str(text) => Ok(text),
+            _ => Err(ConfigErr::InvalidValue(key.to_string(), "a string")),
+        }
+    }
+
+    fn as_bool(&self, key: &str) -> Result<bool, ConfigErr> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            _ => Err(ConfigErr::InvalidValue(key.to_string(), "true or false")),
+        }
+    }
+
+    fn as_count(&self, key: &str) -> Result<usize, ConfigErr> {
+        match self {
+            Value::Int(value) if *value >= 0 => Ok(*value as usize),
+            _ => Err(ConfigErr::InvalidValue(
+                key.to_string(),
+                "a whole number of at least 0",
+            )),
+        }
+    }
+
+    fn as_byte(&self, key: &str) -> Result<u8, ConfigErr> {
+        match self {
+            Value::Int(value) if (0..=u8::MAX as i64).contains(value) => Ok(*value as u8),
+            _ => Err(ConfigErr::InvalidValue(
+                key.to_string(),
+                "a small whole number",
+            )),
+        }
+    }
+
+    fn as_port(&self, key: &str) -> Result<u16, ConfigErr> {
+        match self {
+            Value::Int(value) if (1..=u16::MAX as i64).contains(value) => Ok(*value as u16),
+            _ => Err(ConfigErr::InvalidValue(
+                key.to_string(),
+                "a port between 1 and 65535",
+            )),
+        }
+    }
+
+    fn as_seconds(&self, key: &str) -> Result<Duration, ConfigErr> {
+        match self {
+            Value::Int(value) if *value > 0 => Ok(Duration::from_secs(*value as u64)),
+            _ => Err(ConfigErr::InvalidValue(
+                key.to_string(),
+                "a positive number of seconds",
+            )),
+        }
+    }
+}
+
+/// Parses a config file into its settings, keys of sections are prefixed like `section.key`.
+pub fn parse_config(text: &str, source: &str) -> Result<Vec<(String, Value)>, ConfigErr> {
+    let mut settings = Vec::new();
+    let mut section = String::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let err =
+            |message: &str| ConfigErr::Syntax(source.to_string(), idx + 1, message.to_string());
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| err("unclosed section header"))?;
+            section = name.trim().to_string();
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| err("expected key = value"))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(err("missing key"));
+        }
+
+        let value = parse_value(value.trim()).ok_or_else(|| err("invalid value"))?;
+        let key = if section.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", section, key)
+        };
+        settings.push((key, value));
+    }
+
+    Ok(settings)
+}
+
+/// Cuts a `#` comment off the line, unless it is part of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_string(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    (!inner.contains('"')).then(|| inner.to_string())
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(items) = text.strip_prefix('[') {
+        let items = items.strip_suffix(']')?.trim();
+        if items.is_empty() {
+            return Some(Value::List(Vec::new()));
+        }
+
+        return items
+            .split(',')
+            .map(|item| parse_string(item.trim()))
+            .collect::<Option<_>>()
+            .map(Value::List);
+    }
+
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ if text.starts_with('"') => parse_string(text).map(Value::Str),
+        _ => text.replace('_', "").parse().ok().map(Value::Int),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServerConfig {
+    pub bind: String,
+    /// Settings of the public rooms the server opens by itself.
+    pub board: RoomSettings,
+    /// How many empty public rooms are kept open for players to join.
+    pub idle_rooms: usize,
+    /// Directory of the custom board files, next to the config file unless it is absolute.
+    pub boards_dir: PathBuf,
+    /// The boards found there, loaded along with the config.
+    pub boards: Boards,
+    pub max_rooms: Option<usize>,
+    pub max_users: Option<usize>,
+    /// How long restored seats wait for their players before they count as left.
+    pub rejoin_timeout: Duration,
+    pub snapshot_interval: Duration,
+    /// Strategy of the bot on each side, mice first, `None` when there is no bot for it.
+    pub bots: [Option<String>; 2],
+    pub hints: bool,
+    /// Whether the server answers discovery probes, under its name on the discovery port.
+    pub discovery: bool,
+    pub name: String,
+    pub discovery_port: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: DEFAULT_BIND.to_string(),
+            board: RoomSettings::default(),
+            idle_rooms: 2,
+            boards_dir: PathBuf::from(BOARDS_DIR),
+            boards: Boards::default(),
+            max_rooms: None,
+            max_users: None,
+            rejoin_timeout: Duration::from_secs(120),
+            snapshot_interval: Duration::from_secs(5),
+            bots: [Some("greedy".to_string()), Some("random".to_string())],
+            hints: true,
+            discovery: true,
+            name: DEFAULT_SERVER_NAME.to_string(),
+            discovery_port: DISCOVERY_PORT,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Reads the config file, the environment and the flags. Only the default config file may be
+    /// missing, one named on purpose has to exist.
+    pub fn load(args: &[String], env: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigErr> {
+        let flags = parse_flags(args)?;
+
+        let named = flags
+            .iter()
+            .rev()
+            .find(|(flag, _)| flag == "--config")
+            .map(|(_, path)| path.clone())
+            .or_else(|| env(CONFIG_ENV));
+        let path = named
+            .clone()
+            .map_or_else(|| PathBuf::from(CONFIG_FILE), PathBuf::from);
+
+        let mut config = match fs::read_to_string(&path) {
+            Ok(text) => Self::from_file(&text, &path)?,
+            Err(e) if e.kind() == ErrorKind::NotFound && named.is_none() => Self::default(),
+            Err(e) => return Err(ConfigErr::IO(path.display().to_string(), e)),
+        };
+
+        if let Some(bind) = env(BIND_ENV) {
+            config.bind = bind;
+        }
+        for (flag, value) in flags {
+            if flag == "--bind" {
+                config.bind = value;
+            }
+        }
+
+        config.boards = Boards::load(&config.boards_dir)
+            .map_err(|e| ConfigErr::Boards(config.boards_dir.display().to_string(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(text: &str, path: &Path) -> Result<Self, ConfigErr> {
+        let mut config = Self::default();
+        for (key, value) in parse_config(text, &path.display().to_string())? {
+            config.set(&key, &value)?;
+        }
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.boards_dir = base.join(&config.boards_dir);
+        Ok(config)
+    }
+
+    fn set(&mut self, key: &str, value: &Value) -> Result<(), ConfigErr> {
+        // Zero turns a limit off
+        let limit = |value: &Value| value.as_count(key).map(|max| (max > 0).then_some(max));
+        let bot = |value: &Value| {
+            value
+                .as_str(key)
+                .map(|name| (name != "none").then(|| name.to_string()))
+        };
+
+        match key {
+            "bind" => self.bind = value.as_str(key)?.to_string(),
+            "board.width" => self.board.width = value.as_count(key)?,
+            "board.height" => self.board.height = value.as_count(key)?,
+            "board.walls" => self.board.walls = value.as_count(key)?,
+            "board.mice" => self.board.mice = value.as_count(key)?,
+            "board.shape" => {
+                self.board.shape = match value.as_str(key)? {
+                    "rectangle" => BoardShape::Rectangle,
+                    "hexagon" => BoardShape::Hexagon,
+                    name => BoardShape::Mask(name.to_string()),
+                }
+            }
+            "board.walls_per_turn" => self.board.rules.walls_per_turn = value.as_byte(key)?,
+            "board.mouse_steps" => self.board.rules.mouse_steps = value.as_byte(key)?,
+            "board.no_wall_near_mouse" => {
+                self.board.rules.no_wall_near_mouse = value.as_bool(key)?
+            }
+            "rooms.idle_public" => self.idle_rooms = value.as_count(key)?,
+            "rooms.boards" => self.boards_dir = PathBuf::from(value.as_str(key)?),
+            "limits.max_rooms" => self.max_rooms = limit(value)?,
+            "limits.max_users" => self.max_users = limit(value)?,
+            "timeouts.rejoin" => self.rejoin_timeout = value.as_seconds(key)?,
+            "timeouts.snapshot" => self.snapshot_interval = value.as_seconds(key)?,
+            "ai.mouse" => self.bots[0] = bot(value)?,
+            "ai.wall" => self.bots[1] = bot(value)?,
+            "ai.hints" => self.hints = value.as_bool(key)?,
+            "discovery.enabled" => self.discovery = value.as_bool(key)?,
+            "discovery.name" => self.name = value.as_str(key)?.to_string(),
+            "discovery.port" => self.discovery_port = value.as_port(key)?,
+            _ => return Err(ConfigErr::UnknownKey(key.to_string())),
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigErr> {
+        if self.bind.to_socket_addrs().is_err() {
+            return Err(ConfigErr::InvalidBind(self.bind.clone()));
+        }
+
+        self.board.validate(&self.boards)?;
+
+        if self.name.is_empty() || self.name.len() > MAX_SERVER_NAME {
+            return Err(ConfigErr::InvalidName);
+        }
+
+        for name in self.bots.iter().flatten() {
+            if find_strategy(name).is_none() {
+                let known: Vec<&str> = strategies().iter().map(|s| s.name()).collect();
+                return Err(ConfigErr::UnknownAi(name.clone(), known.join(", ")));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Port the games are played on, announced to the clients that discover the server.
+    pub fn port(&self) -> Option<u16> {
+        self.bind
+            .to_socket_addrs()
+            .ok()?
+            .next()
+            .map(|addr| addr.port())
+    }
+
+    /// Name of the strategy the bot plays `side` with.
+    pub fn bot(&self, side: PlayerType) -> Option<&str> {
+        match side {
+            PlayerType::Mouse => self.bots[0].as_deref(),
+            PlayerType::Wall => self.bots[1].as_deref(),
+        }
+    }
+}
+
+/// Flags with their values, in the order they were given.
+fn parse_flags(args: &[String]) -> Result<Vec<(String, String)>, ConfigErr> {
+    let mut flags = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" | "--bind" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| ConfigErr::MissingValue(arg.clone()))?;
+                flags.push((arg.clone(), value.clone()));
+            }
+            _ => return Err(ConfigErr::UnknownFlag(arg.clone())),
+        }
+    }
+
+    Ok(flags)
+}
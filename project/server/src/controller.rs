@@ -1,9 +1,26 @@
-use crate::room::{PlayerType, Room, TurnResult};
-use std::collections::HashMap;
+use crate::accounts::{
+    ACCOUNTS_FILE, AccountErr, Accounts, hash_password, validate_name, verify_password,
+};
+use crate::ai::find_strategy;
+use crate::archive::{ARCHIVE_FILE, Archive, ArchiveErr, BOT_NAME, EndReason, guest_name};
+use crate::config::ServerConfig;
+use crate::daily::{DAILY_FILE, DAILY_MOUSE, DailyErr, DailyLeaderboard, daily_seed, today};
+use crate::discovery::{self, Announcement};
+use crate::encoding::write_varint;
+use crate::notation::{Move, format_move};
+use crate::ratings::{RATINGS_FILE, Rating, RatingErr, Ratings};
+use crate::room::{PlayerType, Room, RoomSettings, Seats, TurnResult};
+use crate::rules::Rules;
+use crate::shape::{BoardShape, Boards};
+use crate::snapshot::{self, RoomSnapshot, SNAPSHOT_FILE, SeatSnapshot, SnapshotErr};
+use crate::stats::PlayerStats;
+use std::collections::{HashMap, HashSet};
 use std::{
     io::{Read, Write},
-    net::{TcpListener, TcpStream},
+    net::{TcpListener, TcpStream, UdpSocket},
+    path::Path,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 use uuid::Uuid;
 
@@ -20,28 +37,100 @@ enum Protocol {
     WaitTurn,
     YourTurn,
     GameOver,
+    RequestHint,
+    Hint,
+    HintDenied,
+    CreateRoom,
+    StartDaily,
+    RequestDaily,
+    RequestBoards,
+    RequestPosition,
+    RequestAnalysis,
+    Analysis,
+    AnalysisDenied,
+    Register,
+    Login,
+    Logout,
+    AuthSuccess,
+    AuthFail,
+    RequestRatings,
+    RequestRecentGames,
+    RequestGame,
+    RequestLeaderboard,
+    RequestProfile,
+    RequestSession,
+    Rejoin,
+    RoomSeats,
 }
 
+const MAX_BOARD_NAME: usize = 64;
+const RECENT_GAMES: usize = 10;
+const LEADERBOARD_SIZE: usize = 10;
+/// Upper bound on names and passwords read from the wire, the account rules are stricter.
+const MAX_CREDENTIAL: usize = 256;
+/// Pause after a failed read on the discovery socket, so a broken socket does not spin.
+const PROBE_ERROR_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(thiserror::Error, Debug)]
 pub enum ServerErr {
     #[error("Unknown Command")]
     UnknownCommand,
 
+    #[error("Accounts error: {0}")]
+    Accounts(#[from] AccountErr),
+
+    #[error("Ratings error: {0}")]
+    Ratings(#[from] RatingErr),
+
+    #[error("Archive error: {0}")]
+    Archive(#[from] ArchiveErr),
+
+    #[error("Snapshot error: {0}")]
+    Snapshot(#[from] SnapshotErr),
+
+    #[error("Daily leaderboard error: {0}")]
+    Daily(#[from] DailyErr),
+
     #[error("Io error: {0}")]
     IO(#[from] std::io::Error),
 }
 
 struct User {
     pub stream: TcpStream,
+    /// Secret id the client can use to take its seat back after a server restart.
+    session: Uuid,
     username: String,
+    /// Name of the account the user logged into, guests have none.
+    account: Option<String>,
     pub room: Option<Uuid>,
 }
 
+/// A seat of a restored room whose player did not come back yet, the room holds it under the
+/// session id.
+struct AbsentSeat {
+    room: Uuid,
+    username: String,
+    account: Option<String>,
+    since: Instant,
+}
+
+const DAILY_LEADERBOARD_SIZE: usize = 10;
+
 struct ServerState {
     users: HashMap<Uuid, User>,
     rooms: HashMap<Uuid, Room>,
+    /// Restored seats by session id.
+    absent: HashMap<Uuid, AbsentSeat>,
+    daily: DailyLeaderboard,
+    accounts: Accounts,
+    ratings: Ratings,
+    archive: Archive,
+    config: ServerConfig,
 }
 
+/// New rating and the change it made, per player of a rated game.
+type RatingChanges = HashMap<Uuid, (Rating, f64)>;
+
 impl ServerState {
     fn add_user(&mut self, stream: TcpStream) -> Uuid {
         let id = Uuid::new_v4();
@@ -50,7 +139,9 @@ impl ServerState {
             id,
             User {
                 stream,
-                username: "guest".to_string(),
+                session: Uuid::new_v4(),
+                username: "Guest".to_string(),
+                account: None,
                 room: None,
             },
         );
@@ -62,6 +153,154 @@ impl ServerState {
         self.users.remove(id)
     }
 
+    /// Only accounts make it onto the board, guest names are neither unique nor protected.
+    pub fn record_daily(&mut self, id: &Uuid, day: u64, walls: u32) {
+        let Some(user) = self.users.get(id) else {
+            return;
+        };
+        println!(
+            "User [{}] trapped the daily mouse with {} walls",
+            user.username, walls
+        );
+
+        if let Some(account) = &user.account
+            && let Err(e) = self.daily.record(day, account, walls)
+        {
+            eprintln!("Could not record the daily result ({})", e);
+        }
+    }
+
+    /// Name of whoever holds a seat, connected or not.
+    fn seat_name(&self, id: &Uuid) -> String {
+        match (self.users.get(id), self.absent.get(id)) {
+            (Some(user), _) => user.username.clone(),
+            (None, Some(seat)) => seat.username.clone(),
+            (None, None) => String::from("Guest"),
+        }
+    }
+
+    fn seat_account(&self, id: &Uuid) -> Option<&String> {
+        match (self.users.get(id), self.absent.get(id)) {
+            (Some(user), _) => user.account.as_ref(),
+            (None, Some(seat)) => seat.account.as_ref(),
+            (None, None) => None,
+        }
+    }
+
+    /// Name the games, statistics and ratings of a seat are kept under.
+    fn seat_key(&self, id: &Uuid) -> String {
+        match self.seat_account(id) {
+            Some(account) => account.clone(),
+            None => guest_name(&self.seat_name(id)),
+        }
+    }
+
+    /// Tells the players of a room that has not started yet how many seats each side has and how
+    /// many of them are free, mice first.
+    fn send_seats(&mut self, room_id: &Uuid) {
+        let Some(room) = self.rooms.get(room_id) else {
+            return;
+        };
+
+        let mut data = vec![Protocol::RoomSeats as u8];
+        for side in [PlayerType::Mouse, PlayerType::Wall] {
+            data.extend([room.seats.for_side(side), room.free_seats(side)]);
+        }
+        for (pid, _) in room.players.clone() {
+            if let Some(player) = self.users.get_mut(&pid) {
+                player.stream.write_all(&data).ok();
+            }
+        }
+    }
+
+    /// Names of the other side of the room, joined for display.
+    fn opponent_names(&self, room: &Room, uid: &Uuid) -> String {
+        let side = room.get_player_type(uid);
+        let opponents: Vec<String> = room
+            .players
+            .iter()
+            .filter(|(_, ptype)| Some(*ptype) != side)
+            .map(|(id, _)| self.seat_name(id))
+            .collect();
+        opponents.join(" & ")
+    }
+
+    /// Every room someone sits in, empty rooms are simply created again.
+    fn snapshot(&self) -> Vec<RoomSnapshot> {
+        self.rooms
+            .iter()
+            .filter(|(_, room)| room.get_player_count() > 0)
+            .map(|(room_id, room)| {
+                let players = room
+                    .players
+                    .iter()
+                    .map(|(id, side)| SeatSnapshot {
+                        session: self.users.get(id).map_or(*id, |user| user.session),
+                        side: *side,
+                        account: self.seat_account(id).cloned(),
+                        name: self.seat_name(id),
+                    })
+                    .collect();
+                room.snapshot(*room_id, players)
+            })
+            .collect()
+    }
+
+    /// Puts the saved rooms back, their seats wait for the players to rejoin. Games that made it
+    /// into the archive after the snapshot was taken stay finished.
+    fn restore(&mut self, rooms: Vec<RoomSnapshot>) {
+        for snapshot in rooms {
+            if self.archive.find(&snapshot.id).is_some() {
+                continue;
+            }
+
+            let mut room = match Room::restore(&snapshot, &self.config.boards) {
+                Ok(room) => room,
+                Err(e) => {
+                    eprintln!("Could not restore the room [{}] ({})", snapshot.id, e);
+                    continue;
+                }
+            };
+
+            for side in [PlayerType::Mouse, PlayerType::Wall] {
+                if let Some(strategy) = self.config.bot(side).and_then(find_strategy) {
+                    room.set_bot(side, strategy);
+                }
+            }
+
+            for seat in snapshot.players {
+                self.absent.insert(
+                    seat.session,
+                    AbsentSeat {
+                        room: snapshot.id,
+                        username: seat.name,
+                        account: seat.account,
+                        since: Instant::now(),
+                    },
+                );
+            }
+            println!("Restored the room [{}]", snapshot.id);
+            self.rooms.insert(snapshot.id, room);
+        }
+    }
+
+    /// Players connected and players sitting in a running game.
+    fn player_counts(&self) -> (u32, u32) {
+        let playing = self
+            .users
+            .values()
+            .filter_map(|user| user.room.and_then(|room_id| self.rooms.get(&room_id)))
+            .filter(|room| !room.is_available())
+            .count();
+        (self.users.len() as u32, playing as u32)
+    }
+
+    fn is_full(&self) -> bool {
+        self.config
+            .max_users
+            .is_some_and(|max| self.users.len() >= max)
+    }
+
     pub fn get_user_room(&self, id: &Uuid) -> Option<Uuid> {
         if let Some(user) = self.users.get(id) {
             user.room
@@ -69,6 +308,106 @@ impl ServerState {
             None
         }
     }
+
+    /// Removes a room, started games that came to an end are archived and rated.
+    fn close_room(&mut self, room_id: &Uuid, reason: Option<EndReason>) -> RatingChanges {
+        let Some(room) = self.rooms.remove(room_id) else {
+            return HashMap::new();
+        };
+        let Some(reason) = reason.filter(|_| !room.is_available()) else {
+            return HashMap::new();
+        };
+
+        let mut players: Vec<(PlayerType, String)> = room
+            .players
+            .iter()
+            .map(|(id, side)| (*side, self.seat_key(id)))
+            .collect();
+        for side in [PlayerType::Mouse, PlayerType::Wall] {
+            if room.seats.for_side(side) == 0 {
+                players.push((side, BOT_NAME.to_string()));
+            }
+        }
+
+        if let Err(e) = self.archive.record(room.record(*room_id, players, reason)) {
+            eprintln!("Could not archive the game [{}] ({})", room_id, e);
+        }
+
+        self.rate_game(&room.players, reason.winner())
+    }
+
+    /// Games only count when every player is logged in, each player is rated for the side they
+    /// played against the average of the other side.
+    fn rate_game(&mut self, players: &[(Uuid, PlayerType)], winner: PlayerType) -> RatingChanges {
+        let mut changes = HashMap::new();
+
+        let accounts: Option<Vec<(Uuid, PlayerType, String)>> = players
+            .iter()
+            .map(|(id, side)| Some((*id, *side, self.seat_account(id)?.clone())))
+            .collect();
+        let Some(accounts) = accounts else {
+            return changes;
+        };
+
+        let names: HashSet<&String> = accounts.iter().map(|(_, _, name)| name).collect();
+        let both_sides = [PlayerType::Mouse, PlayerType::Wall]
+            .iter()
+            .all(|side| accounts.iter().any(|(_, s, _)| s == side));
+        if names.len() != accounts.len() || !both_sides {
+            return changes;
+        }
+
+        let before: Vec<Rating> = accounts
+            .iter()
+            .map(|(_, side, name)| self.ratings.get(name, *side))
+            .collect();
+        let team = |side: PlayerType| {
+            let ratings: Vec<Rating> = accounts
+                .iter()
+                .zip(&before)
+                .filter(|((_, s, _), _)| *s == side)
+                .map(|(_, rating)| *rating)
+                .collect();
+            Rating::team(&ratings)
+        };
+        let teams = [team(PlayerType::Mouse), team(PlayerType::Wall)];
+
+        for ((id, side, name), old) in accounts.iter().zip(before.iter()) {
+            let opponent = match side {
+                PlayerType::Mouse => &teams[1],
+                PlayerType::Wall => &teams[0],
+            };
+            let score = if *side == winner { 1.0 } else { 0.0 };
+            let new = old.update(opponent, score);
+
+            if let Err(e) = self.ratings.record(name, *side, new) {
+                eprintln!("Could not save the rating of [{}] ({})", name, e);
+            }
+            println!(
+                "User [{}] {:?} rating {:.0} -> {:.0}",
+                name, side, old.rating, new.rating
+            );
+            changes.insert(*id, (new, new.rating - old.rating));
+        }
+
+        changes
+    }
+
+    /// Average rating of the logged in players on one side of a room, 0 when there are none.
+    fn side_rating(&self, room: &Room, side: PlayerType) -> u32 {
+        let ratings: Vec<Rating> = room
+            .side_players(side)
+            .iter()
+            .filter_map(|id| self.seat_account(id))
+            .map(|name| self.ratings.get(name, side))
+            .collect();
+
+        if ratings.is_empty() {
+            0
+        } else {
+            Rating::team(&ratings).rating.round().max(1.0) as u32
+        }
+    }
 }
 
 pub struct Controller {
@@ -77,24 +416,70 @@ pub struct Controller {
 }
 
 impl Controller {
-    pub fn new() -> Result<Self, ServerErr> {
-        let addr = String::from("0.0.0.0:1922");
-        let listener = TcpListener::bind(&addr)?;
+    pub fn new(config: &ServerConfig) -> Result<Self, ServerErr> {
+        let listener = TcpListener::bind(&config.bind)?;
+
+        println!("Server listening on {}", config.bind);
+
+        let mut state = ServerState {
+            users: HashMap::new(),
+            rooms: HashMap::new(),
+            absent: HashMap::new(),
+            daily: DailyLeaderboard::load(DAILY_FILE)?,
+            accounts: Accounts::load(ACCOUNTS_FILE)?,
+            ratings: Ratings::load(RATINGS_FILE)?,
+            archive: Archive::load(ARCHIVE_FILE)?,
+            config: config.clone(),
+        };
 
-        println!("Server listening on {}", addr);
+        // A broken snapshot only costs the running games, the server still starts
+        match snapshot::load(Path::new(SNAPSHOT_FILE)) {
+            Ok(rooms) => state.restore(rooms),
+            Err(e) => eprintln!("Could not restore the rooms ({})", e),
+        }
 
         Ok(Self {
             listener,
-            state: Arc::new(Mutex::new(ServerState {
-                users: HashMap::new(),
-                rooms: HashMap::new(),
-            })),
+            state: Arc::new(Mutex::new(state)),
         })
     }
 
     pub fn run(&mut self) {
-        Self::add_room(2, &self.state);
-        Self::add_room(2, &self.state);
+        Self::open_idle_rooms(&self.state);
+
+        let config = match self.state.lock() {
+            Ok(state_guard) => state_guard.config.clone(),
+            Err(_) => return,
+        };
+
+        let state_clone = Arc::clone(&self.state);
+        let interval = config.snapshot_interval;
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(interval);
+                Self::expire_absent(&state_clone);
+                Self::open_idle_rooms(&state_clone);
+                Self::save_snapshot(&state_clone);
+            }
+        });
+
+        if config.discovery {
+            match UdpSocket::bind(("0.0.0.0", config.discovery_port)) {
+                Ok(socket) => {
+                    println!(
+                        "Answering discovery probes on port {}",
+                        config.discovery_port
+                    );
+                    let state_clone = Arc::clone(&self.state);
+                    std::thread::spawn(move || Self::answer_probes(socket, config, state_clone));
+                }
+                // Games still work without discovery, players type the address instead
+                Err(e) => eprintln!(
+                    "Could not open the discovery port {} ({})",
+                    config.discovery_port, e
+                ),
+            }
+        }
 
         for stream_result in self.listener.incoming() {
             match stream_result {
@@ -102,7 +487,13 @@ impl Controller {
                     let state_clone = Arc::clone(&self.state);
 
                     let uid = match (state_clone.lock(), stream.try_clone()) {
-                        (Ok(mut state_guard), Ok(clone)) => Some(state_guard.add_user(clone)),
+                        (Ok(mut state_guard), Ok(clone)) if !state_guard.is_full() => {
+                            Some(state_guard.add_user(clone))
+                        }
+                        (Ok(_), Ok(_)) => {
+                            println!("Refused a connection, the server is full");
+                            None
+                        }
                         _ => None,
                     };
 
@@ -117,6 +508,40 @@ impl Controller {
         }
     }
 
+    fn answer_probes(socket: UdpSocket, config: ServerConfig, state: Arc<Mutex<ServerState>>) {
+        let Some(port) = config.port() else {
+            return;
+        };
+        let mut buf = [0u8; 512];
+
+        loop {
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("Failed to read a discovery probe {}", e);
+                    std::thread::sleep(PROBE_ERROR_DELAY);
+                    continue;
+                }
+            };
+            if !discovery::is_local(from.ip()) || !discovery::is_probe(&buf[..len]) {
+                continue;
+            }
+
+            let (online, playing) = match state.lock() {
+                Ok(state_guard) => state_guard.player_counts(),
+                Err(_) => return,
+            };
+            let announcement = Announcement {
+                port,
+                name: config.name.clone(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                online,
+                playing,
+            };
+            socket.send_to(&announcement.to_bytes(), from).ok();
+        }
+    }
+
     fn handle_user(mut stream: TcpStream, uid: Uuid, state: Arc<Mutex<ServerState>>) {
         let peer_addr = stream
             .peer_addr()
@@ -135,17 +560,36 @@ impl Controller {
         }
 
         println!("User {} disconnected!", uid);
-        let data: Option<(Uuid, Option<Uuid>, Vec<u8>)> = if let Ok(mut state_guard) = state.lock()
+        type EndData = (Uuid, Vec<Uuid>, Vec<u8>, Option<EndReason>);
+        let data: Option<EndData> = if let Ok(mut state_guard) = state.lock()
             && let Some(room_id) = state_guard.get_user_room(&uid)
             && let Some(room) = state_guard.rooms.get_mut(&room_id)
         {
-            Some((room_id, room.get_other_player(&uid), room.get_grid()))
+            let others = room.get_other_players(&uid);
+            if room.is_available() && !others.is_empty() {
+                // The game has not started yet, the others keep waiting for a new player
+                room.remove_player(&uid);
+                state_guard.send_seats(&room_id);
+                None
+            } else {
+                // Leaving a running game forfeits it
+                let reason = room.get_player_type(&uid).map(EndReason::Forfeit);
+                Some((room_id, others, room.get_game_over(), reason))
+            }
         } else {
             None
         };
 
-        if let Some((room_id, other_player, grid_data)) = data
-            && let Err(e) = Self::end_room(&room_id, &mut stream, other_player, grid_data, &state)
+        if let Some((room_id, others, grid_data, reason)) = data
+            && let Err(e) = Self::end_room(
+                &room_id,
+                &uid,
+                &mut stream,
+                &others,
+                grid_data,
+                reason,
+                &state,
+            )
         {
             eprintln!("Error handleing user disconnection from room! ({})", e);
         }
@@ -165,10 +609,43 @@ impl Controller {
             x if x == Protocol::StartRoomBot as u8 => {
                 Self::handle_new_bot_game(stream, uid, state)?
             }
+            x if x == Protocol::CreateRoom as u8 => Self::handle_create_room(stream, uid, state)?,
             x if x == Protocol::JoinRoom as u8 => Self::handle_join(stream, uid, state)?,
-            x if x == Protocol::RequestRooms as u8 => Self::handle_request_rooms(stream, state)?,
+            x if x == Protocol::StartDaily as u8 => Self::handle_start_daily(stream, uid, state)?,
+            x if x == Protocol::RequestRooms as u8 => {
+                Self::handle_request_rooms(stream, uid, state)?
+            }
+            x if x == Protocol::RequestDaily as u8 => Self::handle_request_daily(stream, state)?,
+            x if x == Protocol::RequestBoards as u8 => Self::handle_request_boards(stream, state)?,
+            x if x == Protocol::RequestPosition as u8 => {
+                Self::handle_request_position(stream, uid, state)?
+            }
             x if x == Protocol::RequestTiles as u8 => Self::handle_request_tiles(stream, state)?,
             x if x == Protocol::Turn as u8 => Self::handle_turn(stream, uid, state)?,
+            x if x == Protocol::RequestHint as u8 => Self::handle_request_hint(stream, uid, state)?,
+            x if x == Protocol::Register as u8 => Self::handle_register(stream, uid, state)?,
+            x if x == Protocol::Login as u8 => Self::handle_login(stream, uid, state)?,
+            x if x == Protocol::Logout as u8 => Self::handle_logout(uid, state),
+            x if x == Protocol::RequestAnalysis as u8 => {
+                Self::handle_request_analysis(stream, uid, state)?
+            }
+            x if x == Protocol::RequestRatings as u8 => {
+                Self::handle_request_ratings(stream, uid, state)?
+            }
+            x if x == Protocol::RequestRecentGames as u8 => {
+                Self::handle_request_recent_games(stream, uid, state)?
+            }
+            x if x == Protocol::RequestGame as u8 => Self::handle_request_game(stream, state)?,
+            x if x == Protocol::RequestLeaderboard as u8 => {
+                Self::handle_request_leaderboard(stream, state)?
+            }
+            x if x == Protocol::RequestProfile as u8 => {
+                Self::handle_request_profile(stream, uid, state)?
+            }
+            x if x == Protocol::RequestSession as u8 => {
+                Self::handle_request_session(stream, uid, state)?
+            }
+            x if x == Protocol::Rejoin as u8 => Self::handle_rejoin(stream, uid, state)?,
             _ => return Err(ServerErr::UnknownCommand),
         }
 
@@ -188,6 +665,30 @@ impl Controller {
             PlayerType::Wall
         };
 
+        let settings = Self::read_settings(stream)?;
+
+        let mut lenb = [0u8; 4];
+        stream.read_exact(&mut lenb)?;
+        let len = u32::from_le_bytes(lenb) as usize;
+
+        let mut usernameb = vec![0u8; len];
+        stream.read_exact(&mut usernameb)?;
+
+        let username = String::from_utf8_lossy(&usernameb);
+
+        if !Self::check_settings(stream, &settings, state)? {
+            return Ok(());
+        }
+
+        let username = Self::set_name(uid, &username, state);
+        Self::start_bot_game(stream, uid, &username, player_type, settings, None, state)
+    }
+
+    fn handle_start_daily(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
         let mut lenb = [0u8; 4];
         stream.read_exact(&mut lenb)?;
         let len = u32::from_le_bytes(lenb) as usize;
@@ -197,11 +698,63 @@ impl Controller {
 
         let username = String::from_utf8_lossy(&usernameb);
 
-        Self::set_name(uid, &username, state);
+        let username = Self::set_name(uid, &username, state);
+
+        // Everyone traps the standard mouse on the same board
+        let day = today();
+        Self::start_bot_game(
+            stream,
+            uid,
+            &username,
+            PlayerType::Wall,
+            RoomSettings {
+                seed: Some(daily_seed(day)),
+                ..Default::default()
+            },
+            Some(day),
+            state,
+        )
+    }
+
+    fn start_bot_game(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        username: &str,
+        player_type: PlayerType,
+        settings: RoomSettings,
+        daily: Option<u64>,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        // The daily mouse is fixed, otherwise the server may run without a bot for the side the
+        // player wants to face
+        let bot = if daily.is_some() {
+            find_strategy(DAILY_MOUSE)
+        } else {
+            match state.lock() {
+                Ok(state_guard) => state_guard
+                    .config
+                    .bot(player_type.opponent())
+                    .and_then(find_strategy),
+                Err(_) => None,
+            }
+        };
 
-        if let Some(room_id) = Self::add_room(1, state) {
+        if let Some(bot) = bot
+            && let Some(room_id) = Self::add_room(Seats::solo(player_type), true, settings, state)
+        {
             Self::add_user_to_room(uid, &room_id, &player_type, state);
 
+            if let Ok(mut state_guard) = state.lock()
+                && let Some(room) = state_guard.rooms.get_mut(&room_id)
+            {
+                room.set_bot(player_type.opponent(), bot);
+                if daily.is_some() {
+                    room.daily = daily;
+                    room.hints = false;
+                    room.analysis = false;
+                }
+            }
+
             let successb: [u8; 1] = [Protocol::JoinSuccess as u8];
             stream.write_all(&successb)?;
             stream.write_all(&room_id.to_bytes_le())?;
@@ -213,15 +766,80 @@ impl Controller {
             stream.write_all(&(opp_name.len() as u32).to_le_bytes())?;
             stream.write_all(opp_name.as_bytes())?;
 
-            if player_type == PlayerType::Mouse
-                && let Ok(mut state_guard) = state.lock()
+            if let Ok(mut state_guard) = state.lock()
                 && let Some(room) = state_guard.rooms.get_mut(&room_id)
             {
-                room.ai_turn();
+                let logged = room.history().len();
+                if player_type == PlayerType::Mouse {
+                    room.ai_turn();
+                    Self::log_moves(&room_id, room, logged, false);
+                }
+
+                stream.write_all(&Self::turn_update(Protocol::YourTurn, room, logged))?;
             }
+        } else {
+            stream.write_all(&[Protocol::JoinFail as u8])?;
+        }
+        Ok(())
+    }
+
+    fn handle_create_room(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let mut typeb = [0u8; 1];
+        stream.read_exact(&mut typeb)?;
+        let player_type = if typeb[0] == 0 {
+            PlayerType::Mouse
+        } else {
+            PlayerType::Wall
+        };
+
+        let settings = Self::read_settings(stream)?;
+
+        let mut seatsb = [0u8; 2];
+        stream.read_exact(&mut seatsb)?;
+        let seats = Seats {
+            mice: seatsb[0],
+            trappers: seatsb[1],
+        };
+
+        let mut hintsb = [0u8; 1];
+        stream.read_exact(&mut hintsb)?;
+
+        let mut lenb = [0u8; 4];
+        stream.read_exact(&mut lenb)?;
+        let len = u32::from_le_bytes(lenb) as usize;
 
-            stream.write_all(&[Protocol::YourTurn as u8])?;
+        let mut usernameb = vec![0u8; len];
+        stream.read_exact(&mut usernameb)?;
+
+        let username = String::from_utf8_lossy(&usernameb);
+
+        if !Self::check_settings(stream, &settings, state)? {
+            return Ok(());
+        }
+        if let Err(e) = seats.validate(&settings) {
+            println!("Refused room seats ({})", e);
+            stream.write_all(&[Protocol::JoinFail as u8])?;
+            return Ok(());
+        }
+
+        let username = Self::set_name(uid, &username, state);
+
+        if let Some(room_id) = Self::add_room(seats, hintsb[0] != 0, settings, state) {
+            Self::add_user_to_room(uid, &room_id, &player_type, state);
+
+            stream.write_all(&[Protocol::JoinSuccess as u8])?;
+            stream.write_all(&room_id.to_bytes_le())?;
+
+            println!("User [{}] created the room [{}]", username, room_id);
+            Self::check_start_room(&room_id, state)?;
+        } else {
+            stream.write_all(&[Protocol::JoinFail as u8])?;
         }
+
         Ok(())
     }
 
@@ -251,11 +869,8 @@ impl Controller {
 
         let username = String::from_utf8_lossy(&usernameb);
 
-        if let Ok(state_guard) = state.lock()
-            && let Some(room) = state_guard.rooms.get(&room_id)
-            && room.get_player_count() == 1
-            && room.players[0].1 == player_type
-        {
+        let username = Self::set_name(uid, &username, state);
+        if !Self::add_user_to_room(uid, &room_id, &player_type, state) {
             let failb: [u8; 1] = [Protocol::JoinFail as u8];
             stream.write_all(&failb)?;
 
@@ -263,9 +878,6 @@ impl Controller {
             return Ok(());
         }
 
-        Self::set_name(uid, &username, state);
-        Self::add_user_to_room(uid, &room_id, &player_type, state);
-
         let successb: [u8; 1] = [Protocol::JoinSuccess as u8];
         stream.write_all(&successb)?;
         println!("User [{}] joined the room [{}]", username, room_id);
@@ -275,97 +887,370 @@ impl Controller {
         Ok(())
     }
 
-    fn check_start_room(room_id: &Uuid, state: &Arc<Mutex<ServerState>>) -> Result<(), ServerErr> {
-        let player_ids = if let Ok(state_guard) = state.lock()
-            && let Some(room) = state_guard.rooms.get(room_id)
-            && room.get_player_count() == 2
+    fn handle_request_session(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let session = if let Ok(state_guard) = state.lock()
+            && let Some(user) = state_guard.users.get(uid)
         {
-            let (p1, ptype1) = room.players[0];
-            let (p2, _) = room.players[1];
-            if ptype1 == PlayerType::Wall {
-                Some((p1, p2))
-            } else {
-                Some((p2, p1))
-            }
+            user.session
         } else {
-            None
+            Uuid::nil()
         };
+        stream.write_all(&session.to_bytes_le())?;
 
-        if let Ok(mut state_guard) = state.lock()
-            && let Some((pid1, pid2)) = player_ids
-        {
-            let p1_name = if let Some(p1) = state_guard.users.get(&pid1) {
-                p1.username.clone()
-            } else {
-                String::from("Guest")
-            };
-            let p2_name = if let Some(p2) = state_guard.users.get(&pid2) {
-                p2.username.clone()
-            } else {
-                String::from("Guest")
-            };
+        Ok(())
+    }
 
-            if let Some(player1) = state_guard.users.get_mut(&pid1) {
-                player1.stream.write_all(&[Protocol::StartGame as u8])?;
-                player1
-                    .stream
-                    .write_all(&(p2_name.len() as u32).to_le_bytes())?;
-                player1.stream.write_all(p2_name.as_bytes())?;
+    /// Gives a restored seat back to its player, who takes over the session, name and account it
+    /// was saved with. A running game continues with a fresh turn update.
+    fn handle_rejoin(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let mut sessionb = [0u8; 16];
+        stream.read_exact(&mut sessionb)?;
+        let session = Uuid::from_bytes_le(sessionb);
 
-                player1.stream.write_all(&[Protocol::YourTurn as u8])?;
+        if let Ok(mut state_guard) = state.lock()
+            && let Some(seat) = state_guard.absent.remove(&session)
+            && let Some(room) = state_guard.rooms.get_mut(&seat.room)
+            && room.replace_player(&session, uid)
+        {
+            let room_id = seat.room;
+            if let Some(user) = state_guard.users.get_mut(uid) {
+                user.session = session;
+                user.username = seat.username.clone();
+                user.account = seat.account;
+                user.room = Some(room_id);
             }
-            if let Some(player2) = state_guard.users.get_mut(&pid2) {
-                player2.stream.write_all(&[Protocol::StartGame as u8])?;
-                player2
-                    .stream
-                    .write_all(&(p1_name.len() as u32).to_le_bytes())?;
-                player2.stream.write_all(p1_name.as_bytes())?;
 
-                player2.stream.write_all(&[Protocol::WaitTurn as u8])?;
-            }
-        }
+            stream.write_all(&[Protocol::JoinSuccess as u8])?;
+            stream.write_all(&room_id.to_bytes_le())?;
+            println!("User [{}] rejoined the room [{}]", seat.username, room_id);
 
-        if player_ids.is_some() {
-            Self::add_room(2, state);
+            if let Some(room) = state_guard.rooms.get(&room_id)
+                && !room.is_available()
+            {
+                let opp_name = if room.max_players == 1 {
+                    "BOT".to_string()
+                } else {
+                    state_guard.opponent_names(room, uid)
+                };
+                stream.write_all(&[Protocol::StartGame as u8])?;
+                stream.write_all(&(opp_name.len() as u32).to_le_bytes())?;
+                stream.write_all(opp_name.as_bytes())?;
+
+                let turn = if room.current_player() == Some(*uid) {
+                    Protocol::YourTurn
+                } else {
+                    Protocol::WaitTurn
+                };
+                stream.write_all(&Self::turn_update(turn, room, room.history().len()))?;
+            } else {
+                state_guard.send_seats(&room_id);
+            }
+        } else {
+            stream.write_all(&[Protocol::JoinFail as u8])?;
         }
 
         Ok(())
     }
 
-    fn handle_request_rooms(
-        stream: &mut TcpStream,
-        state: &Arc<Mutex<ServerState>>,
-    ) -> Result<(), ServerErr> {
-        if let Ok(state_guard) = state.lock() {
-            let mut rooms_buf: Vec<u8> = Vec::new();
+    fn save_snapshot(state: &Arc<Mutex<ServerState>>) {
+        let rooms = match state.lock() {
+            Ok(state_guard) => state_guard.snapshot(),
+            Err(_) => return,
+        };
 
-            let mut count: u32 = 0;
-            for (room_id, room) in state_guard.rooms.iter() {
-                if room.is_available() {
-                    let room_idb: [u8; 16] = room_id.to_bytes_le();
-                    rooms_buf.write_all(&room_idb)?;
+        if let Err(e) = snapshot::save(Path::new(SNAPSHOT_FILE), &rooms) {
+            eprintln!("Could not save the snapshot ({})", e);
+        }
+    }
+
+    /// Seats nobody came back for count as left: waiting rooms free them and running games are
+    /// forfeited, or dropped when none of their players returned.
+    fn expire_absent(state: &Arc<Mutex<ServerState>>) {
+        let Ok(mut state_guard) = state.lock() else {
+            return;
+        };
 
-                    let player_countb: [u8; 1] = [room.get_player_count()];
-                    rooms_buf.write_all(&player_countb)?;
+        let expired: Vec<Uuid> = state_guard
+            .absent
+            .iter()
+            .filter(|(_, seat)| seat.since.elapsed() > state_guard.config.rejoin_timeout)
+            .map(|(session, _)| *session)
+            .collect();
 
-                    count += 1;
+        for session in expired {
+            let Some(seat) = state_guard.absent.remove(&session) else {
+                continue;
+            };
+            let Some(room) = state_guard.rooms.get(&seat.room) else {
+                continue;
+            };
+            println!(
+                "User [{}] did not rejoin the room [{}]",
+                seat.username, seat.room
+            );
+
+            let side = room.get_player_type(&session);
+            let running = !room.is_available();
+            let grid_data = room.get_game_over();
+            let others: Vec<Uuid> = room
+                .get_other_players(&session)
+                .into_iter()
+                .filter(|id| state_guard.users.contains_key(id))
+                .collect();
+
+            if !running && !others.is_empty() {
+                if let Some(room) = state_guard.rooms.get_mut(&seat.room) {
+                    room.remove_player(&session);
                 }
+                state_guard.send_seats(&seat.room);
+                continue;
             }
 
-            let mut data: Vec<u8> = Vec::new();
-            let room_countb: [u8; 4] = count.to_le_bytes();
-            data.write_all(&room_countb)?;
-            data.extend(rooms_buf);
-            stream.write_all(&data)?;
+            let reason = side.filter(|_| !others.is_empty()).map(EndReason::Forfeit);
+            let changes = state_guard.close_room(&seat.room, reason);
+            state_guard
+                .absent
+                .retain(|_, absent| absent.room != seat.room);
+
+            for pid in others {
+                if let Some(player) = state_guard.users.get_mut(&pid) {
+                    player
+                        .stream
+                        .write_all(&Self::game_over(&grid_data, changes.get(&pid)))
+                        .ok();
+                }
+            }
         }
-
-        Ok(())
     }
 
-    fn handle_request_tiles(
-        stream: &mut TcpStream,
-        state: &Arc<Mutex<ServerState>>,
-    ) -> Result<(), ServerErr> {
+    /// Starts the game once every seat is taken. Each player learns the names of the other side
+    /// and whether they move first, until then they learn which seats are still free.
+    fn check_start_room(room_id: &Uuid, state: &Arc<Mutex<ServerState>>) -> Result<(), ServerErr> {
+        let mut started = false;
+
+        if let Ok(mut state_guard) = state.lock()
+            && let Some(room) = state_guard.rooms.get(room_id)
+        {
+            if room.is_available() {
+                state_guard.send_seats(room_id);
+                return Ok(());
+            }
+
+            let players = room.players.clone();
+            let current = room.current_player();
+            let logged = room.history().len();
+            let your_turn = Self::turn_update(Protocol::YourTurn, room, logged);
+            let wait_turn = Self::turn_update(Protocol::WaitTurn, room, logged);
+
+            let names: Vec<String> = players
+                .iter()
+                .map(|(pid, _)| state_guard.opponent_names(room, pid))
+                .collect();
+
+            for ((pid, _), opp_name) in players.iter().zip(names) {
+                if let Some(player) = state_guard.users.get_mut(pid) {
+                    player.stream.write_all(&[Protocol::StartGame as u8])?;
+                    player
+                        .stream
+                        .write_all(&(opp_name.len() as u32).to_le_bytes())?;
+                    player.stream.write_all(opp_name.as_bytes())?;
+
+                    let turn = if current == Some(*pid) {
+                        &your_turn
+                    } else {
+                        &wait_turn
+                    };
+                    player.stream.write_all(turn)?;
+                }
+            }
+
+            started = true;
+        }
+
+        if started {
+            Self::open_idle_rooms(state);
+        }
+
+        Ok(())
+    }
+
+    /// Tops the empty public rooms up to the configured number.
+    fn open_idle_rooms(state: &Arc<Mutex<ServerState>>) {
+        let (missing, settings) = match state.lock() {
+            Ok(state_guard) => {
+                let idle = state_guard
+                    .rooms
+                    .values()
+                    .filter(|room| room.seats == Seats::DUEL && room.get_player_count() == 0)
+                    .count();
+                (
+                    state_guard.config.idle_rooms.saturating_sub(idle),
+                    state_guard.config.board.clone(),
+                )
+            }
+            Err(_) => return,
+        };
+
+        for _ in 0..missing {
+            if Self::add_room(Seats::DUEL, false, settings.clone(), state).is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Joinable rooms for a player of the given role. Logged in players get the rooms whose
+    /// opponents are closest to their own rating first, unrated opponents last.
+    fn handle_request_rooms(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let mut typeb = [0u8; 1];
+        stream.read_exact(&mut typeb)?;
+        let player_type = if typeb[0] == 0 {
+            PlayerType::Mouse
+        } else {
+            PlayerType::Wall
+        };
+
+        if let Ok(state_guard) = state.lock() {
+            let mut rooms_buf: Vec<u8> = Vec::new();
+
+            let mut rooms: Vec<(&Uuid, &Room)> = state_guard
+                .rooms
+                .iter()
+                .filter(|(_, room)| room.is_available())
+                .collect();
+            if let Some(account) = state_guard.seat_account(uid) {
+                let own = state_guard.ratings.get(account, player_type).rating.round() as u32;
+                rooms.sort_by_key(|(_, room)| {
+                    match state_guard.side_rating(room, player_type.opponent()) {
+                        0 => u32::MAX,
+                        rating => rating.abs_diff(own),
+                    }
+                });
+            }
+
+            let mut count: u32 = 0;
+            for (room_id, room) in rooms {
+                let room_idb: [u8; 16] = room_id.to_bytes_le();
+                rooms_buf.write_all(&room_idb)?;
+
+                let player_countb: [u8; 1] = [room.get_player_count()];
+                rooms_buf.write_all(&player_countb)?;
+
+                rooms_buf.write_all(&[
+                    room.seats.mice,
+                    room.seats.trappers,
+                    room.free_seats(PlayerType::Mouse),
+                    room.free_seats(PlayerType::Wall),
+                ])?;
+
+                let (width, height) = room.board_size();
+                rooms_buf.write_all(&(width as u32).to_le_bytes())?;
+                rooms_buf.write_all(&(height as u32).to_le_bytes())?;
+                rooms_buf.write_all(&(room.settings.walls as u32).to_le_bytes())?;
+
+                let shape = &room.settings.shape;
+                rooms_buf.write_all(&[shape.id()])?;
+                rooms_buf.write_all(&(shape.name().len() as u32).to_le_bytes())?;
+                rooms_buf.write_all(shape.name().as_bytes())?;
+                rooms_buf.write_all(&room.settings.rules.as_bytes())?;
+                rooms_buf.write_all(&[room.settings.mice as u8])?;
+                rooms_buf.write_all(&[room.hints as u8])?;
+
+                for side in [PlayerType::Mouse, PlayerType::Wall] {
+                    let rating = state_guard.side_rating(room, side);
+                    rooms_buf.write_all(&rating.to_le_bytes())?;
+                }
+
+                count += 1;
+            }
+
+            let mut data: Vec<u8> = Vec::new();
+            let room_countb: [u8; 4] = count.to_le_bytes();
+            data.write_all(&room_countb)?;
+            data.extend(rooms_buf);
+            stream.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_request_daily(
+        stream: &mut TcpStream,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        if let Ok(mut state_guard) = state.lock() {
+            let mut data: Vec<u8> = Vec::new();
+            let entries = state_guard.daily.top(DAILY_LEADERBOARD_SIZE).to_vec();
+
+            data.write_all(&state_guard.daily.day().to_le_bytes())?;
+            data.write_all(&(entries.len() as u32).to_le_bytes())?;
+            for (username, walls) in entries {
+                data.write_all(&(username.len() as u32).to_le_bytes())?;
+                data.write_all(username.as_bytes())?;
+                data.write_all(&walls.to_le_bytes())?;
+            }
+
+            stream.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_request_boards(
+        stream: &mut TcpStream,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let names = Self::boards(state).names();
+
+        let mut data: Vec<u8> = Vec::new();
+        data.write_all(&(names.len() as u32).to_le_bytes())?;
+        for name in names {
+            data.write_all(&(name.len() as u32).to_le_bytes())?;
+            data.write_all(name.as_bytes())?;
+        }
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// The position of the user's room in game notation, empty when not in a room.
+    fn handle_request_position(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let position = if let Ok(state_guard) = state.lock()
+            && let Some(room_id) = state_guard.get_user_room(uid)
+            && let Some(room) = state_guard.rooms.get(&room_id)
+        {
+            room.position()
+        } else {
+            String::new()
+        };
+
+        let mut data: Vec<u8> = Vec::new();
+        data.write_all(&(position.len() as u32).to_le_bytes())?;
+        data.write_all(position.as_bytes())?;
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    fn handle_request_tiles(
+        stream: &mut TcpStream,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
         let mut room_idb = [0u8; 16];
         stream.read_exact(&mut room_idb)?;
         let room_id = Uuid::from_bytes_le(room_idb);
@@ -381,6 +1266,240 @@ impl Controller {
         Ok(())
     }
 
+    /// Mouse and wall rating of the user, zeros for guests.
+    fn handle_request_ratings(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let ratings = if let Ok(state_guard) = state.lock()
+            && let Some(user) = state_guard.users.get(uid)
+            && let Some(name) = &user.account
+        {
+            [PlayerType::Mouse, PlayerType::Wall]
+                .map(|side| state_guard.ratings.get(name, side).rating.round().max(1.0) as u32)
+        } else {
+            [0, 0]
+        };
+
+        let mut data: Vec<u8> = Vec::new();
+        for rating in ratings {
+            data.write_all(&rating.to_le_bytes())?;
+        }
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// The latest games of the user: id, start time, winning side, end reason, the side the user
+    /// played and the names of the other side.
+    fn handle_request_recent_games(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        if let Ok(state_guard) = state.lock()
+            && state_guard.users.contains_key(uid)
+        {
+            let key = state_guard.seat_key(uid);
+            let games = state_guard.archive.recent(&key, RECENT_GAMES);
+
+            let mut data: Vec<u8> = Vec::new();
+            data.write_all(&(games.len() as u32).to_le_bytes())?;
+            for game in games {
+                let side = game
+                    .players
+                    .iter()
+                    .find(|(_, name)| *name == key)
+                    .map_or(PlayerType::Mouse, |(side, _)| *side);
+                let opponents: Vec<&str> = game
+                    .players
+                    .iter()
+                    .filter(|(player_side, _)| *player_side != side)
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                let opponents = opponents.join(" & ");
+
+                data.write_all(&game.id.to_bytes_le())?;
+                data.write_all(&game.started.to_le_bytes())?;
+                data.write_all(&[game.reason.winner() as u8, game.reason.id(), side as u8])?;
+                data.write_all(&(opponents.len() as u32).to_le_bytes())?;
+                data.write_all(opponents.as_bytes())?;
+            }
+
+            stream.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    /// A whole archived game as a replay file, empty when the id is unknown.
+    fn handle_request_game(
+        stream: &mut TcpStream,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let mut idb = [0u8; 16];
+        stream.read_exact(&mut idb)?;
+        let id = Uuid::from_bytes_le(idb);
+
+        let text = if let Ok(state_guard) = state.lock() {
+            state_guard
+                .archive
+                .find(&id)
+                .map_or_else(String::new, |game| game.format_replay())
+        } else {
+            String::new()
+        };
+
+        let mut data: Vec<u8> = Vec::new();
+        data.write_all(&(text.len() as u32).to_le_bytes())?;
+        data.write_all(text.as_bytes())?;
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// Best rated players per role, mice first: count, then name, rating and rated games.
+    fn handle_request_leaderboard(
+        stream: &mut TcpStream,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let mut data: Vec<u8> = Vec::new();
+
+        if let Ok(state_guard) = state.lock() {
+            for role in [PlayerType::Mouse, PlayerType::Wall] {
+                let top = state_guard.ratings.top(role, LEADERBOARD_SIZE);
+                data.write_all(&(top.len() as u32).to_le_bytes())?;
+                for (name, rating, games) in top {
+                    data.write_all(&(name.len() as u32).to_le_bytes())?;
+                    data.write_all(name.as_bytes())?;
+                    data.write_all(&(rating.rating.round().max(1.0) as u32).to_le_bytes())?;
+                    data.write_all(&games.to_le_bytes())?;
+                }
+            }
+        } else {
+            data.write_all(&[0u8; 8])?;
+        }
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// Statistics of a player, an empty name asks for the user's own. Replies with the name, then
+    /// rating (0 when unrated), games and wins per role, the longest winning streak and the
+    /// average walls needed to trap the mice in tenths, `u32::MAX` before the first trap.
+    fn handle_request_profile(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let name = Self::read_text(stream, MAX_CREDENTIAL)?;
+
+        let mut data: Vec<u8> = Vec::new();
+        if let Ok(state_guard) = state.lock() {
+            let name = if name.is_empty() {
+                state_guard.seat_key(uid)
+            } else {
+                name
+            };
+            let stats = PlayerStats::collect(&name, state_guard.archive.games());
+
+            data.write_all(&(name.len() as u32).to_le_bytes())?;
+            data.write_all(name.as_bytes())?;
+            for role in [PlayerType::Mouse, PlayerType::Wall] {
+                let rating = state_guard
+                    .ratings
+                    .rated(&name, role)
+                    .map_or(0, |rating| rating.rating.round().max(1.0) as u32);
+                data.write_all(&rating.to_le_bytes())?;
+                data.write_all(&stats.role_games(role).to_le_bytes())?;
+                data.write_all(&stats.role_wins(role).to_le_bytes())?;
+            }
+            data.write_all(&stats.longest_streak.to_le_bytes())?;
+            let average = stats
+                .average_moves_to_trap()
+                .map_or(u32::MAX, |average| (average * 10.0).round() as u32);
+            data.write_all(&average.to_le_bytes())?;
+        }
+        stream.write_all(&data)?;
+
+        Ok(())
+    }
+
+    fn handle_request_hint(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let hint = if let Ok(state_guard) = state.lock()
+            && state_guard.config.hints
+            && let Some(room_id) = state_guard.get_user_room(uid)
+            && let Some(room) = state_guard.rooms.get(&room_id)
+        {
+            room.hint(uid)
+        } else {
+            None
+        };
+
+        match hint {
+            Some((suggestion, evaluation)) => {
+                let mut data: Vec<u8> = vec![Protocol::Hint as u8];
+
+                // u32::MAX marks a missing move or mouse, or an unreachable edge
+                let (to, from) = match suggestion {
+                    Some(Move::Wall(tile)) => (Some(tile), None),
+                    Some(Move::Mouse { from, to }) => (Some(to), from),
+                    None => (None, None),
+                };
+                for tile in [to, from] {
+                    let (y, x) = tile.map_or((u32::MAX, u32::MAX), |(y, x)| (y as u32, x as u32));
+                    data.write_all(&y.to_le_bytes())?;
+                    data.write_all(&x.to_le_bytes())?;
+                }
+                data.write_all(&evaluation.escape_distance.unwrap_or(u32::MAX).to_le_bytes())?;
+                data.write_all(&evaluation.open_routes.to_le_bytes())?;
+
+                stream.write_all(&data)?;
+            }
+            None => stream.write_all(&[Protocol::HintDenied as u8])?,
+        }
+
+        Ok(())
+    }
+
+    /// Distance map as varints, row by row: zero for unreachable tiles, otherwise distance + 1.
+    fn handle_request_analysis(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let map = if let Ok(state_guard) = state.lock()
+            && let Some(room_id) = state_guard.get_user_room(uid)
+            && let Some(room) = state_guard.rooms.get(&room_id)
+        {
+            room.distance_map()
+        } else {
+            None
+        };
+
+        match map {
+            Some(map) => {
+                let mut data: Vec<u8> = vec![Protocol::Analysis as u8];
+                write_varint(&mut data, map.first().map_or(0, |line| line.len()) as u64);
+                write_varint(&mut data, map.len() as u64);
+
+                for dist in map.iter().flatten() {
+                    write_varint(&mut data, dist.map_or(0, |dist| dist as u64 + 1));
+                }
+
+                stream.write_all(&data)?;
+            }
+            None => stream.write_all(&[Protocol::AnalysisDenied as u8])?,
+        }
+
+        Ok(())
+    }
+
     fn handle_turn(
         stream: &mut TcpStream,
         uid: &Uuid,
@@ -393,6 +1512,16 @@ impl Controller {
         stream.read_exact(&mut bytes)?;
         let x = u32::from_le_bytes(bytes) as usize;
 
+        // Mouse players name the mouse they move, everyone else sends u32::MAX
+        stream.read_exact(&mut bytes)?;
+        let mouse_y = u32::from_le_bytes(bytes);
+
+        stream.read_exact(&mut bytes)?;
+        let mouse_x = u32::from_le_bytes(bytes);
+
+        let mouse = (mouse_y != u32::MAX && mouse_x != u32::MAX)
+            .then_some((mouse_y as usize, mouse_x as usize));
+
         let room_id: Option<Uuid> = if let Ok(state_guard) = state.lock() {
             state_guard.get_user_room(uid)
         } else {
@@ -410,9 +1539,9 @@ impl Controller {
 
             if let Some(player_count) = player_count {
                 if player_count == 1 {
-                    Self::handle_singe_turn(stream, uid, &y, &x, &rid, state)?;
+                    Self::handle_singe_turn(stream, uid, &y, &x, mouse, &rid, state)?;
                 } else {
-                    Self::handle_multi_turn(stream, uid, &y, &x, &rid, state)?;
+                    Self::handle_multi_turn(stream, uid, &y, &x, mouse, &rid, state)?;
                 }
             }
         }
@@ -425,32 +1554,46 @@ impl Controller {
         uid: &Uuid,
         y: &usize,
         x: &usize,
+        mouse: Option<(usize, usize)>,
         room_id: &Uuid,
         state: &Arc<Mutex<ServerState>>,
     ) -> Result<(), ServerErr> {
         if let Ok(mut state_guard) = state.lock()
             && let Some(room) = state_guard.rooms.get_mut(room_id)
         {
-            match room.process_turn(uid, y, x) {
+            let logged = room.history().len();
+            let result = room.process_turn(uid, y, x, mouse);
+
+            match result {
                 TurnResult::Good => {
-                    if room.ai_turn() == TurnResult::GameOver {
-                        stream.write_all(&[Protocol::GameOver as u8])?;
+                    let bot_result = room.ai_turn();
+                    Self::log_moves(room_id, room, logged, bot_result == TurnResult::GameOver);
 
-                        let data = room.get_grid();
-                        stream.write_all(&data)?;
+                    if bot_result == TurnResult::GameOver {
+                        stream.write_all(&Self::game_over(&room.get_game_over(), None))?;
 
-                        state_guard.rooms.remove(room_id);
+                        let daily_result = room.daily_result();
+                        let reason = room.end_reason();
+                        state_guard.close_room(room_id, reason);
+
+                        if let Some((day, walls)) = daily_result {
+                            state_guard.record_daily(uid, day, walls);
+                        }
                     } else {
-                        stream.write_all(&[Protocol::YourTurn as u8])?;
+                        stream.write_all(&Self::turn_update(Protocol::YourTurn, room, logged))?;
                     }
                 }
+                TurnResult::Continue => {
+                    Self::log_moves(room_id, room, logged, false);
+                    stream.write_all(&Self::turn_update(Protocol::YourTurn, room, logged))?
+                }
                 TurnResult::Bad => {}
                 TurnResult::GameOver => {
-                    stream.write_all(&[Protocol::GameOver as u8])?;
-                    let data = room.get_grid();
-                    stream.write_all(&data)?;
+                    Self::log_moves(room_id, room, logged, true);
+                    stream.write_all(&Self::game_over(&room.get_game_over(), None))?;
 
-                    state_guard.rooms.remove(room_id);
+                    let reason = room.end_reason();
+                    state_guard.close_room(room_id, reason);
                 }
             }
         }
@@ -463,81 +1606,402 @@ impl Controller {
         uid: &Uuid,
         y: &usize,
         x: &usize,
+        mouse: Option<(usize, usize)>,
         room_id: &Uuid,
         state: &Arc<Mutex<ServerState>>,
     ) -> Result<(), ServerErr> {
-        let data: Option<(TurnResult, Option<Uuid>, Vec<u8>)> = if let Ok(mut state_guard) =
-            state.lock()
+        type TurnData = (
+            TurnResult,
+            Option<Uuid>,
+            Vec<Uuid>,
+            Vec<u8>,
+            Option<EndReason>,
+            [Vec<u8>; 2],
+        );
+        let data: Option<TurnData> = if let Ok(mut state_guard) = state.lock()
             && let Some(room) = state_guard.rooms.get_mut(room_id)
         {
+            let logged = room.history().len();
+            let result = room.process_turn(uid, y, x, mouse);
+            Self::log_moves(room_id, room, logged, result == TurnResult::GameOver);
+
             Some((
-                room.process_turn(uid, y, x),
-                room.get_other_player(uid),
-                room.get_grid(),
+                result,
+                room.current_player(),
+                room.get_other_players(uid),
+                room.get_game_over(),
+                room.end_reason(),
+                [
+                    Self::turn_update(Protocol::YourTurn, room, logged),
+                    Self::turn_update(Protocol::WaitTurn, room, logged),
+                ],
             ))
         } else {
             None
         };
 
-        if let Some((turn_result, other_player_id, grid_data)) = data {
+        if let Some((turn_result, current, others, grid_data, reason, [your_turn, wait_turn])) =
+            data
+        {
             match turn_result {
-                TurnResult::Good => {
-                    stream.write_all(&[Protocol::WaitTurn as u8])?;
+                TurnResult::Good | TurnResult::Continue => {
+                    // Everyone refreshes the board, only the player to move gets the turn
+                    let turn = |pid: &Uuid| {
+                        if current == Some(*pid) {
+                            &your_turn
+                        } else {
+                            &wait_turn
+                        }
+                    };
+
+                    stream.write_all(turn(uid))?;
 
-                    if let Some(pid) = other_player_id
-                        && let Ok(mut state_guard) = state.lock()
-                        && let Some(other_player) = state_guard.users.get_mut(&pid)
-                    {
-                        other_player.stream.write_all(&[Protocol::YourTurn as u8])?;
+                    if let Ok(mut state_guard) = state.lock() {
+                        for pid in others.iter() {
+                            if let Some(other_player) = state_guard.users.get_mut(pid) {
+                                other_player.stream.write_all(turn(pid))?;
+                            }
+                        }
                     }
                 }
                 TurnResult::Bad => {}
                 TurnResult::GameOver => {
-                    Self::end_room(room_id, stream, other_player_id, grid_data, state)?
+                    Self::end_room(room_id, uid, stream, &others, grid_data, reason, state)?
                 }
             }
         }
         Ok(())
     }
 
+    /// Turn notification with the board hash and the moves played since `from`, letting clients
+    /// follow the game without downloading the whole board. Mouse moves from an unknown tile send
+    /// `u32::MAX` as the origin.
+    fn turn_update(turn: Protocol, room: &Room, from: usize) -> Vec<u8> {
+        let moves = &room.history()[from..];
+
+        let mut data = vec![turn as u8];
+        data.extend(room.board_hash().to_le_bytes());
+        data.extend((moves.len() as u32).to_le_bytes());
+
+        for mv in moves {
+            let (kind, (y, x), origin) = match *mv {
+                Move::Wall(tile) => (0u8, tile, None),
+                Move::Mouse { from, to } => (1u8, to, Some(from)),
+            };
+
+            data.push(kind);
+            data.extend((y as u32).to_le_bytes());
+            data.extend((x as u32).to_le_bytes());
+
+            if let Some(origin) = origin {
+                let (from_y, from_x) =
+                    origin.map_or((u32::MAX, u32::MAX), |(i, j)| (i as u32, j as u32));
+                data.extend(from_y.to_le_bytes());
+                data.extend(from_x.to_le_bytes());
+            }
+        }
+
+        data
+    }
+
+    /// Prints the moves played since `from` followed by the resulting position.
+    fn log_moves(room_id: &Uuid, room: &Room, from: usize, game_over: bool) {
+        let moves: Vec<String> = room.history()[from..].iter().map(format_move).collect();
+        if moves.is_empty() {
+            return;
+        }
+
+        println!(
+            "Room [{}] {} => {}",
+            room_id,
+            moves.join(" "),
+            room.position()
+        );
+        if game_over {
+            println!("Room [{}] finished: {}", room_id, room.position());
+        }
+    }
+
+    /// Game over message, followed by the rating change for rated games.
+    fn game_over(grid_data: &[u8], change: Option<&(Rating, f64)>) -> Vec<u8> {
+        let mut data = vec![Protocol::GameOver as u8];
+        data.extend(grid_data);
+
+        match change {
+            Some((rating, delta)) => {
+                data.push(1);
+                data.extend((rating.rating.round().max(0.0) as u32).to_le_bytes());
+                data.extend((delta.round() as i32).to_le_bytes());
+            }
+            None => data.push(0),
+        }
+
+        data
+    }
+
+    /// Closes the room and tells every player the game is over.
     fn end_room(
         room_id: &Uuid,
+        uid: &Uuid,
         stream: &mut TcpStream,
-        other_player_id: Option<Uuid>,
+        others: &[Uuid],
         grid_data: Vec<u8>,
+        reason: Option<EndReason>,
         state: &Arc<Mutex<ServerState>>,
     ) -> Result<(), ServerErr> {
+        let changes = if let Ok(mut state_guard) = state.lock() {
+            state_guard.close_room(room_id, reason)
+        } else {
+            HashMap::new()
+        };
+
         // User may already be disconnected and could not send data to him anymore
-        stream.write_all(&[Protocol::GameOver as u8]).ok();
-        stream.write_all(&grid_data).ok();
+        stream
+            .write_all(&Self::game_over(&grid_data, changes.get(uid)))
+            .ok();
 
-        if let Some(pid) = other_player_id
-            && let Ok(mut state_guard) = state.lock()
-            && let Some(other_player) = state_guard.users.get_mut(&pid)
-        {
-            other_player.stream.write_all(&[Protocol::GameOver as u8])?;
-            other_player.stream.write_all(&grid_data)?;
+        if let Ok(mut state_guard) = state.lock() {
+            for pid in others {
+                if let Some(other_player) = state_guard.users.get_mut(pid) {
+                    other_player
+                        .stream
+                        .write_all(&Self::game_over(&grid_data, changes.get(pid)))
+                        .ok();
+                }
+            }
         }
 
+        Ok(())
+    }
+
+    /// Logged in users always play under their account name, guests cannot take a registered one.
+    /// Returns the name the user ends up with.
+    fn set_name(uid: &Uuid, username: &str, state: &Arc<Mutex<ServerState>>) -> String {
         if let Ok(mut state_guard) = state.lock() {
-            state_guard.rooms.remove(room_id);
+            let registered = state_guard.accounts.find(username).is_some();
+
+            if let Some(user) = state_guard.users.get_mut(uid) {
+                if user.account.is_none() {
+                    user.username = if registered {
+                        guest_name(username)
+                    } else {
+                        username.to_string()
+                    };
+                }
+                return user.username.clone();
+            }
         }
 
+        username.to_string()
+    }
+
+    fn read_text(stream: &mut TcpStream, max_len: usize) -> Result<String, ServerErr> {
+        let mut lenb = [0u8; 4];
+        stream.read_exact(&mut lenb)?;
+        let len = u32::from_le_bytes(lenb) as usize;
+        if len > max_len {
+            return Err(ServerErr::UnknownCommand);
+        }
+
+        let mut bytes = vec![0u8; len];
+        stream.read_exact(&mut bytes)?;
+
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    /// Name and password, registering also logs the user in.
+    fn handle_register(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let name = Self::read_text(stream, MAX_CREDENTIAL)?;
+        let password = Self::read_text(stream, MAX_CREDENTIAL)?;
+
+        // Hash before taking the lock, argon2 is slow on purpose
+        let hash = validate_name(&name).and_then(|_| hash_password(&password));
+        let result = match hash {
+            Ok(hash) => {
+                let Ok(mut state_guard) = state.lock() else {
+                    return Ok(());
+                };
+                state_guard.accounts.insert(&name, hash).map(|_| name)
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Ok(name) = &result {
+            println!("User [{}] registered", name);
+        }
+        Self::finish_auth(stream, uid, result, state)
+    }
+
+    fn handle_login(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let name = Self::read_text(stream, MAX_CREDENTIAL)?;
+        let password = Self::read_text(stream, MAX_CREDENTIAL)?;
+
+        let account = if let Ok(state_guard) = state.lock() {
+            state_guard
+                .accounts
+                .find(&name)
+                .map(|(name, hash)| (name.to_string(), hash.to_string()))
+        } else {
+            None
+        };
+
+        let result = match account {
+            Some((name, hash)) => verify_password(&hash, &password).map(|_| name),
+            None => Err(AccountErr::WrongCredentials),
+        };
+        Self::finish_auth(stream, uid, result, state)
+    }
+
+    /// Success carries the registered spelling of the name, failures a readable reason.
+    fn finish_auth(
+        stream: &mut TcpStream,
+        uid: &Uuid,
+        result: Result<String, AccountErr>,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<(), ServerErr> {
+        let (response, text) = match result {
+            Ok(name) => {
+                if let Ok(mut state_guard) = state.lock()
+                    && let Some(user) = state_guard.users.get_mut(uid)
+                {
+                    user.username = name.clone();
+                    user.account = Some(name.clone());
+                }
+
+                println!("User [{}] logged in", name);
+                (Protocol::AuthSuccess, name)
+            }
+            Err(e) => (Protocol::AuthFail, e.to_string()),
+        };
+
+        let mut data: Vec<u8> = vec![response as u8];
+        data.write_all(&(text.len() as u32).to_le_bytes())?;
+        data.write_all(text.as_bytes())?;
+        stream.write_all(&data)?;
+
         Ok(())
     }
 
-    fn set_name(uid: &Uuid, username: &str, state: &Arc<Mutex<ServerState>>) {
+    fn handle_logout(uid: &Uuid, state: &Arc<Mutex<ServerState>>) {
         if let Ok(mut state_guard) = state.lock()
             && let Some(user) = state_guard.users.get_mut(uid)
+            && let Some(name) = user.account.take()
         {
-            user.username = username.to_string();
+            user.username = "Guest".to_string();
+            println!("User [{}] logged out", name);
+        }
+    }
+
+    /// Reads the optional seed (a flag byte followed by the seed itself), the board size, wall
+    /// count and shape of a new room. Custom shapes are followed by the board name, then come
+    /// the rules and the number of mice.
+    fn read_settings(stream: &mut TcpStream) -> Result<RoomSettings, ServerErr> {
+        let mut flagb = [0u8; 1];
+        stream.read_exact(&mut flagb)?;
+
+        let mut seedb = [0u8; 8];
+        stream.read_exact(&mut seedb)?;
+
+        let mut values = [0usize; 3];
+        for value in values.iter_mut() {
+            let mut bytes = [0u8; 4];
+            stream.read_exact(&mut bytes)?;
+            *value = u32::from_le_bytes(bytes) as usize;
+        }
+        let [width, height, walls] = values;
+
+        let mut shapeb = [0u8; 1];
+        stream.read_exact(&mut shapeb)?;
+        let shape = match shapeb[0] {
+            0 => BoardShape::Rectangle,
+            1 => BoardShape::Hexagon,
+            _ => {
+                let mut lenb = [0u8; 4];
+                stream.read_exact(&mut lenb)?;
+                let len = u32::from_le_bytes(lenb) as usize;
+                if len > MAX_BOARD_NAME {
+                    return Err(ServerErr::UnknownCommand);
+                }
+
+                let mut nameb = vec![0u8; len];
+                stream.read_exact(&mut nameb)?;
+                BoardShape::Mask(String::from_utf8_lossy(&nameb).to_string())
+            }
+        };
+
+        let mut rulesb = [0u8; 3];
+        stream.read_exact(&mut rulesb)?;
+
+        let mut miceb = [0u8; 1];
+        stream.read_exact(&mut miceb)?;
+
+        Ok(RoomSettings {
+            width,
+            height,
+            walls,
+            mice: miceb[0] as usize,
+            shape,
+            rules: Rules::from_bytes(rulesb),
+            seed: (flagb[0] != 0).then_some(u64::from_le_bytes(seedb)),
+        })
+    }
+
+    /// The custom boards the server was started with.
+    fn boards(state: &Arc<Mutex<ServerState>>) -> Boards {
+        match state.lock() {
+            Ok(state_guard) => state_guard.config.boards.clone(),
+            Err(_) => Boards::default(),
+        }
+    }
+
+    /// Refuses the room with a `JoinFail` when the requested settings are not playable.
+    fn check_settings(
+        stream: &mut TcpStream,
+        settings: &RoomSettings,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<bool, ServerErr> {
+        if let Err(e) = settings.validate(&Self::boards(state)) {
+            println!("Refused room settings ({})", e);
+            stream.write_all(&[Protocol::JoinFail as u8])?;
+            return Ok(false);
         }
+
+        Ok(true)
     }
 
-    fn add_room(num_players: u8, state: &Arc<Mutex<ServerState>>) -> Option<Uuid> {
+    /// Hints are part of bot practice, multiplayer rooms have to opt in when they are created.
+    fn add_room(
+        seats: Seats,
+        hints: bool,
+        settings: RoomSettings,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Option<Uuid> {
+        let room = match Room::new(seats, hints, settings, &Self::boards(state)) {
+            Ok(room) => room,
+            Err(e) => {
+                println!("Failed creating room ({})", e);
+                return None;
+            }
+        };
+
         if let Ok(mut state_guard) = state.lock() {
+            if let Some(max) = state_guard.config.max_rooms
+                && state_guard.rooms.len() >= max
+            {
+                println!("Refused a new room, {} rooms are open", max);
+                return None;
+            }
+
             let id = Uuid::new_v4();
-            state_guard.rooms.insert(id, Room::new(num_players));
+            state_guard.rooms.insert(id, room);
 
             Some(id)
         } else {
@@ -550,14 +2014,17 @@ impl Controller {
         room_id: &Uuid,
         player_type: &PlayerType,
         state: &Arc<Mutex<ServerState>>,
-    ) {
+    ) -> bool {
         if let Ok(mut state_guard) = state.lock()
             && let Some(room) = state_guard.rooms.get_mut(room_id)
+            && room.add_player(user_id, player_type)
         {
-            room.add_player(user_id, player_type);
             if let Some(user) = state_guard.users.get_mut(user_id) {
                 user.room = Some(*room_id);
             }
+            true
+        } else {
+            false
         }
     }
 }
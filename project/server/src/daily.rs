@@ -0,0 +1,122 @@
+use hexgrid::splitmix64;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DAILY_FILE: &str = "daily.txt";
+/// Everyone faces the same mouse, whatever bots the server runs for practice games.
+pub const DAILY_MOUSE: &str = "greedy";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DailyErr {
+    #[error("Broken daily entry '{0}'")]
+    Corrupt(String),
+
+    #[error("Io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Days since the unix epoch (UTC), every player switches challenge at the same time.
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / SECONDS_PER_DAY)
+}
+
+/// Spreads consecutive days over the whole seed range.
+pub fn daily_seed(day: u64) -> u64 {
+    splitmix64(day)
+}
+
+/// Best daily results of registered players. Every improvement is appended to the file as
+/// `day<TAB>walls<TAB>account` so a restart keeps the day's board.
+pub struct DailyLeaderboard {
+    path: PathBuf,
+    day: u64,
+    entries: Vec<(String, u32)>,
+}
+
+impl DailyLeaderboard {
+    /// A missing file is an empty board, results of earlier days are skipped.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, DailyErr> {
+        let path = path.into();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut board = Self {
+            path,
+            day: today(),
+            entries: Vec::new(),
+        };
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (day, walls, account) =
+                Self::parse_entry(line).ok_or_else(|| DailyErr::Corrupt(line.to_string()))?;
+            if day == board.day {
+                board.insert(account, walls);
+            }
+        }
+
+        Ok(board)
+    }
+
+    fn parse_entry(line: &str) -> Option<(u64, u32, &str)> {
+        let mut parts = line.split('\t');
+        let day = parts.next()?.parse().ok()?;
+        let walls = parts.next()?.parse().ok()?;
+        let account = parts.next().filter(|account| !account.is_empty())?;
+        parts.next().is_none().then_some((day, walls, account))
+    }
+
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+
+    /// Keeps the best score of every account, results for another day than today are dropped.
+    pub fn record(&mut self, day: u64, account: &str, walls: u32) -> Result<(), DailyErr> {
+        self.roll_over();
+        if day != self.day || !self.insert(account, walls) {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}\t{}\t{}", day, walls, account)?;
+        Ok(())
+    }
+
+    /// Whether the result beat the account's best so far.
+    fn insert(&mut self, account: &str, walls: u32) -> bool {
+        if let Some(index) = self.entries.iter().position(|(name, _)| name == account) {
+            if self.entries[index].1 <= walls {
+                return false;
+            }
+            self.entries.remove(index);
+        }
+        self.entries.push((account.to_string(), walls));
+
+        // Stable sort, on equal scores whoever got there first stays ahead
+        self.entries.sort_by_key(|(_, walls)| *walls);
+        true
+    }
+
+    pub fn top(&mut self, count: usize) -> &[(String, u32)] {
+        self.roll_over();
+        &self.entries[..count.min(self.entries.len())]
+    }
+
+    fn roll_over(&mut self) {
+        let today = today();
+        if today != self.day {
+            self.day = today;
+            self.entries.clear();
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! LAN discovery. Clients broadcast a probe to the discovery port and every server on the network
+//! answers with the port its games run on, its name, its version and how many players it has.
+//!
+//! Probes are padded to the size of the largest answer and only private addresses get one, so
+//! the server cannot be used to amplify traffic towards someone else.
+
+use hexgrid::discovery::{ANNOUNCE, PROBE};
+use std::io::Write;
+use std::net::IpAddr;
+
+pub use hexgrid::discovery::{
+    DISCOVERY_PORT, MAGIC, MAX_ANNOUNCEMENT, MAX_SERVER_NAME, PROBE_SIZE,
+};
+
+pub fn probe() -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    data.push(PROBE);
+    data.resize(PROBE_SIZE, 0);
+    data
+}
+
+pub fn is_probe(data: &[u8]) -> bool {
+    data.len() >= PROBE_SIZE && data.starts_with(MAGIC) && data[MAGIC.len()] == PROBE
+}
+
+/// Loopback, private and link-local addresses, the ones a LAN client can probe from.
+pub fn is_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_local(IpAddr::V4(ip)),
+            None => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local(),
+        },
+    }
+}
+
+/// Answer to a probe, the address of the server is the one the datagram came from.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Announcement {
+    pub port: u16,
+    pub name: String,
+    pub version: String,
+    pub online: u32,
+    /// Players sitting in a running game.
+    pub playing: u32,
+}
+
+impl Announcement {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.push(ANNOUNCE);
+        data.write_all(&self.port.to_le_bytes()).ok();
+        for text in [&self.name, &self.version] {
+            data.write_all(&(text.len() as u32).to_le_bytes()).ok();
+            data.write_all(text.as_bytes()).ok();
+        }
+        data.write_all(&self.online.to_le_bytes()).ok();
+        data.write_all(&self.playing.to_le_bytes()).ok();
+        data
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let mut rest = data.strip_prefix(MAGIC)?.strip_prefix(&[ANNOUNCE])?;
+
+        let port = u16::from_le_bytes(take(&mut rest, 2)?.try_into().ok()?);
+        let name = take_text(&mut rest)?;
+        let version = take_text(&mut rest)?;
+        let online = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+        let playing = u32::from_le_bytes(take(&mut rest, 4)?.try_into().ok()?);
+
+        Some(Self {
+            port,
+            name,
+            version,
+            online,
+            playing,
+        })
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Some(head)
+}
+
+fn take_text(data: &mut &[u8]) -> Option<String> {
+    let len = u32::from_le_bytes(take(data, 4)?.try_into().ok()?) as usize;
+    if len > MAX_SERVER_NAME {
+        return None;
+    }
+    String::from_utf8(take(data, len)?.to_vec()).ok()
+}
@@ -0,0 +1,99 @@
+//! Building blocks of the binary grid encoding: LEB128 varints and row major bitsets.
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum EncodingErr {
+    #[error("Unsupported grid encoding version {0}")]
+    Version(u8),
+
+    #[error("Encoded grid ended early")]
+    Truncated,
+
+    #[error("Varint does not fit in 64 bits")]
+    Varint,
+
+    #[error("Invalid board size {0}x{1}")]
+    Size(u64, u64),
+
+    #[error("Tile ({0}, {1}) is invalid")]
+    Tile(u64, u64),
+
+    #[error("Board needs at least one mouse")]
+    NoMouse,
+
+    #[error("Unexpected bytes after the grid")]
+    Trailing,
+}
+
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Packs the bits least significant first, padding the last byte with zeros.
+pub fn pack_bits(bits: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (i, bit) in bits.enumerate() {
+        if i % 8 == 0 {
+            bytes.push(0);
+        }
+        if bit {
+            *bytes.last_mut().unwrap() |= 1 << (i % 8);
+        }
+    }
+
+    bytes
+}
+
+pub fn bit(bytes: &[u8], i: usize) -> bool {
+    bytes[i / 8] & (1 << (i % 8)) != 0
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub fn u8(&mut self) -> Result<u8, EncodingErr> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn varint(&mut self) -> Result<u64, EncodingErr> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            let part = (byte & 0x7F) as u64;
+            if shift == 63 && part > 1 {
+                return Err(EncodingErr::Varint);
+            }
+
+            value |= part << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(EncodingErr::Varint)
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], EncodingErr> {
+        let end = self.pos.checked_add(len).ok_or(EncodingErr::Truncated)?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(EncodingErr::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+}
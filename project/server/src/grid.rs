@@ -1,160 +1,457 @@
 use std::collections::VecDeque;
 
+use crate::encoding::{EncodingErr, Reader, bit, pack_bits, write_varint};
 use crate::room::TurnResult;
-use rand::random_range;
+use crate::rules::Rules;
+use crate::shape::{Mask, ShapeErr};
+use hexgrid::{MAX_BOARD_SIZE, offset_distance, offset_neighbours, zobrist_key};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Bumped whenever the layout of `Grid::as_bytes` changes.
+pub const GRID_ENCODING_VERSION: u8 = 1;
+
+/// Mice never start next to each other.
+const MICE_SPACING: u32 = 2;
 
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Hash)]
 pub enum Entity {
     None,
     Mouse,
     Wall,
+    Void,
 }
 
+#[derive(Clone, Hash)]
 pub struct Grid {
     width: usize,
     height: usize,
     tiles: Vec<Vec<Entity>>,
-    mouse_pos: (usize, usize),
+    mice: Vec<(usize, usize)>,
+    seed: u64,
+    rules: Rules,
+    zobrist: u64,
 }
 
 impl Grid {
-    pub fn new(width: usize, height: usize) -> Self {
-        let mut tiles: Vec<Vec<Entity>> = vec![vec![Entity::None; width]; height];
-
-        let mouse_pos = (height / 2, width / 2);
-        tiles[mouse_pos.0][mouse_pos.1] = Entity::Mouse;
+    pub fn new(width: usize, height: usize, num_walls: usize, seed: u64) -> Result<Self, ShapeErr> {
+        Self::with_mask(&vec![vec![true; width]; height], num_walls, 1, seed)
+    }
 
-        Self::generate_walls(&mut tiles, 5);
+    /// The same seed always produces the same board, ChaCha keeps it stable across platforms.
+    /// The mice start on the inner cells closest to the middle of the mask.
+    pub fn with_mask(
+        mask: &Mask,
+        num_walls: usize,
+        num_mice: usize,
+        seed: u64,
+    ) -> Result<Self, ShapeErr> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let height = mask.len();
+        let width = mask.first().map_or(0, |line| line.len());
+
+        let tiles: Vec<Vec<Entity>> = mask
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|&cell| if cell { Entity::None } else { Entity::Void })
+                    .collect()
+            })
+            .collect();
 
-        Self {
+        let mut grid = Self {
             width,
             height,
             tiles,
-            mouse_pos,
+            mice: Vec::new(),
+            seed,
+            rules: Rules::default(),
+            zobrist: 0,
+        };
+
+        let center = (height / 2, width / 2);
+        let mut starts: Vec<(usize, usize)> = grid
+            .free_tiles()
+            .into_iter()
+            .filter(|&(y, x)| !grid.is_edge(y, x))
+            .collect();
+        starts.sort_by_key(|&pos| offset_distance(pos, center));
+
+        for pos in starts {
+            if grid.mice.len() == num_mice {
+                break;
+            }
+            if grid
+                .mice
+                .iter()
+                .all(|&mouse| offset_distance(mouse, pos) >= MICE_SPACING)
+            {
+                grid.tiles[pos.0][pos.1] = Entity::Mouse;
+                grid.mice.push(pos);
+            }
+        }
+
+        if num_mice == 0 || grid.mice.len() < num_mice {
+            return Err(ShapeErr::NoStart);
+        }
+
+        Self::generate_walls(&mut grid.tiles, num_walls, &mut rng)?;
+        grid.zobrist = zobrist_hash(&grid.tiles);
+
+        Ok(grid)
+    }
+
+    pub fn from_tiles(tiles: Vec<Vec<Entity>>) -> Option<Self> {
+        let height = tiles.len();
+        let width = tiles.first().map_or(0, |line| line.len());
+        if width == 0 || tiles.iter().any(|line| line.len() != width) {
+            return None;
         }
+
+        let mice: Vec<(usize, usize)> = tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.iter()
+                    .enumerate()
+                    .filter(|(_, tile)| **tile == Entity::Mouse)
+                    .map(move |(x, _)| (y, x))
+            })
+            .collect();
+
+        if mice.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            mice,
+            seed: 0,
+            rules: Rules::default(),
+            zobrist: zobrist_hash(&tiles),
+            tiles,
+        })
     }
 
+    /// Versioned encoding: version, varint width and height, a flags byte, the void bitset when
+    /// the board has a shape, the wall bitset and finally the varint coordinates of every mouse.
     pub fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = Vec::new();
-        let mut tiles: Vec<u8> = Vec::new();
-
-        bytes.extend((self.width as u32).to_le_bytes());
-        bytes.extend((self.height as u32).to_le_bytes());
-
-        for (y, line) in self.tiles.iter().enumerate() {
-            for (x, tile) in line.iter().enumerate() {
-                if *tile != Entity::None {
-                    tiles.push(y as u8);
-                    tiles.push(x as u8);
-                    tiles.push(*tile as u8);
+        let mut bytes: Vec<u8> = vec![GRID_ENCODING_VERSION];
+        write_varint(&mut bytes, self.width as u64);
+        write_varint(&mut bytes, self.height as u64);
+
+        let tiles = || self.tiles.iter().flatten();
+        let shaped = tiles().any(|&tile| tile == Entity::Void);
+        bytes.push(shaped as u8);
+
+        if shaped {
+            bytes.extend(pack_bits(tiles().map(|&tile| tile == Entity::Void)));
+        }
+        bytes.extend(pack_bits(tiles().map(|&tile| tile == Entity::Wall)));
+
+        write_varint(&mut bytes, self.mice.len() as u64);
+        for &(y, x) in &self.mice {
+            write_varint(&mut bytes, y as u64);
+            write_varint(&mut bytes, x as u64);
+        }
+
+        bytes
+    }
+
+    /// Decodes `as_bytes`, the seed and rules are not part of the encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EncodingErr> {
+        let mut reader = Reader::new(bytes);
+
+        let version = reader.u8()?;
+        if version != GRID_ENCODING_VERSION {
+            return Err(EncodingErr::Version(version));
+        }
+
+        let (width, height) = (reader.varint()?, reader.varint()?);
+        let sides = 1..=MAX_BOARD_SIZE as u64;
+        if !sides.contains(&width) || !sides.contains(&height) {
+            return Err(EncodingErr::Size(width, height));
+        }
+        let cells = (width * height) as usize;
+        let (width, height) = (width as usize, height as usize);
+        let bitset_len = cells.div_ceil(8);
+
+        let shaped = reader.u8()? & 1 != 0;
+        let voids = if shaped {
+            Some(reader.take(bitset_len)?)
+        } else {
+            None
+        };
+        let walls = reader.take(bitset_len)?;
+
+        let mut tiles = vec![vec![Entity::None; width]; height];
+        for (i, tile) in tiles.iter_mut().flatten().enumerate() {
+            let void = voids.is_some_and(|voids| bit(voids, i));
+            *tile = match (void, bit(walls, i)) {
+                (false, false) => Entity::None,
+                (false, true) => Entity::Wall,
+                (true, false) => Entity::Void,
+                (true, true) => {
+                    return Err(EncodingErr::Tile((i / width) as u64, (i % width) as u64));
                 }
+            };
+        }
+
+        let count = reader.varint()?;
+        if count == 0 {
+            return Err(EncodingErr::NoMouse);
+        }
+
+        let mut mice = Vec::new();
+        for _ in 0..count {
+            let (y, x) = (reader.varint()?, reader.varint()?);
+            if y >= height as u64 || x >= width as u64 {
+                return Err(EncodingErr::Tile(y, x));
             }
+
+            let tile = &mut tiles[y as usize][x as usize];
+            if *tile != Entity::None {
+                return Err(EncodingErr::Tile(y, x));
+            }
+            *tile = Entity::Mouse;
+            mice.push((y as usize, x as usize));
         }
 
-        bytes.extend((tiles.len() as u32 / 3).to_le_bytes());
-        bytes.extend(tiles);
+        if !reader.is_empty() {
+            return Err(EncodingErr::Trailing);
+        }
 
-        bytes
+        Ok(Self {
+            width,
+            height,
+            mice,
+            seed: 0,
+            rules: Rules::default(),
+            zobrist: zobrist_hash(&tiles),
+            tiles,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    /// The first mouse, the only one in the standard game.
+    pub fn mouse_pos(&self) -> (usize, usize) {
+        self.mice[0]
+    }
+
+    pub fn mice(&self) -> &[(usize, usize)] {
+        &self.mice
     }
 
-    fn generate_walls(tiles: &mut [Vec<Entity>], mut num_walls: u8) {
+    /// Zobrist hash of the tiles, kept up to date as moves are played.
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
+    pub fn tile(&self, y: usize, x: usize) -> Entity {
+        self.tiles[y][x]
+    }
+
+    pub fn is_free(&self, y: usize, x: usize) -> bool {
+        y < self.height && x < self.width && self.tiles[y][x] == Entity::None
+    }
+
+    /// Boundary cells of the shape, the ones with a missing neighbour.
+    pub fn is_edge(&self, y: usize, x: usize) -> bool {
+        offset_neighbours(y as i32, x as i32).iter().any(|&(i, j)| {
+            i < 0
+                || i >= self.height as i32
+                || j < 0
+                || j >= self.width as i32
+                || self.tiles[i as usize][j as usize] == Entity::Void
+        })
+    }
+
+    pub fn free_tiles(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (y, x)))
+            .filter(|&(y, x)| self.tiles[y][x] == Entity::None)
+            .collect()
+    }
+
+    /// The trapper only wins once every mouse is stuck.
+    pub fn is_trapped(&self) -> bool {
+        self.mice
+            .iter()
+            .all(|&(mi, mj)| self.get_valid_neighbours(mi, mj).is_empty())
+    }
+
+    /// A single mouse on the edge wins for the mice.
+    pub fn has_escaped(&self) -> bool {
+        self.mice.iter().any(|&(mi, mj)| self.is_edge(mi, mj))
+    }
+
+    fn generate_walls<R: Rng + ?Sized>(
+        tiles: &mut [Vec<Entity>],
+        mut num_walls: usize,
+        rng: &mut R,
+    ) -> Result<(), ShapeErr> {
+        let free = tiles.iter().flatten().filter(|&&tile| tile == Entity::None);
+        if free.count() < num_walls {
+            return Err(ShapeErr::NoWallRoom(num_walls));
+        }
+
         let height = tiles.len();
         let width = tiles[0].len();
         while num_walls > 0 {
-            let x = rand::random_range(0..width);
-            let y = rand::random_range(0..height);
+            let x = rng.random_range(0..width);
+            let y = rng.random_range(0..height);
 
             if tiles[y][x] == Entity::None {
                 tiles[y][x] = Entity::Wall;
                 num_walls -= 1;
             }
         }
+
+        Ok(())
+    }
+
+    fn set_tile(&mut self, y: usize, x: usize, entity: Entity) {
+        let index = y * self.width + x;
+        self.zobrist ^=
+            zobrist_key(index, self.tiles[y][x] as u8) ^ zobrist_key(index, entity as u8);
+        self.tiles[y][x] = entity;
+    }
+
+    /// Free tiles, except next to a mouse when the rules keep walls away from them.
+    pub fn can_place_wall(&self, y: usize, x: usize) -> bool {
+        if !self.is_free(y, x) {
+            return false;
+        }
+
+        !(self.rules.no_wall_near_mouse
+            && self
+                .mice
+                .iter()
+                .any(|&(mi, mj)| self.get_valid_neighbours(mi, mj).contains(&(y, x))))
+    }
+
+    pub fn wall_moves(&self) -> Vec<(usize, usize)> {
+        self.free_tiles()
+            .into_iter()
+            .filter(|&(y, x)| self.can_place_wall(y, x))
+            .collect()
     }
 
     pub fn place(&mut self, y: &usize, x: &usize, entity: Entity) -> TurnResult {
-        if self.tiles[*y][*x] == Entity::None {
-            self.tiles[*y][*x] = entity;
+        if entity == Entity::Wall && !self.can_place_wall(*y, *x) {
+            return TurnResult::Bad;
+        }
+
+        if self.is_free(*y, *x) {
+            self.set_tile(*y, *x, entity);
             TurnResult::Good
         } else {
             TurnResult::Bad
         }
     }
 
-    pub fn place_random(&mut self, entity: Entity) -> TurnResult {
-        let (mi, mj) = self.mouse_pos;
-        let valid = self.get_valid_neighbours(mi, mj);
+    /// Tiles any of the mice can reach this turn.
+    pub fn mouse_moves(&self) -> Vec<(usize, usize)> {
+        let mut moves: Vec<(usize, usize)> = Vec::new();
+        for &mouse in &self.mice {
+            for tile in self.mouse_moves_from(mouse) {
+                if !moves.contains(&tile) {
+                    moves.push(tile);
+                }
+            }
+        }
+
+        moves
+    }
 
-        if !valid.is_empty() {
-            let idx = random_range(0..valid.len());
-            let (y, x) = valid[idx];
+    /// Tiles the mouse can reach this turn, walking at most `mouse_steps` tiles. Reaching the
+    /// edge ends the walk, so the mouse cannot pass along it.
+    pub fn mouse_moves_from(&self, mouse: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut moves: Vec<(usize, usize)> = Vec::new();
+        let mut frontier = vec![mouse];
+
+        for _ in 0..self.rules.mouse_steps {
+            let mut next = Vec::new();
+            for (i, j) in frontier {
+                if (i, j) != mouse && self.is_edge(i, j) {
+                    continue;
+                }
 
-            self.tiles[y][x] = entity;
-            TurnResult::Good
-        } else {
-            TurnResult::GameOver
+                for tile in self.get_valid_neighbours(i, j) {
+                    if !moves.contains(&tile) {
+                        moves.push(tile);
+                        next.push(tile);
+                    }
+                }
+            }
+            frontier = next;
         }
+
+        moves
     }
 
+    /// Moves the first mouse that can reach the tile.
     pub fn move_mouse(&mut self, y: &usize, x: &usize) -> TurnResult {
-        let (mi, mj) = self.mouse_pos;
-        let valid = self.get_valid_neighbours(mi, mj);
-
-        if !valid.is_empty() {
-            if let Some((i, j)) = valid.iter().find(|(i, j)| i == y && j == x) {
-                self.tiles[mi][mj] = Entity::None;
-                self.tiles[*i][*j] = Entity::Mouse;
-                self.mouse_pos = (*i, *j);
-
-                return if *i == 0 || *i == self.height - 1 || *j == 0 || *j == self.width - 1 {
-                    TurnResult::GameOver
-                } else {
-                    TurnResult::Good
-                };
-            }
+        let mouse = self
+            .mice
+            .iter()
+            .copied()
+            .find(|&mouse| self.mouse_moves_from(mouse).contains(&(*y, *x)));
 
-            TurnResult::Bad
-        } else {
-            TurnResult::GameOver
+        match mouse {
+            Some(mouse) => self.move_mouse_from(mouse, y, x),
+            None if self.is_trapped() => TurnResult::GameOver,
+            None => TurnResult::Bad,
         }
     }
 
-    pub fn move_mouse_random(&mut self) -> TurnResult {
-        let (mi, mj) = self.mouse_pos;
-        let valid = self.get_valid_neighbours(mi, mj);
+    pub fn move_mouse_from(&mut self, mouse: (usize, usize), y: &usize, x: &usize) -> TurnResult {
+        if self.is_trapped() {
+            return TurnResult::GameOver;
+        }
 
-        if !valid.is_empty() {
-            let dist_map = self.get_distance_map();
-            let best_move = valid.iter().min_by_key(|&(i, j)| dist_map[*i][*j]);
+        let Some(idx) = self.mice.iter().position(|&m| m == mouse) else {
+            return TurnResult::Bad;
+        };
+        if !self.mouse_moves_from(mouse).contains(&(*y, *x)) {
+            return TurnResult::Bad;
+        }
 
-            if let Some(&(i, j)) = best_move {
-                self.tiles[mi][mj] = Entity::None;
-                self.tiles[i][j] = Entity::Mouse;
-                self.mouse_pos = (i, j);
+        self.set_tile(mouse.0, mouse.1, Entity::None);
+        self.set_tile(*y, *x, Entity::Mouse);
+        self.mice[idx] = (*y, *x);
 
-                if i == 0 || i == self.height - 1 || j == 0 || j == self.width - 1 {
-                    TurnResult::GameOver
-                } else {
-                    TurnResult::Good
-                }
-            } else {
-                TurnResult::GameOver
-            }
-        } else {
+        if self.is_edge(*y, *x) {
             TurnResult::GameOver
+        } else {
+            TurnResult::Good
         }
     }
 
-    fn get_valid_neighbours(&self, pi: usize, pj: usize) -> Vec<(usize, usize)> {
-        let neighbours: Vec<(i32, i32)> = if pj.is_multiple_of(2) {
-            vec![(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (-1, 1)]
-        } else {
-            vec![(-1, 0), (1, 0), (0, -1), (0, 1), (1, -1), (1, 1)]
-        };
-
-        neighbours
-            .iter()
-            .map(|(di, dj)| (pi as i32 + di, pj as i32 + dj))
+    pub fn get_valid_neighbours(&self, pi: usize, pj: usize) -> Vec<(usize, usize)> {
+        offset_neighbours(pi as i32, pj as i32)
+            .into_iter()
             .filter(|&(i, j)| {
                 i >= 0
                     && i < self.height as i32
@@ -166,24 +463,28 @@ impl Grid {
             .collect()
     }
 
-    fn get_distance_map(&self) -> Vec<Vec<i32>> {
+    /// Number of moves the closest mouse needs to reach the edge if no more walls are placed.
+    pub fn escape_distance(&self) -> Option<u32> {
+        let dist_map = self.get_distance_map();
+
+        self.mice
+            .iter()
+            .flat_map(|&(mi, mj)| self.get_valid_neighbours(mi, mj))
+            .map(|(i, j)| dist_map[i][j])
+            .filter(|&dist| dist != i32::MAX)
+            .min()
+            .map(|dist| dist as u32 + 1)
+    }
+
+    pub fn get_distance_map(&self) -> Vec<Vec<i32>> {
         let mut dist_map = vec![vec![i32::MAX; self.width]; self.height];
         let mut queue = VecDeque::new();
 
-        let mut add_tile = |i: usize, j: usize| {
-            if self.tiles[i][j] == Entity::None {
+        for (i, j) in self.free_tiles() {
+            if self.is_edge(i, j) {
                 dist_map[i][j] = 0;
                 queue.push_back((i, j));
             }
-        };
-
-        for i in 0..self.height {
-            add_tile(i, 0);
-            add_tile(i, self.width - 1);
-        }
-        for j in 0..self.width {
-            add_tile(0, j);
-            add_tile(self.height - 1, j);
         }
 
         while let Some((i, j)) = queue.pop_front() {
@@ -198,3 +499,8 @@ impl Grid {
         dist_map
     }
 }
+
+/// The keys live in `hexgrid` so the client derives the same hash for its copy of the board.
+fn zobrist_hash(tiles: &[Vec<Entity>]) -> u64 {
+    hexgrid::zobrist_hash(tiles.iter().flatten().map(|&entity| entity as u8))
+}
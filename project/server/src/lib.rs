@@ -0,0 +1,18 @@
+pub mod accounts;
+pub mod ai;
+pub mod archive;
+pub mod config;
+pub mod controller;
+pub mod daily;
+pub mod discovery;
+pub mod encoding;
+pub mod grid;
+pub mod notation;
+pub mod ratings;
+pub mod room;
+pub mod rules;
+pub mod shape;
+pub mod snapshot;
+pub mod solver;
+pub mod stats;
+pub mod tournament;
@@ -1,11 +1,22 @@
-mod controller;
-mod grid;
-mod room;
-
-use crate::controller::Controller;
+use server::config::{ServerConfig, USAGE};
+use server::controller::Controller;
 
 fn main() {
-    match Controller::new() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let config = match ServerConfig::load(&args, |name| std::env::var(name).ok()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid server configuration: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    match Controller::new(&config) {
         Ok(mut controller) => controller.run(),
         Err(e) => eprintln!("Failed starting server: ({})", e),
     }
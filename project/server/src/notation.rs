@@ -0,0 +1,207 @@
+//! One line notation for positions and moves, meant for logs and bug reports.
+//!
+//! A position is `<width>x<height> <rows> <side>`. Rows are separated by `/` and read left to
+//! right like the tiles of a grid line: a number is a run of free tiles, `#` a wall, `M` a mouse
+//! and `-` a tile outside of the board shape. The side to move is `w` for the trapper and `m` for
+//! the mice, e.g. `5x5 5/1#3/2M2/5/5 w`.
+//!
+//! Moves use `row,col` coordinates: `#2,3` places a wall, `2,2>1,2` moves the mouse on `2,2` and
+//! `>1,2` moves whichever mouse can reach `1,2`.
+
+use crate::grid::{Entity, Grid};
+use crate::room::PlayerType;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum NotationErr {
+    #[error("Expected '<width>x<height> <rows> <side>'")]
+    Layout,
+
+    #[error("Invalid board size '{0}'")]
+    Size(String),
+
+    #[error("Unknown tile '{0}'")]
+    UnknownTile(char),
+
+    #[error("Row {0} does not match the board width")]
+    RowWidth(usize),
+
+    #[error("Expected {0} rows")]
+    RowCount(usize),
+
+    #[error("Board needs at least one mouse")]
+    NoMouse,
+
+    #[error("Unknown side to move '{0}'")]
+    Side(String),
+
+    #[error("Invalid move '{0}'")]
+    Move(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Move {
+    Wall((usize, usize)),
+    Mouse {
+        from: Option<(usize, usize)>,
+        to: (usize, usize),
+    },
+}
+
+pub fn format_position(grid: &Grid, to_move: PlayerType) -> String {
+    let rows: Vec<String> = (0..grid.height())
+        .map(|y| {
+            let mut row = String::new();
+            let mut free = 0;
+
+            for x in 0..grid.width() {
+                let symbol = match grid.tile(y, x) {
+                    Entity::None => {
+                        free += 1;
+                        continue;
+                    }
+                    Entity::Wall => '#',
+                    Entity::Mouse => 'M',
+                    Entity::Void => '-',
+                };
+
+                if free > 0 {
+                    row.push_str(&free.to_string());
+                    free = 0;
+                }
+                row.push(symbol);
+            }
+
+            if free > 0 {
+                row.push_str(&free.to_string());
+            }
+            row
+        })
+        .collect();
+
+    let side = match to_move {
+        PlayerType::Mouse => 'm',
+        PlayerType::Wall => 'w',
+    };
+
+    format!(
+        "{}x{} {} {}",
+        grid.width(),
+        grid.height(),
+        rows.join("/"),
+        side
+    )
+}
+
+pub fn parse_position(text: &str) -> Result<(Grid, PlayerType), NotationErr> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let [size, board, side] = parts[..] else {
+        return Err(NotationErr::Layout);
+    };
+
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<usize>().ok()?, h.parse::<usize>().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| NotationErr::Size(size.to_string()))?;
+
+    let rows: Vec<&str> = board.split('/').collect();
+    if rows.len() != height {
+        return Err(NotationErr::RowCount(height));
+    }
+
+    let tiles = rows
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let line = parse_row(y, row, width)?;
+            if line.len() == width {
+                Ok(line)
+            } else {
+                Err(NotationErr::RowWidth(y))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let to_move = match side {
+        "m" => PlayerType::Mouse,
+        "w" => PlayerType::Wall,
+        _ => return Err(NotationErr::Side(side.to_string())),
+    };
+
+    let grid = Grid::from_tiles(tiles).ok_or(NotationErr::NoMouse)?;
+    Ok((grid, to_move))
+}
+
+/// Rows never grow past `width`, a huge run of free tiles is refused before it is expanded.
+fn parse_row(y: usize, row: &str, width: usize) -> Result<Vec<Entity>, NotationErr> {
+    let mut line = Vec::with_capacity(width);
+    let mut free: usize = 0;
+
+    for c in row.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            free = free
+                .checked_mul(10)
+                .and_then(|free| free.checked_add(digit as usize))
+                .ok_or(NotationErr::RowWidth(y))?;
+            continue;
+        }
+
+        // The run and the tile after it both have to fit in the row
+        if line.len().checked_add(free).is_none_or(|len| len >= width) {
+            return Err(NotationErr::RowWidth(y));
+        }
+        line.extend(std::iter::repeat_n(Entity::None, free));
+        free = 0;
+
+        line.push(match c {
+            '#' => Entity::Wall,
+            'M' => Entity::Mouse,
+            '-' => Entity::Void,
+            _ => return Err(NotationErr::UnknownTile(c)),
+        });
+    }
+
+    if line.len().checked_add(free).is_none_or(|len| len > width) {
+        return Err(NotationErr::RowWidth(y));
+    }
+    line.extend(std::iter::repeat_n(Entity::None, free));
+
+    Ok(line)
+}
+
+pub fn format_move(mv: &Move) -> String {
+    match mv {
+        Move::Wall((y, x)) => format!("#{},{}", y, x),
+        Move::Mouse { from, to } => {
+            let from = from.map_or(String::new(), |(y, x)| format!("{},{}", y, x));
+            format!("{}>{},{}", from, to.0, to.1)
+        }
+    }
+}
+
+pub fn parse_move(text: &str) -> Result<Move, NotationErr> {
+    let text = text.trim();
+    let invalid = || NotationErr::Move(text.to_string());
+
+    let coords = |part: &str| -> Result<(usize, usize), NotationErr> {
+        let (y, x) = part.split_once(',').ok_or_else(invalid)?;
+        Ok((
+            y.trim().parse().map_err(|_| invalid())?,
+            x.trim().parse().map_err(|_| invalid())?,
+        ))
+    };
+
+    if let Some(tile) = text.strip_prefix('#') {
+        return Ok(Move::Wall(coords(tile)?));
+    }
+
+    let (from, to) = text.split_once('>').ok_or_else(invalid)?;
+    Ok(Move::Mouse {
+        from: if from.is_empty() {
+            None
+        } else {
+            Some(coords(from)?)
+        },
+        to: coords(to)?,
+    })
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::room::PlayerType;
+
+pub const RATINGS_FILE: &str = "ratings.txt";
+pub const DEFAULT_RATING: f64 = 1500.0;
+pub const DEFAULT_DEVIATION: f64 = 350.0;
+/// Keeps regular players' ratings from freezing after many games.
+pub const MIN_DEVIATION: f64 = 50.0;
+
+/// Glicko scale factor, ln(10) / 400.
+const Q: f64 = std::f64::consts::LN_10 / 400.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RatingErr {
+    #[error("Broken rating entry '{0}'")]
+    Corrupt(String),
+
+    #[error("Io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Glicko-1 rating, the deviation shrinks as the player gets more games in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+        }
+    }
+}
+
+impl Rating {
+    fn g(deviation: f64) -> f64 {
+        1.0 / (1.0 + 3.0 * Q * Q * deviation * deviation / (PI * PI)).sqrt()
+    }
+
+    /// Chance to beat the opponent, uncertain opponents pull it towards a coin flip.
+    pub fn expected(&self, opponent: &Rating) -> f64 {
+        let g = Self::g(opponent.deviation);
+        1.0 / (1.0 + 10f64.powf(-g * (self.rating - opponent.rating) / 400.0))
+    }
+
+    /// Rating after a single game, `score` is 1 for a win and 0 for a loss.
+    pub fn update(&self, opponent: &Rating, score: f64) -> Rating {
+        let g = Self::g(opponent.deviation);
+        let expected = self.expected(opponent);
+        let d2 = 1.0 / (Q * Q * g * g * expected * (1.0 - expected));
+        let precision = 1.0 / (self.deviation * self.deviation) + 1.0 / d2;
+
+        Rating {
+            rating: self.rating + Q / precision * g * (score - expected),
+            deviation: (1.0 / precision).sqrt().max(MIN_DEVIATION),
+        }
+    }
+
+    /// A side with several players is rated like one player with the average rating.
+    pub fn team(ratings: &[Rating]) -> Rating {
+        if ratings.is_empty() {
+            return Rating::default();
+        }
+
+        let count = ratings.len() as f64;
+        Rating {
+            rating: ratings.iter().map(|r| r.rating).sum::<f64>() / count,
+            deviation: (ratings.iter().map(|r| r.deviation.powi(2)).sum::<f64>() / count).sqrt(),
+        }
+    }
+}
+
+pub struct RatingEntry {
+    pub name: String,
+    pub role: PlayerType,
+    /// Unix time of the game in seconds.
+    pub time: u64,
+    pub rating: Rating,
+}
+
+/// Ratings of registered players, one rating per role. Every change is appended to the file as
+/// `name<TAB>m|w<TAB>time<TAB>rating<TAB>deviation` so the whole history is kept.
+pub struct Ratings {
+    path: PathBuf,
+    current: HashMap<(String, PlayerType), Rating>,
+    history: Vec<RatingEntry>,
+}
+
+impl Ratings {
+    /// A missing file means nobody played a rated game yet.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, RatingErr> {
+        let path = path.into();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut ratings = Self {
+            path,
+            current: HashMap::new(),
+            history: Vec::new(),
+        };
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let entry =
+                Self::parse_entry(line).ok_or_else(|| RatingErr::Corrupt(line.to_string()))?;
+            ratings
+                .current
+                .insert((entry.name.clone(), entry.role), entry.rating);
+            ratings.history.push(entry);
+        }
+
+        Ok(ratings)
+    }
+
+    fn parse_entry(line: &str) -> Option<RatingEntry> {
+        let [name, role, time, rating, deviation] =
+            line.split('\t').collect::<Vec<_>>().try_into().ok()?;
+
+        let role = match role {
+            "m" => PlayerType::Mouse,
+            "w" => PlayerType::Wall,
+            _ => return None,
+        };
+
+        Some(RatingEntry {
+            name: name.to_string(),
+            role,
+            time: time.parse().ok()?,
+            rating: Rating {
+                rating: rating.parse().ok()?,
+                deviation: deviation.parse().ok()?,
+            },
+        })
+    }
+
+    /// Players without a rated game in the role start from the default.
+    pub fn get(&self, name: &str, role: PlayerType) -> Rating {
+        self.rated(name, role).unwrap_or_default()
+    }
+
+    /// The rating of players who played a rated game in the role.
+    pub fn rated(&self, name: &str, role: PlayerType) -> Option<Rating> {
+        self.current.get(&(name.to_string(), role)).copied()
+    }
+
+    /// Number of rated games in the role.
+    pub fn games(&self, name: &str, role: PlayerType) -> u32 {
+        self.history(name)
+            .filter(|entry| entry.role == role)
+            .count() as u32
+    }
+
+    /// Highest rated players of a role with their number of rated games.
+    pub fn top(&self, role: PlayerType, count: usize) -> Vec<(&str, Rating, u32)> {
+        let mut players: Vec<(&str, Rating, u32)> = self
+            .current
+            .iter()
+            .filter(|((_, player_role), _)| *player_role == role)
+            .map(|((name, _), rating)| (name.as_str(), *rating, self.games(name, role)))
+            .collect();
+
+        players.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating).then(a.0.cmp(b.0)));
+        players.truncate(count);
+        players
+    }
+
+    /// Every rating the player had, oldest first.
+    pub fn history(&self, name: &str) -> impl Iterator<Item = &RatingEntry> {
+        self.history.iter().filter(move |entry| entry.name == name)
+    }
+
+    pub fn record(
+        &mut self,
+        name: &str,
+        role: PlayerType,
+        rating: Rating,
+    ) -> Result<(), RatingErr> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let role_char = match role {
+            PlayerType::Mouse => 'm',
+            PlayerType::Wall => 'w',
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{:.2}\t{:.2}",
+            name, role_char, time, rating.rating, rating.deviation
+        )?;
+
+        self.current.insert((name.to_string(), role), rating);
+        self.history.push(RatingEntry {
+            name: name.to_string(),
+            role,
+            time,
+            rating,
+        });
+        Ok(())
+    }
+}
@@ -1,4 +1,12 @@
+use crate::ai::{Evaluation, GreedyStrategy, RandomStrategy, Strategy, evaluate};
+use crate::archive::{EndReason, GameRecord, unix_millis};
 use crate::grid::{Entity, Grid};
+use crate::notation::{Move, format_move, format_position};
+use crate::rules::{Rules, RulesErr};
+use crate::shape::{BoardShape, Boards, Mask, ShapeErr, cell_count};
+use crate::snapshot::{RoomSnapshot, SeatSnapshot, SnapshotErr};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use uuid::Uuid;
 
 #[derive(PartialEq)]
@@ -6,38 +14,286 @@ pub enum TurnResult {
     Good,
     Bad,
     GameOver,
+    /// The move was accepted and the same player moves again.
+    Continue,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum PlayerType {
     Mouse,
     Wall,
 }
 
+impl PlayerType {
+    pub fn opponent(self) -> Self {
+        match self {
+            PlayerType::Mouse => PlayerType::Wall,
+            PlayerType::Wall => PlayerType::Mouse,
+        }
+    }
+}
+
+/// Coordinates travel as varints, the upper bound only keeps boards and their bitsets reasonable.
+pub const MIN_BOARD_SIZE: usize = 3;
+pub const MAX_BOARD_SIZE: usize = hexgrid::MAX_BOARD_SIZE;
+pub const MAX_MICE: usize = 4;
+pub const MAX_TEAM_SIZE: u8 = 2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SettingsErr {
+    #[error("Board size must be between {MIN_BOARD_SIZE} and {MAX_BOARD_SIZE}")]
+    InvalidSize,
+
+    #[error("Too many walls, at most {0} fit on this board")]
+    TooManyWalls(usize),
+
+    #[error("Number of mice must be between 1 and {MAX_MICE}")]
+    InvalidMice,
+
+    #[error("Teams must have between 1 and {MAX_TEAM_SIZE} players")]
+    InvalidTeams,
+
+    #[error("Every mouse player needs a mouse of their own")]
+    NotEnoughMice,
+
+    #[error("Invalid board shape: {0}")]
+    Shape(#[from] ShapeErr),
+
+    #[error("Invalid rules: {0}")]
+    Rules(#[from] RulesErr),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RoomSettings {
+    pub width: usize,
+    pub height: usize,
+    pub walls: usize,
+    pub mice: usize,
+    pub shape: BoardShape,
+    pub rules: Rules,
+    pub seed: Option<u64>,
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            width: 11,
+            height: 11,
+            walls: 5,
+            mice: 1,
+            shape: BoardShape::Rectangle,
+            rules: Rules::default(),
+            seed: None,
+        }
+    }
+}
+
+impl RoomSettings {
+    pub fn validate(&self, boards: &Boards) -> Result<(), SettingsErr> {
+        self.checked_mask(boards).map(|_| ())
+    }
+
+    /// The board mask, once every setting was checked against it.
+    fn checked_mask(&self, boards: &Boards) -> Result<Mask, SettingsErr> {
+        self.rules.validate()?;
+        if !(1..=MAX_MICE).contains(&self.mice) {
+            return Err(SettingsErr::InvalidMice);
+        }
+
+        let size_range = MIN_BOARD_SIZE..=MAX_BOARD_SIZE;
+        if !size_range.contains(&self.width) || !size_range.contains(&self.height) {
+            return Err(SettingsErr::InvalidSize);
+        }
+
+        let mask = self.shape.build(self.width, self.height, boards)?;
+        let (width, height) = (mask[0].len(), mask.len());
+        if !size_range.contains(&width) || !size_range.contains(&height) {
+            return Err(SettingsErr::InvalidSize);
+        }
+
+        // Keep at least half of the board free so the walls can always be generated
+        let max_walls = cell_count(&mask) / 2;
+        if self.walls > max_walls {
+            return Err(SettingsErr::TooManyWalls(max_walls));
+        }
+
+        Grid::with_mask(&mask, 0, self.mice, 0)?;
+
+        Ok(mask)
+    }
+}
+
+/// How many players sit on each side of a room.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Seats {
+    pub mice: u8,
+    pub trappers: u8,
+}
+
+impl Seats {
+    pub const DUEL: Seats = Seats {
+        mice: 1,
+        trappers: 1,
+    };
+
+    /// A bot room only seats the human, the bot plays the other side.
+    pub fn solo(player_type: PlayerType) -> Self {
+        match player_type {
+            PlayerType::Mouse => Seats {
+                mice: 1,
+                trappers: 0,
+            },
+            PlayerType::Wall => Seats {
+                mice: 0,
+                trappers: 1,
+            },
+        }
+    }
+
+    pub fn total(&self) -> u8 {
+        self.mice + self.trappers
+    }
+
+    pub fn for_side(&self, side: PlayerType) -> u8 {
+        match side {
+            PlayerType::Mouse => self.mice,
+            PlayerType::Wall => self.trappers,
+        }
+    }
+
+    pub fn validate(&self, settings: &RoomSettings) -> Result<(), SettingsErr> {
+        let team_range = 1..=MAX_TEAM_SIZE;
+        if !team_range.contains(&self.mice) || !team_range.contains(&self.trappers) {
+            return Err(SettingsErr::InvalidTeams);
+        }
+        if settings.mice < self.mice as usize {
+            return Err(SettingsErr::NotEnoughMice);
+        }
+
+        Ok(())
+    }
+}
+
 pub struct Room {
     pub players: Vec<(Uuid, PlayerType)>,
     pub max_players: u8,
+    pub seats: Seats,
+    pub hints: bool,
+    /// Lets players see how far each tile is from the edge.
+    pub analysis: bool,
+    pub daily: Option<u64>,
+    pub settings: RoomSettings,
     grid: Grid,
+    rng: ChaCha8Rng,
+    /// Strategy the bot plays each side with, mice first.
+    bots: [Box<dyn Strategy>; 2],
+    walls_placed: u32,
+    walls_this_turn: u8,
+    /// Side of a bot that could not move, it loses the game.
+    forfeit: Option<PlayerType>,
+    to_move: PlayerType,
+    /// Index of the next player to move on each side, mice first.
+    rotation: [usize; 2],
+    history: Vec<Move>,
+    /// Milliseconds since the start of the game for every move of the history.
+    move_times: Vec<u64>,
+    initial: String,
+    /// Unix time in ms at which the last seat was taken.
+    started: u64,
 }
 
 impl Room {
-    pub fn new(max_players: u8) -> Self {
-        Self {
+    pub fn new(
+        seats: Seats,
+        hints: bool,
+        settings: RoomSettings,
+        boards: &Boards,
+    ) -> Result<Self, SettingsErr> {
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        // Snapshots and custom boards may have changed since the settings were first checked
+        let mask = settings.checked_mask(boards)?;
+        let mut grid = Grid::with_mask(&mask, settings.walls, settings.mice, seed)?;
+        grid.set_rules(settings.rules);
+        let initial = format_position(&grid, PlayerType::Wall);
+
+        // The bot draws from its own stream so its moves do not shift the board generation
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(1);
+
+        Ok(Self {
             players: Vec::new(),
-            max_players,
-            grid: Grid::new(11, 11),
-        }
+            max_players: seats.total(),
+            seats,
+            hints,
+            // Practice games against the bot come with the analysis on
+            analysis: seats.total() == 1,
+            daily: None,
+            settings,
+            grid,
+            rng,
+            bots: [Box::new(GreedyStrategy), Box::new(RandomStrategy)],
+            walls_placed: 0,
+            walls_this_turn: 0,
+            forfeit: None,
+            to_move: PlayerType::Wall,
+            rotation: [0, 0],
+            history: Vec::new(),
+            move_times: Vec::new(),
+            initial,
+            started: 0,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.grid.seed()
     }
 
     pub fn is_available(&self) -> bool {
         self.get_player_count() < self.max_players
     }
 
-    pub fn add_player(&mut self, uid: &Uuid, player_type: &PlayerType) {
+    pub fn free_seats(&self, side: PlayerType) -> u8 {
+        self.seats.for_side(side) - self.side_players(side).len() as u8
+    }
+
+    /// Refuses the player when their side is already full.
+    pub fn add_player(&mut self, uid: &Uuid, player_type: &PlayerType) -> bool {
+        if self.free_seats(*player_type) == 0 {
+            return false;
+        }
+
         self.players.push((*uid, *player_type));
+        if !self.is_available() {
+            self.started = unix_millis();
+        }
+        true
+    }
+
+    /// Hands a seat over to a new connection of the same player.
+    pub fn replace_player(&mut self, old: &Uuid, new: &Uuid) -> bool {
+        match self.players.iter_mut().find(|(id, _)| id == old) {
+            Some((id, _)) => {
+                *id = *new;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Frees the seat of a player leaving before the game started.
+    pub fn remove_player(&mut self, uid: &Uuid) {
+        self.players.retain(|(id, _)| id != uid);
+    }
+
+    pub fn side_players(&self, side: PlayerType) -> Vec<Uuid> {
+        self.players
+            .iter()
+            .filter(|(_, ptype)| *ptype == side)
+            .map(|(id, _)| *id)
+            .collect()
     }
 
-    fn get_player_type(&self, uid: &Uuid) -> Option<PlayerType> {
+    pub fn get_player_type(&self, uid: &Uuid) -> Option<PlayerType> {
         self.players
             .iter()
             .find(|(id, _)| *id == *uid)
@@ -48,37 +304,419 @@ impl Room {
         self.players.len() as u8
     }
 
+    pub fn board_size(&self) -> (usize, usize) {
+        (self.grid.width(), self.grid.height())
+    }
+
     pub fn get_grid(&self) -> Vec<u8> {
         self.grid.as_bytes()
     }
 
-    pub fn get_other_player(&self, uid: &Uuid) -> Option<Uuid> {
-        if self.get_player_count() == 2 {
-            if self.players[0].0 == *uid {
-                Some(self.players[1].0)
-            } else {
-                Some(self.players[0].0)
+    /// Zobrist hash of the board, sent along with every turn so clients can spot drift.
+    pub fn board_hash(&self) -> u64 {
+        self.grid.zobrist()
+    }
+
+    pub fn position(&self) -> String {
+        format_position(&self.grid, self.to_move)
+    }
+
+    /// Every accepted move so far, bot moves included.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    fn push_move(&mut self, mv: Move) {
+        self.history.push(mv);
+        self.move_times
+            .push(unix_millis().saturating_sub(self.started));
+    }
+
+    /// Archive entry of the finished game, `players` names every seat.
+    pub fn record(
+        &self,
+        id: Uuid,
+        players: Vec<(PlayerType, String)>,
+        reason: EndReason,
+    ) -> GameRecord {
+        GameRecord {
+            id,
+            started: self.started,
+            seed: self.seed(),
+            rules: self.settings.rules,
+            position: self.initial.clone(),
+            players,
+            moves: self
+                .move_times
+                .iter()
+                .copied()
+                .zip(self.history.iter().copied())
+                .collect(),
+            reason,
+        }
+    }
+
+    /// Everything needed to rebuild the room, `players` describes every taken seat in order.
+    pub fn snapshot(&self, id: Uuid, players: Vec<SeatSnapshot>) -> RoomSnapshot {
+        RoomSnapshot {
+            id,
+            seats: self.seats,
+            settings: RoomSettings {
+                seed: Some(self.seed()),
+                ..self.settings.clone()
+            },
+            hints: self.hints,
+            analysis: self.analysis,
+            daily: self.daily,
+            to_move: self.to_move,
+            rotation: self.rotation,
+            walls_this_turn: self.walls_this_turn,
+            bot_word_pos: self.rng.get_word_pos(),
+            started: self.started,
+            players,
+            moves: self
+                .move_times
+                .iter()
+                .copied()
+                .zip(self.history.iter().copied())
+                .collect(),
+        }
+    }
+
+    /// Generates the board again and replays the moves. The seats are held by the session ids
+    /// until their players come back.
+    pub fn restore(snapshot: &RoomSnapshot, boards: &Boards) -> Result<Self, SnapshotErr> {
+        let mut room = Room::new(
+            snapshot.seats,
+            snapshot.hints,
+            snapshot.settings.clone(),
+            boards,
+        )?;
+
+        for (time, mv) in &snapshot.moves {
+            let legal = match *mv {
+                Move::Wall((y, x)) => {
+                    room.walls_placed += 1;
+                    room.grid.place(&y, &x, Entity::Wall) == TurnResult::Good
+                }
+                Move::Mouse { from, to: (y, x) } => {
+                    let result = match from {
+                        Some(from) => room.grid.move_mouse_from(from, &y, &x),
+                        None => room.grid.move_mouse(&y, &x),
+                    };
+                    result != TurnResult::Bad && room.grid.mice().contains(&(y, x))
+                }
+            };
+            if !legal {
+                return Err(SnapshotErr::IllegalMove(format_move(mv)));
             }
-        } else {
-            None
+
+            room.history.push(*mv);
+            room.move_times.push(*time);
         }
+
+        room.players = snapshot
+            .players
+            .iter()
+            .map(|seat| (seat.session, seat.side))
+            .collect();
+        room.analysis = snapshot.analysis;
+        room.daily = snapshot.daily;
+        room.to_move = snapshot.to_move;
+        room.rotation = snapshot.rotation;
+        room.walls_this_turn = snapshot.walls_this_turn;
+        room.rng.set_word_pos(snapshot.bot_word_pos);
+        room.started = snapshot.started;
+
+        Ok(room)
+    }
+
+    pub fn get_game_over(&self) -> Vec<u8> {
+        let mut data = self.grid.as_bytes();
+        data.extend(self.seed().to_le_bytes());
+        data
+    }
+
+    pub fn get_other_players(&self, uid: &Uuid) -> Vec<Uuid> {
+        self.players
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| id != uid)
+            .collect()
     }
 
-    pub fn process_turn(&mut self, uid: &Uuid, y: &usize, x: &usize) -> TurnResult {
+    fn side_index(side: PlayerType) -> usize {
+        match side {
+            PlayerType::Mouse => 0,
+            PlayerType::Wall => 1,
+        }
+    }
+
+    /// The player whose turn it is, `None` while the bot moves.
+    pub fn current_player(&self) -> Option<Uuid> {
+        let players = self.side_players(self.to_move);
+        if players.is_empty() {
+            return None;
+        }
+
+        Some(players[self.rotation[Self::side_index(self.to_move)] % players.len()])
+    }
+
+    /// Passes the turn to the next player of the other side. Mouse players whose mice are all
+    /// stuck are skipped while a teammate can still move.
+    fn end_turn(&mut self) {
+        self.rotation[Self::side_index(self.to_move)] += 1;
+        self.to_move = self.to_move.opponent();
+
+        if self.to_move == PlayerType::Mouse {
+            for _ in 0..self.side_players(PlayerType::Mouse).len() {
+                match self.current_player() {
+                    Some(uid) if !self.can_move(&uid) => self.rotation[0] += 1,
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    fn can_move(&self, uid: &Uuid) -> bool {
+        self.grid
+            .mice()
+            .iter()
+            .any(|&m| self.controls_mouse(uid, m) && !self.grid.mouse_moves_from(m).is_empty())
+    }
+
+    /// Mice are shared out between the mouse players in turn, a lone mouse player moves them all.
+    fn controls_mouse(&self, uid: &Uuid, mouse: (usize, usize)) -> bool {
+        let mouse_players = self.side_players(PlayerType::Mouse);
+
+        let Some(player_idx) = mouse_players.iter().position(|id| id == uid) else {
+            return false;
+        };
+
+        self.grid
+            .mice()
+            .iter()
+            .position(|&m| m == mouse)
+            .is_some_and(|idx| idx % mouse_players.len() == player_idx)
+    }
+
+    /// Only the current player may move. Mouse players may name the mouse to move, otherwise the
+    /// first of theirs that can reach the tile moves.
+    pub fn process_turn(
+        &mut self,
+        uid: &Uuid,
+        y: &usize,
+        x: &usize,
+        mouse: Option<(usize, usize)>,
+    ) -> TurnResult {
+        if self.current_player() != Some(*uid) {
+            return TurnResult::Bad;
+        }
+
+        let result = self.play_turn(uid, y, x, mouse);
+        if result == TurnResult::Good {
+            self.end_turn();
+        }
+        result
+    }
+
+    fn play_turn(
+        &mut self,
+        uid: &Uuid,
+        y: &usize,
+        x: &usize,
+        mouse: Option<(usize, usize)>,
+    ) -> TurnResult {
         if let Some(player_type) = self.get_player_type(uid) {
             match player_type {
-                PlayerType::Mouse => self.grid.move_mouse(y, x),
-                PlayerType::Wall => self.grid.place(y, x, Entity::Wall),
+                PlayerType::Mouse => {
+                    if self.grid.is_trapped() {
+                        return TurnResult::GameOver;
+                    }
+
+                    let mouse = mouse.or_else(|| {
+                        self.grid.mice().iter().copied().find(|&m| {
+                            self.controls_mouse(uid, m)
+                                && self.grid.mouse_moves_from(m).contains(&(*y, *x))
+                        })
+                    });
+
+                    match mouse {
+                        Some(mouse) if self.controls_mouse(uid, mouse) => {
+                            let result = self.grid.move_mouse_from(mouse, y, x);
+                            if self.grid.mice().contains(&(*y, *x)) {
+                                self.push_move(Move::Mouse {
+                                    from: Some(mouse),
+                                    to: (*y, *x),
+                                });
+                            }
+                            result
+                        }
+                        _ => TurnResult::Bad,
+                    }
+                }
+                PlayerType::Wall => {
+                    let result = self.grid.place(y, x, Entity::Wall);
+                    if result != TurnResult::Good {
+                        return result;
+                    }
+
+                    self.walls_placed += 1;
+                    self.walls_this_turn += 1;
+                    self.push_move(Move::Wall((*y, *x)));
+
+                    // Extra walls are pointless once the mouse is stuck
+                    if self.walls_this_turn < self.grid.rules().walls_per_turn
+                        && !self.grid.is_trapped()
+                        && !self.grid.wall_moves().is_empty()
+                    {
+                        TurnResult::Continue
+                    } else {
+                        self.walls_this_turn = 0;
+                        TurnResult::Good
+                    }
+                }
             }
         } else {
             TurnResult::Bad
         }
     }
 
+    /// Why the game ended, `None` while it is still running. A mouse that escaped onto an edge
+    /// tile without free neighbours is stuck as well, the escape counts.
+    pub fn end_reason(&self) -> Option<EndReason> {
+        if self.grid.has_escaped() {
+            Some(EndReason::Escaped)
+        } else if self.grid.is_trapped() {
+            Some(EndReason::Trapped)
+        } else {
+            self.forfeit.map(EndReason::Forfeit)
+        }
+    }
+
+    /// Day and number of walls of a finished daily challenge, if the trapper won it.
+    pub fn daily_result(&self) -> Option<(u64, u32)> {
+        let day = self.daily?;
+        (self.end_reason() == Some(EndReason::Trapped)).then_some((day, self.walls_placed))
+    }
+
+    /// Suggested move for the player, mouse moves name the mouse that should take the step.
+    pub fn hint(&self, uid: &Uuid) -> Option<(Option<Move>, Evaluation)> {
+        if !self.hints {
+            return None;
+        }
+
+        // Hints draw from their own stream so asking for one never shifts the bot moves
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed());
+        rng.set_stream(2);
+
+        let player_type = self.get_player_type(uid)?;
+        let suggestion = GreedyStrategy
+            .choose(&self.grid, player_type, &mut rng)
+            .map(|tile| match player_type {
+                PlayerType::Mouse => Move::Mouse {
+                    from: self.mouse_source(tile),
+                    to: tile,
+                },
+                PlayerType::Wall => Move::Wall(tile),
+            });
+
+        Some((suggestion, evaluate(&self.grid)))
+    }
+
+    /// The mouse that moves to the tile, the first one that can reach it like in `move_mouse`.
+    fn mouse_source(&self, tile: (usize, usize)) -> Option<(usize, usize)> {
+        self.grid
+            .mice()
+            .iter()
+            .copied()
+            .find(|&m| self.grid.mouse_moves_from(m).contains(&tile))
+    }
+
+    /// Moves every tile needs to reach the edge, `None` for walls, mice and cut off tiles.
+    pub fn distance_map(&self) -> Option<Vec<Vec<Option<u32>>>> {
+        if !self.analysis {
+            return None;
+        }
+
+        let map = self
+            .grid
+            .get_distance_map()
+            .into_iter()
+            .map(|line| {
+                line.into_iter()
+                    .map(|dist| (dist != i32::MAX).then_some(dist as u32))
+                    .collect()
+            })
+            .collect();
+
+        Some(map)
+    }
+
+    pub fn set_bot(&mut self, side: PlayerType, strategy: Box<dyn Strategy>) {
+        match side {
+            PlayerType::Mouse => self.bots[0] = strategy,
+            PlayerType::Wall => self.bots[1] = strategy,
+        }
+    }
+
     pub fn ai_turn(&mut self) -> TurnResult {
+        let result = self.play_ai_turn();
+        if result == TurnResult::Good {
+            self.end_turn();
+        }
+        result
+    }
+
+    fn play_ai_turn(&mut self) -> TurnResult {
         match self.players[0].1 {
-            PlayerType::Mouse => self.grid.place_random(Entity::Wall),
-            PlayerType::Wall => self.grid.move_mouse_random(),
+            PlayerType::Mouse => {
+                let mut placed = 0;
+                while placed < self.grid.rules().walls_per_turn && !self.grid.is_trapped() {
+                    let Some((y, x)) = self.bots[1].wall_move(&self.grid, &mut self.rng) else {
+                        break;
+                    };
+                    if self.grid.place(&y, &x, Entity::Wall) != TurnResult::Good {
+                        return self.bot_forfeits(PlayerType::Wall);
+                    }
+
+                    self.walls_placed += 1;
+                    self.push_move(Move::Wall((y, x)));
+                    placed += 1;
+                }
+
+                if self.grid.is_trapped() {
+                    TurnResult::GameOver
+                } else if placed == 0 {
+                    self.bot_forfeits(PlayerType::Wall)
+                } else {
+                    TurnResult::Good
+                }
+            }
+            PlayerType::Wall => {
+                let Some((y, x)) = self.bots[0].mouse_move(&self.grid, &mut self.rng) else {
+                    if self.grid.is_trapped() {
+                        return TurnResult::GameOver;
+                    }
+                    return self.bot_forfeits(PlayerType::Mouse);
+                };
+
+                let from = self.mouse_source((y, x));
+                let result = self.grid.move_mouse(&y, &x);
+                if result == TurnResult::Bad {
+                    return self.bot_forfeits(PlayerType::Mouse);
+                }
+                if self.grid.mice().contains(&(y, x)) {
+                    self.push_move(Move::Mouse { from, to: (y, x) });
+                }
+                result
+            }
         }
     }
+
+    /// A bot without a legal move loses instead of passing its turn back to the player.
+    fn bot_forfeits(&mut self, side: PlayerType) -> TurnResult {
+        self.forfeit = Some(side);
+        TurnResult::GameOver
+    }
 }
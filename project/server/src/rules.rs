@@ -0,0 +1,63 @@
+pub const MAX_WALLS_PER_TURN: u8 = 3;
+pub const MAX_MOUSE_STEPS: u8 = 3;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RulesErr {
+    #[error("Walls per turn must be between 1 and {MAX_WALLS_PER_TURN}")]
+    WallsPerTurn,
+
+    #[error("Mouse steps must be between 1 and {MAX_MOUSE_STEPS}")]
+    MouseSteps,
+}
+
+/// House rules of a room, the defaults are the standard game.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rules {
+    pub walls_per_turn: u8,
+    pub mouse_steps: u8,
+    pub no_wall_near_mouse: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            walls_per_turn: 1,
+            mouse_steps: 1,
+            no_wall_near_mouse: false,
+        }
+    }
+}
+
+impl Rules {
+    pub fn validate(&self) -> Result<(), RulesErr> {
+        if !(1..=MAX_WALLS_PER_TURN).contains(&self.walls_per_turn) {
+            return Err(RulesErr::WallsPerTurn);
+        }
+        if !(1..=MAX_MOUSE_STEPS).contains(&self.mouse_steps) {
+            return Err(RulesErr::MouseSteps);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_standard(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Wire format: walls per turn, mouse steps and a flags byte.
+    pub fn as_bytes(&self) -> [u8; 3] {
+        [
+            self.walls_per_turn,
+            self.mouse_steps,
+            self.no_wall_near_mouse as u8,
+        ]
+    }
+
+    pub fn from_bytes(bytes: [u8; 3]) -> Self {
+        Self {
+            walls_per_turn: bytes[0],
+            mouse_steps: bytes[1],
+            no_wall_near_mouse: bytes[2] & 1 != 0,
+        }
+    }
+}
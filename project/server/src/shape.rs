@@ -0,0 +1,177 @@
+use hexgrid::offset_distance;
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path, sync::Arc};
+
+pub const BOARDS_DIR: &str = "boards";
+
+/// Which cells of the bounding rectangle exist, indexed like the grid tiles.
+pub type Mask = Vec<Vec<bool>>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ShapeErr {
+    #[error("Unknown board '{0}'")]
+    UnknownBoard(String),
+
+    #[error("Board file {0}: {1}")]
+    File(String, Box<ShapeErr>),
+
+    #[error("Board file is empty")]
+    Empty,
+
+    #[error("Unknown tile '{0}' in board file")]
+    UnknownTile(char),
+
+    #[error("Board has no room for the mouse")]
+    NoStart,
+
+    #[error("Board has no room for {0} walls")]
+    NoWallRoom(usize),
+
+    #[error("Io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum BoardShape {
+    Rectangle,
+    Hexagon,
+    Mask(String),
+}
+
+impl BoardShape {
+    pub fn id(&self) -> u8 {
+        match self {
+            BoardShape::Rectangle => 0,
+            BoardShape::Hexagon => 1,
+            BoardShape::Mask(_) => 2,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            BoardShape::Rectangle => "rectangle",
+            BoardShape::Hexagon => "hexagon",
+            BoardShape::Mask(name) => name,
+        }
+    }
+
+    /// Rectangles use the whole `width` x `height`, hexagons take `width` as their diameter and
+    /// masks come with their own size.
+    pub fn build(&self, width: usize, height: usize, boards: &Boards) -> Result<Mask, ShapeErr> {
+        match self {
+            BoardShape::Rectangle => Ok(vec![vec![true; width]; height]),
+            BoardShape::Hexagon => Ok(hexagon(width / 2)),
+            BoardShape::Mask(name) => boards.get(name).cloned(),
+        }
+    }
+}
+
+pub fn hexagon(radius: usize) -> Mask {
+    let size = 2 * radius + 1;
+    let center = (radius, radius);
+
+    (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| offset_distance((y, x), center) as usize <= radius)
+                .collect()
+        })
+        .collect()
+}
+
+/// The custom boards, read once at startup and shared by every room built from them.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Boards {
+    masks: Arc<BTreeMap<String, Mask>>,
+}
+
+impl Boards {
+    /// Every `<name>.txt` in `dir`, one line per row with `.` for cells and `-` for holes. A
+    /// missing directory has no boards, a broken file is reported.
+    pub fn load(dir: &Path) -> Result<Self, ShapeErr> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut masks = BTreeMap::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "txt") {
+                continue;
+            }
+            let Some(name) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .filter(|name| is_valid_name(name))
+            else {
+                continue;
+            };
+
+            let mask = parse_mask(&fs::read_to_string(&path)?)
+                .map_err(|e| ShapeErr::File(name.to_string(), Box::new(e)))?;
+            masks.insert(name.to_string(), mask);
+        }
+
+        Ok(Self {
+            masks: Arc::new(masks),
+        })
+    }
+
+    pub fn from_masks(masks: impl IntoIterator<Item = (String, Mask)>) -> Self {
+        Self {
+            masks: Arc::new(masks.into_iter().collect()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Mask, ShapeErr> {
+        self.masks
+            .get(name)
+            .ok_or_else(|| ShapeErr::UnknownBoard(name.to_string()))
+    }
+
+    /// Sorted by name.
+    pub fn names(&self) -> Vec<String> {
+        self.masks.keys().cloned().collect()
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn parse_mask(text: &str) -> Result<Mask, ShapeErr> {
+    let mut mask = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .map(|line| {
+            line.chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    '.' => Ok(true),
+                    '-' => Ok(false),
+                    _ => Err(ShapeErr::UnknownTile(c)),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Mask, _>>()?;
+
+    // Short lines are padded with holes so the mask stays rectangular
+    let width = mask.iter().map(Vec::len).max().unwrap_or(0);
+    if width == 0 {
+        return Err(ShapeErr::Empty);
+    }
+    for line in mask.iter_mut() {
+        line.resize(width, false);
+    }
+
+    Ok(mask)
+}
+
+pub fn cell_count(mask: &Mask) -> usize {
+    mask.iter().flatten().filter(|cell| **cell).count()
+}
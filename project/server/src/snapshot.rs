@@ -0,0 +1,302 @@
+//! Rooms with players in them, saved every few seconds so a restarted server can pick up the
+//! running games. Every room is a block of lines:
+//!
+//! ```text
+//! room <id>
+//! seats <mice> <trappers>
+//! board <width> <height> <walls> <mice> <seed>
+//! shape <rectangle|hexagon|mask <name>>
+//! rules <walls per turn> <mouse steps> <keep away 0|1>
+//! options <hints 0|1> <analysis 0|1> <daily day or ->
+//! turn <m|w> <mouse rotation> <trapper rotation> <walls placed this turn>
+//! bot <word position of the bot's random stream>
+//! started <unix time in ms, 0 while waiting for players>
+//! player <session> <m|w> <account or -> <name>
+//! move <ms since the start> <move in notation>
+//! end
+//! ```
+//!
+//! The board itself is not saved, it is generated again from the seed and the moves are replayed.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::archive::{parse_side, side_char};
+use crate::notation::{Move, format_move, parse_move};
+use crate::room::{PlayerType, RoomSettings, Seats, SettingsErr};
+use crate::rules::Rules;
+use crate::shape::BoardShape;
+
+pub const SNAPSHOT_FILE: &str = "snapshot.txt";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotErr {
+    #[error("Broken room snapshot at '{0}'")]
+    Corrupt(String),
+
+    #[error("Invalid room settings: {0}")]
+    Settings(#[from] SettingsErr),
+
+    #[error("Move {0} does not fit the board")]
+    IllegalMove(String),
+
+    #[error("Io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// A taken seat. Players get it back by sending their session id.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SeatSnapshot {
+    pub session: Uuid,
+    pub side: PlayerType,
+    pub account: Option<String>,
+    pub name: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RoomSnapshot {
+    pub id: Uuid,
+    pub seats: Seats,
+    /// Always carries the seed of the board.
+    pub settings: RoomSettings,
+    pub hints: bool,
+    pub analysis: bool,
+    pub daily: Option<u64>,
+    pub to_move: PlayerType,
+    /// Index of the next player to move on each side, mice first.
+    pub rotation: [usize; 2],
+    pub walls_this_turn: u8,
+    pub bot_word_pos: u128,
+    pub started: u64,
+    pub players: Vec<SeatSnapshot>,
+    pub moves: Vec<(u64, Move)>,
+}
+
+impl RoomSnapshot {
+    pub fn format(&self) -> String {
+        let settings = &self.settings;
+        let shape = match &settings.shape {
+            BoardShape::Mask(name) => format!("mask {}", name),
+            shape => shape.name().to_string(),
+        };
+
+        let mut lines = vec![
+            format!("room {}", self.id),
+            format!("seats {} {}", self.seats.mice, self.seats.trappers),
+            format!(
+                "board {} {} {} {} {}",
+                settings.width,
+                settings.height,
+                settings.walls,
+                settings.mice,
+                settings.seed.unwrap_or_default()
+            ),
+            format!("shape {}", shape),
+            format!(
+                "rules {} {} {}",
+                settings.rules.walls_per_turn,
+                settings.rules.mouse_steps,
+                settings.rules.no_wall_near_mouse as u8
+            ),
+            format!(
+                "options {} {} {}",
+                self.hints as u8,
+                self.analysis as u8,
+                self.daily.map_or("-".to_string(), |day| day.to_string())
+            ),
+            format!(
+                "turn {} {} {} {}",
+                side_char(self.to_move),
+                self.rotation[0],
+                self.rotation[1],
+                self.walls_this_turn
+            ),
+            format!("bot {}", self.bot_word_pos),
+            format!("started {}", self.started),
+        ];
+
+        for seat in &self.players {
+            let name = seat.name.replace(['\n', '\r'], " ");
+            lines.push(format!(
+                "player {} {} {} {}",
+                seat.session,
+                side_char(seat.side),
+                seat.account.as_deref().unwrap_or("-"),
+                name
+            ));
+        }
+        for (time, mv) in &self.moves {
+            lines.push(format!("move {} {}", time, format_move(mv)));
+        }
+        lines.push("end".to_string());
+
+        lines.join("\n") + "\n"
+    }
+
+    fn parse_lines(lines: &[&str]) -> Option<Self> {
+        let mut id = None;
+        let mut seats = None;
+        let mut board = None;
+        let mut shape = None;
+        let mut rules = None;
+        let mut options = None;
+        let mut turn = None;
+        let mut bot_word_pos = None;
+        let mut started = None;
+        let mut players = Vec::new();
+        let mut moves = Vec::new();
+
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let numbers =
+                || -> Option<Vec<u64>> { value.split(' ').map(|v| v.parse().ok()).collect() };
+
+            match key {
+                "room" => id = Some(Uuid::parse_str(value).ok()?),
+                "seats" => {
+                    let [mice, trappers] = numbers()?.try_into().ok()?;
+                    seats = Some(Seats {
+                        mice: mice.try_into().ok()?,
+                        trappers: trappers.try_into().ok()?,
+                    });
+                }
+                "board" => {
+                    let values: [u64; 5] = numbers()?.try_into().ok()?;
+                    board = Some(values);
+                }
+                "shape" => {
+                    shape = Some(match value.split_once(' ') {
+                        Some(("mask", name)) => BoardShape::Mask(name.to_string()),
+                        None if value == "rectangle" => BoardShape::Rectangle,
+                        None if value == "hexagon" => BoardShape::Hexagon,
+                        _ => return None,
+                    });
+                }
+                "rules" => {
+                    let [walls_per_turn, mouse_steps, keep_away] = numbers()?.try_into().ok()?;
+                    rules = Some(Rules {
+                        walls_per_turn: walls_per_turn.try_into().ok()?,
+                        mouse_steps: mouse_steps.try_into().ok()?,
+                        no_wall_near_mouse: keep_away != 0,
+                    });
+                }
+                "options" => {
+                    let [hints, analysis, daily] =
+                        value.split(' ').collect::<Vec<_>>().try_into().ok()?;
+                    let daily = match daily {
+                        "-" => None,
+                        day => Some(day.parse().ok()?),
+                    };
+                    options = Some((hints == "1", analysis == "1", daily));
+                }
+                "turn" => {
+                    let [side, mouse, trapper, walls] =
+                        value.split(' ').collect::<Vec<_>>().try_into().ok()?;
+                    turn = Some((
+                        parse_side(side)?,
+                        [mouse.parse().ok()?, trapper.parse().ok()?],
+                        walls.parse().ok()?,
+                    ));
+                }
+                "bot" => bot_word_pos = Some(value.parse().ok()?),
+                "started" => started = Some(value.parse().ok()?),
+                "player" => {
+                    let mut parts = value.splitn(4, ' ');
+                    let session = Uuid::parse_str(parts.next()?).ok()?;
+                    let side = parse_side(parts.next()?)?;
+                    let account = match parts.next()? {
+                        "-" => None,
+                        account => Some(account.to_string()),
+                    };
+                    players.push(SeatSnapshot {
+                        session,
+                        side,
+                        account,
+                        name: parts.next().unwrap_or("").to_string(),
+                    });
+                }
+                "move" => {
+                    let (time, mv) = value.split_once(' ')?;
+                    moves.push((time.parse().ok()?, parse_move(mv).ok()?));
+                }
+                "end" => {}
+                _ => return None,
+            }
+        }
+
+        let [width, height, walls, mice, seed] = board?;
+        let (hints, analysis, daily) = options?;
+        let (to_move, rotation, walls_this_turn) = turn?;
+
+        Some(Self {
+            id: id?,
+            seats: seats?,
+            settings: RoomSettings {
+                width: width as usize,
+                height: height as usize,
+                walls: walls as usize,
+                mice: mice as usize,
+                shape: shape?,
+                rules: rules?,
+                seed: Some(seed),
+            },
+            hints,
+            analysis,
+            daily,
+            to_move,
+            rotation,
+            walls_this_turn,
+            bot_word_pos: bot_word_pos?,
+            started: started?,
+            players,
+            moves,
+        })
+    }
+}
+
+/// Splits a snapshot into its rooms, every room has to be closed by an `end` line.
+pub fn parse_snapshot(text: &str) -> Result<Vec<RoomSnapshot>, SnapshotErr> {
+    let mut rooms = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        lines.push(line);
+        if line == "end" {
+            let room = RoomSnapshot::parse_lines(&lines)
+                .ok_or_else(|| SnapshotErr::Corrupt(lines[0].to_string()))?;
+            rooms.push(room);
+            lines.clear();
+        }
+    }
+
+    match lines.first() {
+        Some(line) => Err(SnapshotErr::Corrupt(line.to_string())),
+        None => Ok(rooms),
+    }
+}
+
+/// A missing file means there is nothing to restore.
+pub fn load(path: &Path) -> Result<Vec<RoomSnapshot>, SnapshotErr> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_snapshot(&text),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replaces the whole file. The rooms go to a temporary file first and are renamed over the old
+/// snapshot, a crash mid write leaves the previous snapshot intact.
+pub fn save(path: &Path, rooms: &[RoomSnapshot]) -> Result<(), SnapshotErr> {
+    let text: String = rooms.iter().map(RoomSnapshot::format).collect();
+
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use crate::grid::{Entity, Grid};
+use crate::notation;
+use crate::room::PlayerType;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const DEFAULT_MAX_NODES: u64 = 5_000_000;
+
+/// Result of a position for the side to move, with the number of plies until the game ends.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Win(u32),
+    Loss(u32),
+}
+
+impl Outcome {
+    fn plies(&self) -> u32 {
+        match self {
+            Outcome::Win(plies) | Outcome::Loss(plies) => *plies,
+        }
+    }
+
+    /// The same result seen by the opponent, one ply earlier.
+    fn parent(&self) -> Outcome {
+        match self {
+            Outcome::Win(plies) => Outcome::Loss(plies + 1),
+            Outcome::Loss(plies) => Outcome::Win(plies + 1),
+        }
+    }
+
+    /// Quick wins first, then the longest resistance.
+    fn is_better_than(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (Outcome::Win(a), Outcome::Win(b)) => a < b,
+            (Outcome::Win(_), Outcome::Loss(_)) => true,
+            (Outcome::Loss(_), Outcome::Win(_)) => false,
+            (Outcome::Loss(a), Outcome::Loss(b)) => a > b,
+        }
+    }
+}
+
+pub struct Solution {
+    pub outcome: Outcome,
+    pub best_move: Option<(usize, usize)>,
+}
+
+/// Depths searched so far for a position: `winner` forces the end within `win` plies and
+/// cannot within `no_win`.
+#[derive(Default)]
+struct Bounds {
+    win: Option<u32>,
+    no_win: Option<u32>,
+}
+
+/// The whole encoded board with the side to move and the side trying to win, so two positions
+/// never share an entry.
+type PositionKey = (Vec<u8>, PlayerType, PlayerType);
+
+pub struct Solver {
+    table: HashMap<PositionKey, Bounds>,
+    nodes: u64,
+    max_nodes: u64,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_NODES)
+    }
+}
+
+impl Solver {
+    pub fn new(max_nodes: u64) -> Self {
+        Self {
+            table: HashMap::new(),
+            nodes: 0,
+            max_nodes,
+        }
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Solves the position exactly, or gives up with `None` once the node budget is spent.
+    pub fn solve(&mut self, grid: &Grid, to_move: PlayerType) -> Option<Solution> {
+        let outcome = self.outcome(grid, to_move)?;
+        let opponent = Self::opponent(to_move);
+
+        let mut best: Option<(Outcome, Option<(usize, usize)>)> = None;
+        for (y, x) in Self::moves(grid, to_move) {
+            let child = Self::play(grid, to_move, y, x);
+            let child_outcome = if to_move == PlayerType::Mouse && grid.is_edge(y, x) {
+                Outcome::Win(1)
+            } else {
+                self.outcome(&child, opponent)?.parent()
+            };
+
+            if best.is_none_or(|(b, _)| child_outcome.is_better_than(&b)) {
+                best = Some((child_outcome, Some((y, x))));
+            }
+            if child_outcome == outcome {
+                break;
+            }
+        }
+
+        Some(Solution {
+            outcome,
+            best_move: best.and_then(|(_, best_move)| best_move),
+        })
+    }
+
+    /// Iterative deepening, the first depth at which either side can force a win gives both the
+    /// winner and the distance to mate.
+    fn outcome(&mut self, grid: &Grid, to_move: PlayerType) -> Option<Outcome> {
+        let max_depth = 2 * grid.free_tiles().len() as u32 + 2;
+        let opponent = Self::opponent(to_move);
+
+        for depth in 0..=max_depth {
+            if self.forced(grid, to_move, to_move, depth)? {
+                return Some(Outcome::Win(depth));
+            }
+            if self.forced(grid, to_move, opponent, depth)? {
+                return Some(Outcome::Loss(depth));
+            }
+        }
+
+        None
+    }
+
+    /// Whether `winner` can force the end of the game in its favour within `depth` plies.
+    fn forced(
+        &mut self,
+        grid: &Grid,
+        to_move: PlayerType,
+        winner: PlayerType,
+        depth: u32,
+    ) -> Option<bool> {
+        let key = (grid.as_bytes(), to_move, winner);
+        if let Some(bounds) = self.table.get(&key) {
+            // A win within fewer plies is a win within more, and the other way round for losses
+            if bounds.win.is_some_and(|win| win <= depth) {
+                return Some(true);
+            }
+            if bounds.no_win.is_some_and(|no_win| no_win >= depth) {
+                return Some(false);
+            }
+        }
+
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            return None;
+        }
+
+        let moves = Self::moves(grid, to_move);
+        let result = if moves.is_empty() {
+            // Either the mouse cannot move or the trapper has nothing left to block
+            winner == PlayerType::Wall
+        } else if depth == 0 || !Self::can_escape_within(grid, to_move, winner, depth) {
+            false
+        } else {
+            let mut result = to_move != winner;
+            for (y, x) in moves {
+                let child_result = if to_move == PlayerType::Mouse && grid.is_edge(y, x) {
+                    winner == PlayerType::Mouse
+                } else {
+                    let child = Self::play(grid, to_move, y, x);
+                    self.forced(&child, Self::opponent(to_move), winner, depth - 1)?
+                };
+
+                if child_result == (to_move == winner) {
+                    result = child_result;
+                    break;
+                }
+            }
+            result
+        };
+
+        let bounds = self.table.entry(key).or_default();
+        if result {
+            bounds.win = Some(bounds.win.map_or(depth, |win| win.min(depth)));
+        } else {
+            bounds.no_win = Some(bounds.no_win.map_or(depth, |no_win| no_win.max(depth)));
+        }
+        Some(result)
+    }
+
+    /// Cheap bound, the mouse needs at least one move per tile between it and the edge.
+    fn can_escape_within(grid: &Grid, to_move: PlayerType, winner: PlayerType, depth: u32) -> bool {
+        if winner == PlayerType::Wall {
+            return true;
+        }
+
+        match grid.escape_distance() {
+            Some(distance) => {
+                let needed = match to_move {
+                    PlayerType::Mouse => 2 * distance - 1,
+                    PlayerType::Wall => 2 * distance,
+                };
+                needed <= depth
+            }
+            None => false,
+        }
+    }
+
+    fn moves(grid: &Grid, to_move: PlayerType) -> Vec<(usize, usize)> {
+        match to_move {
+            PlayerType::Mouse => {
+                let (mi, mj) = grid.mouse_pos();
+                let dist_map = grid.get_distance_map();
+                let mut moves = grid.get_valid_neighbours(mi, mj);
+                moves.sort_by_key(|&(i, j)| dist_map[i][j]);
+                moves
+            }
+            PlayerType::Wall => {
+                if grid.is_trapped() {
+                    Vec::new()
+                } else {
+                    // A wall the mouse can never reach is a wasted turn, and an extra wall can
+                    // never hurt the trapper, so only the mouse's region has to be searched.
+                    Self::reachable(grid)
+                }
+            }
+        }
+    }
+
+    fn play(grid: &Grid, to_move: PlayerType, y: usize, x: usize) -> Grid {
+        let mut child = grid.clone();
+        match to_move {
+            PlayerType::Mouse => child.move_mouse(&y, &x),
+            PlayerType::Wall => child.place(&y, &x, Entity::Wall),
+        };
+        child
+    }
+
+    fn opponent(side: PlayerType) -> PlayerType {
+        match side {
+            PlayerType::Mouse => PlayerType::Wall,
+            PlayerType::Wall => PlayerType::Mouse,
+        }
+    }
+
+    /// Tiles the mouse can still walk to, closest first.
+    fn reachable(grid: &Grid) -> Vec<(usize, usize)> {
+        let start = grid.mouse_pos();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([start]);
+        let mut tiles = Vec::new();
+
+        while let Some((i, j)) = queue.pop_front() {
+            for next in grid.get_valid_neighbours(i, j) {
+                if seen.insert(next) {
+                    tiles.push(next);
+                    // The mouse stops as soon as it reaches the edge
+                    if !grid.is_edge(next.0, next.1) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        tiles
+    }
+}
+
+/// Reads a position file: the side to move ("mouse" or "wall") on the first line, followed by
+/// one line per row using `.` for free tiles, `#` for walls, `M` for the mouse and `-` for cells
+/// outside of the board shape. A single line in the game notation is accepted as well.
+pub fn parse_position(text: &str) -> Result<(Grid, PlayerType), String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"));
+
+    let first = lines.next().ok_or("Empty position".to_string())?;
+    let to_move = match first.to_lowercase().as_str() {
+        "mouse" => PlayerType::Mouse,
+        "wall" => PlayerType::Wall,
+        _ if first.contains(' ') => {
+            let (grid, to_move) = notation::parse_position(first).map_err(|e| e.to_string())?;
+            return single_mouse(grid).map(|grid| (grid, to_move));
+        }
+        _ => return Err(format!("Unknown side to move '{}'", first)),
+    };
+
+    let tiles = lines
+        .map(|line| {
+            line.chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    '.' => Ok(Entity::None),
+                    '#' => Ok(Entity::Wall),
+                    'M' => Ok(Entity::Mouse),
+                    '-' => Ok(Entity::Void),
+                    _ => Err(format!("Unknown tile '{}'", c)),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let grid = Grid::from_tiles(tiles)
+        .ok_or("Board must be rectangular and contain a mouse".to_string())?;
+
+    single_mouse(grid).map(|grid| (grid, to_move))
+}
+
+/// The solver only searches the standard game with one mouse, which has not escaped yet.
+fn single_mouse(grid: Grid) -> Result<Grid, String> {
+    if grid.mice().len() != 1 {
+        return Err("Board must contain exactly one mouse".to_string());
+    }
+
+    let (y, x) = grid.mouse_pos();
+    if grid.is_edge(y, x) {
+        return Err("The mouse already stands on the edge".to_string());
+    }
+
+    Ok(grid)
+}
+
+pub fn describe(outcome: &Outcome, to_move: PlayerType) -> String {
+    let side = match to_move {
+        PlayerType::Mouse => "Mouse",
+        PlayerType::Wall => "Trapper",
+    };
+
+    match outcome {
+        Outcome::Win(_) => format!("{} to move wins in {} plies", side, outcome.plies()),
+        Outcome::Loss(_) => format!("{} to move loses in {} plies", side, outcome.plies()),
+    }
+}
@@ -0,0 +1,70 @@
+use crate::archive::{EndReason, GameRecord};
+use crate::notation::Move;
+use crate::room::PlayerType;
+
+/// Results of a player over the archived games, counters are indexed by role, mice first.
+#[derive(Default, Debug, PartialEq)]
+pub struct PlayerStats {
+    pub games: [u32; 2],
+    pub wins: [u32; 2],
+    pub longest_streak: u32,
+    /// Walls the player's side needed in the games they won by trapping the mice.
+    trap_walls: u32,
+    traps: u32,
+}
+
+fn role_index(role: PlayerType) -> usize {
+    match role {
+        PlayerType::Mouse => 0,
+        PlayerType::Wall => 1,
+    }
+}
+
+impl PlayerStats {
+    /// `games` have to be in the order they were played for the streaks.
+    pub fn collect<'a>(name: &str, games: impl IntoIterator<Item = &'a GameRecord>) -> Self {
+        let mut stats = Self::default();
+        let mut streak = 0;
+
+        for game in games {
+            let Some(&(role, _)) = game.players.iter().find(|(_, player)| player == name) else {
+                continue;
+            };
+            let idx = role_index(role);
+            stats.games[idx] += 1;
+
+            if game.reason.winner() != role {
+                streak = 0;
+                continue;
+            }
+
+            stats.wins[idx] += 1;
+            streak += 1;
+            stats.longest_streak = stats.longest_streak.max(streak);
+
+            if game.reason == EndReason::Trapped {
+                stats.traps += 1;
+                stats.trap_walls += game
+                    .moves
+                    .iter()
+                    .filter(|(_, mv)| matches!(mv, Move::Wall(_)))
+                    .count() as u32;
+            }
+        }
+
+        stats
+    }
+
+    pub fn role_games(&self, role: PlayerType) -> u32 {
+        self.games[role_index(role)]
+    }
+
+    pub fn role_wins(&self, role: PlayerType) -> u32 {
+        self.wins[role_index(role)]
+    }
+
+    /// `None` until the player trapped the mice once.
+    pub fn average_moves_to_trap(&self) -> Option<f64> {
+        (self.traps > 0).then(|| self.trap_walls as f64 / self.traps as f64)
+    }
+}
@@ -0,0 +1,240 @@
+use crate::ai::Strategy;
+use crate::grid::{Entity, Grid};
+use crate::room::{PlayerType, TurnResult};
+use rand::{SeedableRng, rngs::StdRng};
+use std::io::Write;
+
+const Z_95: f64 = 1.96;
+
+pub struct Options {
+    pub games: u64,
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub walls: usize,
+    pub strategies: Vec<Box<dyn Strategy>>,
+    pub csv: Option<String>,
+    pub json: Option<String>,
+}
+
+pub struct MatchStats {
+    pub strategy: &'static str,
+    pub opponent: &'static str,
+    pub role: PlayerType,
+    pub games: u64,
+    pub wins: u64,
+    pub total_plies: u64,
+}
+
+impl MatchStats {
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.games as f64
+        }
+    }
+
+    pub fn avg_length(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.total_plies as f64 / self.games as f64
+        }
+    }
+
+    /// Wilson score interval, better behaved than the normal one near 0% and 100%.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        if self.games == 0 {
+            return (0.0, 0.0);
+        }
+
+        let n = self.games as f64;
+        let p = self.win_rate();
+        let z2 = Z_95 * Z_95;
+
+        let denom = 1.0 + z2 / n;
+        let center = (p + z2 / (2.0 * n)) / denom;
+        let half = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denom;
+
+        // The interval always holds the win rate, rounding could leave a clean sweep short of it
+        ((center - half).clamp(0.0, p), (center + half).clamp(p, 1.0))
+    }
+
+    pub fn role_name(&self) -> &'static str {
+        match self.role {
+            PlayerType::Mouse => "mouse",
+            PlayerType::Wall => "wall",
+        }
+    }
+}
+
+/// Plays one game with the usual turn order (trapper first) and returns the winner and the number
+/// of plies played.
+pub fn play_game(
+    mut grid: Grid,
+    mouse: &dyn Strategy,
+    wall: &dyn Strategy,
+    rng: &mut StdRng,
+) -> (PlayerType, u64) {
+    let max_plies = (grid.width() * grid.height() * 2) as u64;
+    let mut plies = 0;
+
+    while plies < max_plies {
+        let Some((y, x)) = wall.wall_move(&grid, rng) else {
+            return (PlayerType::Wall, plies);
+        };
+        if grid.place(&y, &x, Entity::Wall) == TurnResult::Bad {
+            // Illegal moves forfeit the game
+            return (PlayerType::Mouse, plies);
+        }
+        plies += 1;
+
+        let Some((y, x)) = mouse.mouse_move(&grid, rng) else {
+            return (PlayerType::Wall, plies);
+        };
+        let result = grid.move_mouse(&y, &x);
+        plies += 1;
+        match result {
+            TurnResult::Good | TurnResult::Continue => {}
+            TurnResult::Bad => return (PlayerType::Wall, plies),
+            TurnResult::GameOver => {
+                return if grid.has_escaped() {
+                    (PlayerType::Mouse, plies)
+                } else {
+                    (PlayerType::Wall, plies)
+                };
+            }
+        }
+    }
+
+    (PlayerType::Wall, plies)
+}
+
+pub fn run(options: &Options) -> Vec<MatchStats> {
+    let mut results = Vec::new();
+
+    for (a_idx, a) in options.strategies.iter().enumerate() {
+        for b in options.strategies.iter().skip(a_idx + 1) {
+            let mut a_mouse = MatchStats {
+                strategy: a.name(),
+                opponent: b.name(),
+                role: PlayerType::Mouse,
+                games: 0,
+                wins: 0,
+                total_plies: 0,
+            };
+            let mut b_mouse = MatchStats {
+                strategy: b.name(),
+                opponent: a.name(),
+                role: PlayerType::Mouse,
+                games: 0,
+                wins: 0,
+                total_plies: 0,
+            };
+
+            for game in 0..options.games {
+                let seed = options.seed.wrapping_add(game);
+
+                // Both role assignments play the exact same board and AI dice
+                for (mouse, wall, stats) in [
+                    (a.as_ref(), b.as_ref(), &mut a_mouse),
+                    (b.as_ref(), a.as_ref(), &mut b_mouse),
+                ] {
+                    let Ok(grid) = Grid::new(options.width, options.height, options.walls, seed)
+                    else {
+                        continue;
+                    };
+                    let mut rng = StdRng::seed_from_u64(!seed);
+
+                    let (winner, plies) = play_game(grid, mouse, wall, &mut rng);
+                    stats.games += 1;
+                    stats.total_plies += plies;
+                    if winner == PlayerType::Mouse {
+                        stats.wins += 1;
+                    }
+                }
+            }
+
+            for (stats, mouse, wall) in [(&a_mouse, a, b), (&b_mouse, b, a)] {
+                results.push(MatchStats {
+                    strategy: wall.name(),
+                    opponent: mouse.name(),
+                    role: PlayerType::Wall,
+                    games: stats.games,
+                    wins: stats.games - stats.wins,
+                    total_plies: stats.total_plies,
+                });
+            }
+            results.push(a_mouse);
+            results.push(b_mouse);
+        }
+    }
+
+    results
+}
+
+pub fn write_csv(file: &mut impl Write, results: &[MatchStats]) -> std::io::Result<()> {
+    writeln!(
+        file,
+        "strategy,opponent,role,games,wins,win_rate,ci_low,ci_high,avg_length"
+    )?;
+
+    for stats in results {
+        let (low, high) = stats.confidence_interval();
+        writeln!(
+            file,
+            "{},{},{},{},{},{:.4},{:.4},{:.4},{:.2}",
+            stats.strategy,
+            stats.opponent,
+            stats.role_name(),
+            stats.games,
+            stats.wins,
+            stats.win_rate(),
+            low,
+            high,
+            stats.avg_length()
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn write_json(
+    file: &mut impl Write,
+    options: &Options,
+    results: &[MatchStats],
+) -> std::io::Result<()> {
+    writeln!(file, "{{")?;
+    writeln!(file, "  \"seed\": {},", options.seed)?;
+    writeln!(file, "  \"games\": {},", options.games)?;
+    writeln!(file, "  \"width\": {},", options.width)?;
+    writeln!(file, "  \"height\": {},", options.height)?;
+    writeln!(file, "  \"walls\": {},", options.walls)?;
+    writeln!(file, "  \"results\": [")?;
+
+    for (idx, stats) in results.iter().enumerate() {
+        let (low, high) = stats.confidence_interval();
+        writeln!(
+            file,
+            "    {{\"strategy\": \"{}\", \"opponent\": \"{}\", \"role\": \"{}\", \"games\": {}, \
+             \"wins\": {}, \"win_rate\": {:.4}, \"ci_low\": {:.4}, \"ci_high\": {:.4}, \
+             \"avg_length\": {:.2}}}{}",
+            stats.strategy,
+            stats.opponent,
+            stats.role_name(),
+            stats.games,
+            stats.wins,
+            stats.win_rate(),
+            low,
+            high,
+            stats.avg_length(),
+            if idx + 1 < results.len() { "," } else { "" }
+        )?;
+    }
+
+    writeln!(file, "  ]")?;
+    writeln!(file, "}}")?;
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use server::accounts::{AccountErr, Accounts, hash_password, verify_password};
+use std::path::PathBuf;
+
+fn accounts_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{}.txt", name, std::process::id()));
+    std::fs::remove_file(&path).ok();
+    path
+}
+
+#[test]
+fn registered_players_can_log_in() {
+    let path = accounts_path("accounts-login");
+    let mut accounts = Accounts::load(&path).unwrap();
+    accounts
+        .insert("alice", hash_password("secret1").unwrap())
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let (name, hash) = accounts.find("ALICE").unwrap();
+    assert_eq!(name, "alice");
+    assert!(verify_password(hash, "secret1").is_ok());
+    assert!(accounts.find("bob").is_none());
+}
+
+#[test]
+fn wrong_passwords_are_rejected() {
+    let hash = hash_password("secret1").unwrap();
+    assert!(matches!(
+        verify_password(&hash, "secret2"),
+        Err(AccountErr::WrongCredentials)
+    ));
+    assert!(matches!(
+        hash_password("short"),
+        Err(AccountErr::InvalidPassword)
+    ));
+}
+
+#[test]
+fn names_are_taken_regardless_of_case() {
+    let path = accounts_path("accounts-taken");
+    let mut accounts = Accounts::load(&path).unwrap();
+    accounts
+        .insert("Alice", hash_password("secret1").unwrap())
+        .unwrap();
+    let duplicate = accounts.insert("alice", hash_password("secret2").unwrap());
+    let bot = accounts.insert("bot", hash_password("secret3").unwrap());
+    std::fs::remove_file(&path).ok();
+
+    assert!(matches!(duplicate, Err(AccountErr::Taken(name)) if name == "Alice"));
+    assert!(matches!(bot, Err(AccountErr::Taken(_))));
+    assert!(matches!(
+        accounts.insert("a b", String::new()),
+        Err(AccountErr::InvalidName)
+    ));
+}
+
+#[test]
+fn reloaded_accounts_still_verify() {
+    let path = accounts_path("accounts-reload");
+    let mut accounts = Accounts::load(&path).unwrap();
+    for (name, password) in [("alice", "secret1"), ("bob", "hunter22")] {
+        accounts
+            .insert(name, hash_password(password).unwrap())
+            .unwrap();
+    }
+
+    let reloaded = Accounts::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let (_, hash) = reloaded.find("bob").unwrap();
+    assert!(verify_password(hash, "hunter22").is_ok());
+    assert!(verify_password(hash, "secret1").is_err());
+    assert!(reloaded.find("alice").is_some());
+}
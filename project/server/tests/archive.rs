@@ -0,0 +1,151 @@
+use server::archive::{Archive, BOT_NAME, EndReason, GameRecord, guest_name, parse_records};
+use server::grid::Grid;
+use server::notation::{Move, format_position};
+use server::room::PlayerType;
+use server::rules::Rules;
+use uuid::Uuid;
+
+fn game(players: &[(PlayerType, &str)], reason: EndReason) -> GameRecord {
+    GameRecord {
+        id: Uuid::new_v4(),
+        started: 1_700_000_000_000,
+        seed: u64::MAX,
+        rules: Rules {
+            walls_per_turn: 2,
+            mouse_steps: 1,
+            no_wall_near_mouse: true,
+        },
+        position: format_position(&Grid::new(7, 7, 4, 3).unwrap(), PlayerType::Wall),
+        players: players
+            .iter()
+            .map(|(side, name)| (*side, name.to_string()))
+            .collect(),
+        moves: vec![
+            (1_250, Move::Wall((0, 1))),
+            (3_000, Move::Wall((2, 2))),
+            (
+                4_500,
+                Move::Mouse {
+                    from: Some((3, 3)),
+                    to: (3, 2),
+                },
+            ),
+            (
+                6_000,
+                Move::Mouse {
+                    from: None,
+                    to: (2, 1),
+                },
+            ),
+        ],
+        reason,
+    }
+}
+
+#[test]
+fn records_round_trip() {
+    for reason in [
+        EndReason::Trapped,
+        EndReason::Escaped,
+        EndReason::Forfeit(PlayerType::Mouse),
+        EndReason::Forfeit(PlayerType::Wall),
+    ] {
+        let record = game(
+            &[
+                (PlayerType::Mouse, "Alice (guest)"),
+                (PlayerType::Wall, "Bot"),
+            ],
+            reason,
+        );
+        assert_eq!(GameRecord::parse(&record.format()).unwrap(), record);
+        assert_eq!(
+            GameRecord::parse_replay(&record.format_replay()).unwrap(),
+            record
+        );
+    }
+}
+
+#[test]
+fn names_stay_on_their_line() {
+    let record = game(
+        &[(PlayerType::Mouse, "bad\nend"), (PlayerType::Wall, "bob")],
+        EndReason::Trapped,
+    );
+    let parsed = GameRecord::parse(&record.format()).unwrap();
+    assert_eq!(parsed.players[0].1, "bad end");
+}
+
+#[test]
+fn broken_records() {
+    let text = game(&[(PlayerType::Mouse, "a")], EndReason::Escaped).format();
+
+    // Unfinished game at the end of the log
+    let unfinished = text.replace("end\n", "");
+    assert!(parse_records(&unfinished).is_err());
+
+    let unknown = text.replace("seed", "sead");
+    assert!(parse_records(&unknown).is_err());
+
+    let bad_move = text.replace("move 1250 #0,1", "move 1250 #0");
+    assert!(parse_records(&bad_move).is_err());
+
+    assert!(GameRecord::parse(&(text.clone() + &text)).is_err());
+    assert!(GameRecord::parse_replay(&format!("replay 2\n{}", text)).is_err());
+    assert!(GameRecord::parse_replay(&text).is_err());
+}
+
+#[test]
+fn archive_queries() {
+    let path = std::env::temp_dir().join(format!("games-{}.txt", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    let games: Vec<GameRecord> = (0..5)
+        .map(|i| {
+            let opponent = if i % 2 == 0 { "bob" } else { "carol" };
+            game(
+                &[(PlayerType::Mouse, "alice"), (PlayerType::Wall, opponent)],
+                EndReason::Trapped,
+            )
+        })
+        .collect();
+
+    let mut archive = Archive::load(&path).unwrap();
+    for game in &games {
+        archive.record(game.clone()).unwrap();
+    }
+
+    let reloaded = Archive::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let recent: Vec<Uuid> = reloaded.recent("alice", 3).iter().map(|g| g.id).collect();
+    assert_eq!(recent, vec![games[4].id, games[3].id, games[2].id]);
+    assert_eq!(reloaded.recent("carol", 10).len(), 2);
+    assert!(reloaded.recent("dave", 10).is_empty());
+
+    assert_eq!(reloaded.find(&games[1].id), Some(&games[1]));
+    assert_eq!(reloaded.find(&Uuid::new_v4()), None);
+}
+
+#[test]
+fn guests_are_kept_apart_from_accounts() {
+    assert_eq!(guest_name("alice"), "alice (guest)");
+    assert_eq!(guest_name("alice (guest)"), "alice (guest)");
+
+    let archive_path = std::env::temp_dir().join(format!("guests-{}.txt", std::process::id()));
+    std::fs::remove_file(&archive_path).ok();
+    let mut archive = Archive::load(&archive_path).unwrap();
+    archive
+        .record(game(
+            &[
+                (PlayerType::Mouse, &guest_name("alice")),
+                (PlayerType::Wall, &guest_name(BOT_NAME)),
+            ],
+            EndReason::Escaped,
+        ))
+        .unwrap();
+    std::fs::remove_file(&archive_path).ok();
+
+    assert!(archive.recent("alice", 10).is_empty());
+    assert!(archive.recent(BOT_NAME, 10).is_empty());
+    assert_eq!(archive.recent("alice (guest)", 10).len(), 1);
+}
@@ -0,0 +1,105 @@
+use rand::RngCore;
+use server::ai::Strategy;
+use server::archive::EndReason;
+use server::grid::Grid;
+use server::notation::{Move, parse_position};
+use server::room::{PlayerType, Room, RoomSettings, Seats, TurnResult};
+use server::shape::Boards;
+use uuid::Uuid;
+
+/// Asks for a wall on the mouse, or for nothing at all.
+struct BrokenStrategy {
+    moves: bool,
+}
+
+impl Strategy for BrokenStrategy {
+    fn name(&self) -> &'static str {
+        "broken"
+    }
+
+    fn mouse_move(&self, grid: &Grid, _rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+        self.moves.then(|| grid.mice()[0])
+    }
+
+    fn wall_move(&self, grid: &Grid, _rng: &mut dyn RngCore) -> Option<(usize, usize)> {
+        self.moves.then(|| grid.mice()[0])
+    }
+}
+
+fn practice_room(player: PlayerType, moves: bool) -> Room {
+    let settings = RoomSettings {
+        seed: Some(3),
+        ..Default::default()
+    };
+    let mut room = Room::new(Seats::solo(player), true, settings, &Boards::default()).unwrap();
+    assert!(room.add_player(&Uuid::new_v4(), &player));
+    room.set_bot(player.opponent(), Box::new(BrokenStrategy { moves }));
+    room
+}
+
+#[test]
+fn broken_bots_forfeit() {
+    for moves in [true, false] {
+        for player in [PlayerType::Mouse, PlayerType::Wall] {
+            let mut room = practice_room(player, moves);
+            // Mouse players let the bot open the game
+            if player == PlayerType::Wall {
+                let uid = room.players[0].0;
+                let (grid, _) = parse_position(&room.position()).unwrap();
+                let (y, x) = grid.wall_moves()[0];
+                assert!(room.process_turn(&uid, &y, &x, None) == TurnResult::Good);
+            }
+            let history = room.history().len();
+
+            assert!(room.ai_turn() == TurnResult::GameOver);
+            assert_eq!(
+                room.end_reason(),
+                Some(EndReason::Forfeit(player.opponent()))
+            );
+            assert_eq!(room.history().len(), history);
+        }
+    }
+}
+
+#[test]
+fn mouse_hints_name_their_mouse() {
+    let settings = RoomSettings {
+        mice: 2,
+        seed: Some(5),
+        ..Default::default()
+    };
+    let mut room = Room::new(
+        Seats::solo(PlayerType::Mouse),
+        true,
+        settings,
+        &Boards::default(),
+    )
+    .unwrap();
+    let uid = Uuid::new_v4();
+    assert!(room.add_player(&uid, &PlayerType::Mouse));
+    assert!(room.ai_turn() == TurnResult::Good);
+
+    let (
+        Some(Move::Mouse {
+            from: Some(mouse),
+            to,
+        }),
+        _,
+    ) = room.hint(&uid).unwrap()
+    else {
+        panic!("expected a mouse hint");
+    };
+    let (grid, _) = parse_position(&room.position()).unwrap();
+    assert!(grid.mice().contains(&mouse));
+    assert!(grid.mouse_moves_from(mouse).contains(&to));
+
+    // The same position always gets the same hint
+    let (again, _) = room.hint(&uid).unwrap();
+    assert_eq!(
+        again,
+        Some(Move::Mouse {
+            from: Some(mouse),
+            to
+        })
+    );
+}
@@ -0,0 +1,254 @@
+use std::path::Path;
+use std::time::Duration;
+
+use server::config::{
+    BIND_ENV, CONFIG_ENV, ConfigErr, DEFAULT_BIND, ServerConfig, Value, parse_config,
+};
+use server::room::{PlayerType, RoomSettings};
+use server::shape::BoardShape;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn temp_config(name: &str, text: &str) -> String {
+    let path = std::env::temp_dir().join(format!("{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn parses_the_toml_subset() {
+    let text = r#"
+# comment
+bind = "127.0.0.1:2000" # trailing comment
+[rooms]
+max = 1_000
+public = true
+names = ["a#b", "c"]
+"#;
+
+    assert_eq!(
+        parse_config(text, "test").unwrap(),
+        vec![
+            ("bind".to_string(), Value::Str("127.0.0.1:2000".to_string())),
+            ("rooms.max".to_string(), Value::Int(1000)),
+            ("rooms.public".to_string(), Value::Bool(true)),
+            (
+                "rooms.names".to_string(),
+                Value::List(vec!["a#b".to_string(), "c".to_string()])
+            ),
+        ]
+    );
+}
+
+#[test]
+fn syntax_errors_name_the_line() {
+    let err = parse_config("bind = \"x\"\n\nport 12", "server.toml").unwrap_err();
+    assert_eq!(err.to_string(), "server.toml:3: expected key = value");
+
+    assert!(matches!(
+        parse_config("[rooms", "server.toml"),
+        Err(ConfigErr::Syntax(_, 1, _))
+    ));
+    assert!(parse_config("bind = 127.0.0.1", "server.toml").is_err());
+}
+
+#[test]
+fn unknown_and_mistyped_settings_are_refused() {
+    let path = Path::new("server.toml");
+    assert!(matches!(
+        ServerConfig::from_file("bnd = \"x\"", path),
+        Err(ConfigErr::UnknownKey(_))
+    ));
+    assert!(matches!(
+        ServerConfig::from_file("bind = 1922", path),
+        Err(ConfigErr::InvalidValue(_, _))
+    ));
+}
+
+#[test]
+fn flags_override_the_environment_and_the_file() {
+    let path = temp_config("config-order", "bind = \"127.0.0.1:3000\"\n");
+    let env = |name: &str| match name {
+        CONFIG_ENV => Some(path.clone()),
+        BIND_ENV => Some("127.0.0.1:3001".to_string()),
+        _ => None,
+    };
+
+    let from_env = ServerConfig::load(&[], env).unwrap();
+    assert_eq!(from_env.bind, "127.0.0.1:3001");
+
+    let from_flag = ServerConfig::load(&args(&["--bind", "127.0.0.1:3002"]), env).unwrap();
+    assert_eq!(from_flag.bind, "127.0.0.1:3002");
+
+    let from_file = ServerConfig::load(&args(&["--config", &path]), |_| None).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(from_file.bind, "127.0.0.1:3000");
+}
+
+#[test]
+fn bad_flags_and_addresses_are_reported() {
+    let no_env = |_: &str| None;
+    assert!(matches!(
+        ServerConfig::load(&args(&["--port", "1"]), no_env),
+        Err(ConfigErr::UnknownFlag(_))
+    ));
+    assert!(matches!(
+        ServerConfig::load(&args(&["--bind"]), no_env),
+        Err(ConfigErr::MissingValue(_))
+    ));
+    assert!(matches!(
+        ServerConfig::load(&args(&["--bind", "no port"]), no_env),
+        Err(ConfigErr::InvalidBind(_))
+    ));
+    assert!(matches!(
+        ServerConfig::load(&args(&["--config", "/nonexistent/server.toml"]), no_env),
+        Err(ConfigErr::IO(_, _))
+    ));
+    assert_eq!(ServerConfig::default().bind, DEFAULT_BIND);
+}
+
+#[test]
+fn reads_the_gameplay_and_limit_sections() {
+    let text = r#"
+[board]
+width = 9
+height = 9
+walls = 8
+shape = "hexagon"
+walls_per_turn = 2
+
+[rooms]
+idle_public = 4
+
+[limits]
+max_rooms = 50
+max_users = 0
+
+[timeouts]
+rejoin = 30
+snapshot = 10
+
+[ai]
+mouse = "random"
+wall = "none"
+hints = false
+"#;
+    let config = ServerConfig::from_file(text, Path::new("server.toml")).unwrap();
+    config.validate().unwrap();
+
+    assert_eq!(config.board.width, 9);
+    assert_eq!(config.board.walls, 8);
+    assert_eq!(config.board.shape, BoardShape::Hexagon);
+    assert_eq!(config.board.rules.walls_per_turn, 2);
+    assert_eq!(config.idle_rooms, 4);
+    assert_eq!(config.max_rooms, Some(50));
+    assert_eq!(config.max_users, None);
+    assert_eq!(config.rejoin_timeout, Duration::from_secs(30));
+    assert_eq!(config.snapshot_interval, Duration::from_secs(10));
+    assert_eq!(config.bot(PlayerType::Mouse), Some("random"));
+    assert_eq!(config.bot(PlayerType::Wall), None);
+    assert!(!config.hints);
+
+    let defaults = ServerConfig::default();
+    assert_eq!(defaults.board, RoomSettings::default());
+    assert_eq!(defaults.idle_rooms, 2);
+    assert_eq!(defaults.bot(PlayerType::Mouse), Some("greedy"));
+    assert!(defaults.discovery);
+}
+
+#[test]
+fn boards_are_found_next_to_the_config() {
+    let path = Path::new("/etc/trap/server.toml");
+    let default = ServerConfig::from_file("", path).unwrap();
+    assert_eq!(default.boards_dir, Path::new("/etc/trap/boards"));
+
+    let relative = ServerConfig::from_file("[rooms]\nboards = \"maps\"", path).unwrap();
+    assert_eq!(relative.boards_dir, Path::new("/etc/trap/maps"));
+
+    let absolute = ServerConfig::from_file("[rooms]\nboards = \"/srv/maps\"", path).unwrap();
+    assert_eq!(absolute.boards_dir, Path::new("/srv/maps"));
+
+    let local = ServerConfig::from_file("", Path::new("server.toml")).unwrap();
+    assert_eq!(local.boards_dir, Path::new("boards"));
+}
+
+#[test]
+fn custom_boards_are_loaded_with_the_config() {
+    let dir = std::env::temp_dir().join(format!("config-boards-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("maps")).unwrap();
+    let ring = ".....\n.....\n..-..\n.....\n.....\n";
+    std::fs::write(dir.join("maps/ring.txt"), ring).unwrap();
+    std::fs::write(dir.join("maps/notes.md"), "not a board").unwrap();
+    let config_path = dir.join("server.toml");
+    let load = |shape: &str| {
+        let text = format!(
+            "[rooms]\nboards = \"maps\"\n[board]\nwalls = 2\nshape = \"{}\"",
+            shape
+        );
+        std::fs::write(&config_path, text).unwrap();
+        ServerConfig::load(
+            &args(&["--config", &config_path.display().to_string()]),
+            |_| None,
+        )
+    };
+
+    let config = load("ring").unwrap();
+    assert_eq!(config.boards.names(), vec!["ring".to_string()]);
+    assert!(matches!(load("donut"), Err(ConfigErr::Board(_))));
+
+    std::fs::write(dir.join("maps/broken.txt"), "..?..").unwrap();
+    let broken = load("ring");
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(matches!(broken, Err(ConfigErr::Boards(_, _))));
+}
+
+#[test]
+fn discovery_can_be_named_and_moved() {
+    let text =
+        "bind = \"127.0.0.1:2000\"\n[discovery]\nenabled = true\nname = \"Office\"\nport = 2001\n";
+    let config = ServerConfig::from_file(text, Path::new("server.toml")).unwrap();
+    assert!(config.discovery);
+    assert_eq!(config.name, "Office");
+    assert_eq!(config.discovery_port, 2001);
+    assert_eq!(config.port(), Some(2000));
+
+    let path = Path::new("server.toml");
+    assert!(matches!(
+        ServerConfig::from_file("[discovery]\nport = 70000", path),
+        Err(ConfigErr::InvalidValue(_, _))
+    ));
+    let unnamed = ServerConfig::from_file("[discovery]\nname = \"\"", path).unwrap();
+    assert!(matches!(unnamed.validate(), Err(ConfigErr::InvalidName)));
+}
+
+#[test]
+fn invalid_gameplay_settings_are_reported() {
+    let check = |text: &str| {
+        ServerConfig::from_file(text, Path::new("server.toml")).and_then(|config| config.validate())
+    };
+
+    assert!(matches!(
+        check("[board]\nwidth = 2"),
+        Err(ConfigErr::Board(_))
+    ));
+    assert!(matches!(
+        check("[board]\nwalls = 100"),
+        Err(ConfigErr::Board(_))
+    ));
+    assert!(matches!(
+        check("[limits]\nmax_rooms = -1"),
+        Err(ConfigErr::InvalidValue(_, _))
+    ));
+    assert!(matches!(
+        check("[timeouts]\nrejoin = 0"),
+        Err(ConfigErr::InvalidValue(_, _))
+    ));
+
+    let err = check("[ai]\nmouse = \"smart\"").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unknown AI 'smart', expected one of: random, greedy"
+    );
+}
@@ -0,0 +1,70 @@
+use server::daily::{DailyLeaderboard, daily_seed, today};
+
+fn daily_path(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{}.txt", name, std::process::id()));
+    std::fs::remove_file(&path).ok();
+    path
+}
+
+#[test]
+fn days_get_their_own_seeds() {
+    // Pinned, every server has to hand out the same board on the same day
+    assert_eq!(daily_seed(0), 0xE220_A839_7B1D_CDAF);
+    assert_eq!(daily_seed(20_000), daily_seed(20_000));
+
+    let seeds: Vec<u64> = (20_000..20_100).map(daily_seed).collect();
+    for (i, seed) in seeds.iter().enumerate() {
+        assert!(!seeds[i + 1..].contains(seed));
+    }
+}
+
+#[test]
+fn fewest_walls_rank_first_and_ties_keep_their_order() {
+    let path = daily_path("daily-ranking");
+    let mut board = DailyLeaderboard::load(&path).unwrap();
+    let day = board.day();
+
+    board.record(day, "alice", 12).unwrap();
+    board.record(day, "bob", 9).unwrap();
+    board.record(day, "carol", 12).unwrap();
+    board.record(day, "dave", 15).unwrap();
+    // A worse result keeps the best one, results of other days are dropped
+    board.record(day, "bob", 20).unwrap();
+    board.record(day - 1, "erin", 1).unwrap();
+    // Improving onto a tie ranks behind whoever got there first
+    board.record(day, "dave", 12).unwrap();
+
+    let expected = [("bob", 9), ("alice", 12), ("carol", 12), ("dave", 12)];
+    let top: Vec<(&str, u32)> = board
+        .top(10)
+        .iter()
+        .map(|(n, w)| (n.as_str(), *w))
+        .collect();
+    assert_eq!(top, expected);
+    assert_eq!(board.top(2).len(), 2);
+
+    let mut reloaded = DailyLeaderboard::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    // Unless the day rolled over in between
+    if reloaded.day() == day {
+        let top: Vec<(&str, u32)> = reloaded
+            .top(10)
+            .iter()
+            .map(|(n, w)| (n.as_str(), *w))
+            .collect();
+        assert_eq!(top, expected);
+    }
+}
+
+#[test]
+fn earlier_days_are_not_reloaded() {
+    let path = daily_path("daily-old");
+    std::fs::write(&path, format!("{}\talice\t3\n", today() - 1)).unwrap();
+    let broken = DailyLeaderboard::load(&path);
+    std::fs::write(&path, format!("{}\t3\talice\n", today() - 1)).unwrap();
+    let mut old = DailyLeaderboard::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert!(broken.is_err());
+    assert!(old.top(10).is_empty());
+}
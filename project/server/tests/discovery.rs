@@ -0,0 +1,69 @@
+use server::discovery::{Announcement, MAGIC, PROBE_SIZE, is_local, is_probe, probe};
+use std::net::IpAddr;
+
+fn announcement() -> Announcement {
+    Announcement {
+        port: 1922,
+        name: "Office".to_string(),
+        version: "0.1.0".to_string(),
+        online: 5,
+        playing: 2,
+    }
+}
+
+#[test]
+fn announcements_survive_the_wire() {
+    let data = announcement().to_bytes();
+    assert!(data.starts_with(MAGIC));
+    assert_eq!(Announcement::from_bytes(&data), Some(announcement()));
+}
+
+#[test]
+fn only_probes_are_answered() {
+    assert!(is_probe(&probe()));
+    assert!(!is_probe(&announcement().to_bytes()));
+    assert!(!is_probe(b"TTMX\0"));
+    assert!(!is_probe(&[]));
+}
+
+#[test]
+fn probes_are_as_big_as_the_answer() {
+    let answer = Announcement {
+        name: "n".repeat(64),
+        version: "v".repeat(64),
+        ..announcement()
+    };
+    assert!(answer.to_bytes().len() <= PROBE_SIZE);
+    assert_eq!(probe().len(), PROBE_SIZE);
+    assert!(!is_probe(&probe()[..PROBE_SIZE - 1]));
+    assert!(!is_probe(b"TTMD\0"));
+}
+
+#[test]
+fn only_local_addresses_are_answered() {
+    let local = [
+        "127.0.0.1",
+        "10.1.2.3",
+        "192.168.0.7",
+        "169.254.1.1",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+    ];
+    for ip in local {
+        assert!(is_local(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+    }
+    for ip in ["8.8.8.8", "2001:db8::1", "::ffff:1.1.1.1"] {
+        assert!(!is_local(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+    }
+    assert!(is_local("::ffff:192.168.1.1".parse::<IpAddr>().unwrap()));
+}
+
+#[test]
+fn broken_announcements_are_ignored() {
+    let data = announcement().to_bytes();
+    for len in 0..data.len() {
+        assert_eq!(Announcement::from_bytes(&data[..len]), None);
+    }
+    assert_eq!(Announcement::from_bytes(&probe()), None);
+}
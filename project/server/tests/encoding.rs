@@ -0,0 +1,166 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use server::encoding::{EncodingErr, Reader, write_varint};
+use server::grid::{GRID_ENCODING_VERSION, Grid};
+use server::shape::hexagon;
+
+fn round_trip(grid: &Grid) {
+    let bytes = grid.as_bytes();
+    let decoded = Grid::from_bytes(&bytes).expect("encoded grid decodes");
+
+    assert_eq!(decoded.width(), grid.width());
+    assert_eq!(decoded.height(), grid.height());
+    assert_eq!(decoded.mice(), grid.mice());
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            assert!(decoded.tile(y, x) == grid.tile(y, x), "tile ({y}, {x})");
+        }
+    }
+    assert_eq!(decoded.as_bytes(), bytes);
+}
+
+#[test]
+fn varints_round_trip() {
+    for value in [
+        0,
+        1,
+        127,
+        128,
+        255,
+        300,
+        16_383,
+        16_384,
+        u32::MAX as u64,
+        u64::MAX,
+    ] {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, value);
+
+        let mut reader = Reader::new(&bytes);
+        assert_eq!(reader.varint(), Ok(value));
+        assert!(reader.is_empty());
+    }
+}
+
+#[test]
+fn overlong_varint() {
+    let bytes = [0xFF; 11];
+    assert_eq!(Reader::new(&bytes).varint(), Err(EncodingErr::Varint));
+}
+
+#[test]
+fn rectangular_boards_round_trip() {
+    for seed in 0..20 {
+        round_trip(&Grid::new(11, 9, 15, seed).unwrap());
+    }
+}
+
+#[test]
+fn shaped_boards_round_trip() {
+    for seed in 0..10 {
+        round_trip(&Grid::with_mask(&hexagon(6), 10, 2, seed).unwrap());
+    }
+}
+
+#[test]
+fn mice_keep_their_order() {
+    let grid = Grid::with_mask(&vec![vec![true; 15]; 15], 20, 4, 7).unwrap();
+    let decoded = Grid::from_bytes(&grid.as_bytes()).unwrap();
+    assert_eq!(decoded.mice(), grid.mice());
+}
+
+#[test]
+fn boards_beyond_255() {
+    let grid = Grid::new(300, 280, 5_000, 3).unwrap();
+    round_trip(&grid);
+
+    // Two bitset bits per tile at most, plus a few header bytes
+    assert!(grid.as_bytes().len() < 300 * 280 / 4 + 32);
+}
+
+#[test]
+fn invalid_grids() {
+    let bytes = Grid::new(7, 7, 5, 1).unwrap().as_bytes();
+
+    let mut version = bytes.clone();
+    version[0] = GRID_ENCODING_VERSION + 1;
+    assert_eq!(
+        Grid::from_bytes(&version).err(),
+        Some(EncodingErr::Version(GRID_ENCODING_VERSION + 1))
+    );
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+        Grid::from_bytes(&trailing).err(),
+        Some(EncodingErr::Trailing)
+    );
+
+    for len in 0..bytes.len() {
+        assert_eq!(
+            Grid::from_bytes(&bytes[..len]).err(),
+            Some(EncodingErr::Truncated)
+        );
+    }
+
+    let empty = [GRID_ENCODING_VERSION, 0, 5, 0];
+    assert_eq!(
+        Grid::from_bytes(&empty).err(),
+        Some(EncodingErr::Size(0, 5))
+    );
+
+    // 2000x1 board, refused before its bitset is read
+    let wide = [GRID_ENCODING_VERSION, 0xD0, 0x0F, 1, 0];
+    assert_eq!(
+        Grid::from_bytes(&wide).err(),
+        Some(EncodingErr::Size(2000, 1))
+    );
+
+    // 2x1 board, no walls, mouse count 0
+    let no_mouse = [GRID_ENCODING_VERSION, 2, 1, 0, 0, 0];
+    assert_eq!(
+        Grid::from_bytes(&no_mouse).err(),
+        Some(EncodingErr::NoMouse)
+    );
+
+    // Mouse placed on a wall
+    let on_wall = [GRID_ENCODING_VERSION, 2, 1, 0, 1, 1, 0, 0];
+    assert_eq!(
+        Grid::from_bytes(&on_wall).err(),
+        Some(EncodingErr::Tile(0, 0))
+    );
+}
+
+#[test]
+fn fuzz_random_bytes() {
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+    for _ in 0..20_000 {
+        let len = rng.random_range(0..64);
+        let mut bytes: Vec<u8> = (0..len).map(|_| rng.random()).collect();
+        if rng.random_bool(0.5) && !bytes.is_empty() {
+            bytes[0] = GRID_ENCODING_VERSION;
+        }
+
+        if let Ok(grid) = Grid::from_bytes(&bytes) {
+            assert_eq!(grid.as_bytes(), bytes);
+        }
+    }
+}
+
+#[test]
+fn fuzz_corrupted_grids() {
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+
+    for seed in 0..200 {
+        let mut bytes = Grid::with_mask(&hexagon(4), 6, 2, seed).unwrap().as_bytes();
+        for _ in 0..rng.random_range(1..4) {
+            let i = rng.random_range(0..bytes.len());
+            bytes[i] ^= 1 << rng.random_range(0..8);
+        }
+
+        if let Ok(grid) = Grid::from_bytes(&bytes) {
+            round_trip(&grid);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use server::grid::Grid;
+use server::notation::{
+    Move, NotationErr, format_move, format_position, parse_move, parse_position,
+};
+use server::room::PlayerType;
+use server::shape::hexagon;
+
+fn round_trip(grid: &Grid, to_move: PlayerType) {
+    let text = format_position(grid, to_move);
+    let (parsed, side) = parse_position(&text).expect("formatted position parses");
+
+    assert_eq!(side, to_move);
+    assert_eq!(parsed.width(), grid.width());
+    assert_eq!(parsed.height(), grid.height());
+    // The notation lists mice row by row, not in the order they were placed
+    let mut mice = grid.mice().to_vec();
+    mice.sort();
+    assert_eq!(parsed.mice(), mice);
+    assert_eq!(format_position(&parsed, side), text);
+}
+
+#[test]
+fn rectangular_boards_round_trip() {
+    for seed in 0..20 {
+        let grid = Grid::new(11, 9, 12, seed).unwrap();
+        round_trip(&grid, PlayerType::Wall);
+        round_trip(&grid, PlayerType::Mouse);
+    }
+}
+
+#[test]
+fn shaped_boards_round_trip() {
+    for seed in 0..10 {
+        let grid = Grid::with_mask(&hexagon(5), 8, 1, seed).unwrap();
+        round_trip(&grid, PlayerType::Wall);
+    }
+}
+
+#[test]
+fn several_mice_round_trip() {
+    for seed in 0..10 {
+        let grid = Grid::with_mask(&vec![vec![true; 13]; 13], 10, 3, seed).unwrap();
+        assert_eq!(grid.mice().len(), 3);
+        round_trip(&grid, PlayerType::Mouse);
+    }
+}
+
+#[test]
+fn known_position() {
+    let (grid, side) = parse_position("5x3 5/1#M2/-4 w").unwrap();
+
+    assert_eq!(side, PlayerType::Wall);
+    assert_eq!(grid.mice(), &[(1, 2)]);
+    assert!(!grid.is_free(1, 1));
+    assert!(!grid.is_free(2, 0));
+    assert_eq!(format_position(&grid, side), "5x3 5/1#M2/-4 w");
+}
+
+#[test]
+fn invalid_positions() {
+    assert_eq!(parse_position("5x3 5/5").err(), Some(NotationErr::Layout));
+    assert_eq!(
+        parse_position("5y3 5/1M3/5 w").err(),
+        Some(NotationErr::Size("5y3".to_string()))
+    );
+    assert_eq!(
+        parse_position("5x3 5/1M3 w").err(),
+        Some(NotationErr::RowCount(3))
+    );
+    assert_eq!(
+        parse_position("5x3 5/1M4/5 w").err(),
+        Some(NotationErr::RowWidth(1))
+    );
+    assert_eq!(
+        parse_position("5x3 5/1M?2/5 w").err(),
+        Some(NotationErr::UnknownTile('?'))
+    );
+    assert_eq!(
+        parse_position("5x3 5/5/5 w").err(),
+        Some(NotationErr::NoMouse)
+    );
+    assert_eq!(
+        parse_position("5x3 5/1M3/5 x").err(),
+        Some(NotationErr::Side("x".to_string()))
+    );
+}
+
+#[test]
+fn oversized_runs_are_refused() {
+    let grid = Grid::new(7, 5, 4, 3).unwrap();
+    let text = format_position(&grid, PlayerType::Wall);
+    let (_, rows) = text.split_once(' ').unwrap();
+    let (_, rest) = rows.split_once('/').unwrap();
+
+    // Runs far past the row width, with and without a tile after them, and past usize
+    for run in ["4000000000000", "4000000000000#", &"9".repeat(40)] {
+        let oversized = format!("7x5 {}/{}", run, rest);
+        assert_eq!(
+            parse_position(&oversized).err(),
+            Some(NotationErr::RowWidth(0))
+        );
+    }
+    assert!(parse_position(&text).is_ok());
+}
+
+#[test]
+fn moves_round_trip() {
+    let moves = [
+        Move::Wall((3, 7)),
+        Move::Mouse {
+            from: Some((4, 4)),
+            to: (3, 4),
+        },
+        Move::Mouse {
+            from: None,
+            to: (0, 12),
+        },
+    ];
+
+    for mv in moves {
+        assert_eq!(parse_move(&format_move(&mv)), Ok(mv));
+    }
+
+    assert_eq!(format_move(&moves[0]), "#3,7");
+    assert_eq!(format_move(&moves[1]), "4,4>3,4");
+    assert_eq!(format_move(&moves[2]), ">0,12");
+}
+
+#[test]
+fn invalid_moves() {
+    for text in ["", "#3", "#a,1", "3,4", "3,4>", ">1;2", "1,2>3,x"] {
+        assert_eq!(parse_move(text), Err(NotationErr::Move(text.to_string())));
+    }
+}
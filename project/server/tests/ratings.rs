@@ -0,0 +1,116 @@
+use server::ratings::{DEFAULT_DEVIATION, MIN_DEVIATION, Rating, Ratings};
+use server::room::PlayerType;
+
+fn rating(rating: f64, deviation: f64) -> Rating {
+    Rating { rating, deviation }
+}
+
+#[test]
+fn even_game_moves_both_players_equally() {
+    let a = Rating::default();
+    let b = Rating::default();
+
+    let winner = a.update(&b, 1.0);
+    let loser = b.update(&a, 0.0);
+
+    assert!(winner.rating > a.rating);
+    assert!(loser.rating < b.rating);
+    assert!((winner.rating - a.rating - (b.rating - loser.rating)).abs() < 1e-9);
+    assert!(winner.deviation < DEFAULT_DEVIATION);
+}
+
+#[test]
+fn upsets_count_more() {
+    let strong = rating(1800.0, 80.0);
+    let weak = rating(1400.0, 80.0);
+
+    let expected_win = strong.update(&weak, 1.0).rating - strong.rating;
+    let upset = weak.update(&strong, 1.0).rating - weak.rating;
+
+    assert!(strong.expected(&weak) > 0.5);
+    assert!(upset > expected_win);
+}
+
+#[test]
+fn deviation_has_a_floor() {
+    let mut player = Rating::default();
+    let opponent = rating(1500.0, 60.0);
+
+    for game in 0..500 {
+        player = player.update(&opponent, (game % 2) as f64);
+        assert!(player.deviation >= MIN_DEVIATION);
+    }
+    assert_eq!(player.deviation, MIN_DEVIATION);
+}
+
+#[test]
+fn team_rating_is_the_average() {
+    let team = Rating::team(&[rating(1400.0, 100.0), rating(1600.0, 100.0)]);
+    assert_eq!(team, rating(1500.0, 100.0));
+    assert_eq!(Rating::team(&[]), Rating::default());
+}
+
+#[test]
+fn ratings_survive_a_reload() {
+    let path = std::env::temp_dir().join(format!("ratings-{}.txt", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    let mut ratings = Ratings::load(&path).unwrap();
+    assert_eq!(ratings.get("alice", PlayerType::Mouse), Rating::default());
+
+    ratings
+        .record("alice", PlayerType::Mouse, rating(1662.31, 290.32))
+        .unwrap();
+    ratings
+        .record("alice", PlayerType::Mouse, rating(1580.0, 250.0))
+        .unwrap();
+    ratings
+        .record("bob", PlayerType::Wall, rating(1337.69, 290.32))
+        .unwrap();
+
+    let reloaded = Ratings::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(
+        reloaded.get("alice", PlayerType::Mouse),
+        rating(1580.0, 250.0)
+    );
+    assert_eq!(reloaded.get("alice", PlayerType::Wall), Rating::default());
+    assert_eq!(
+        reloaded.get("bob", PlayerType::Wall),
+        rating(1337.69, 290.32)
+    );
+    assert_eq!(reloaded.history("alice").count(), 2);
+}
+
+#[test]
+fn top_players_are_sorted_per_role() {
+    let path = std::env::temp_dir().join(format!("ratings-top-{}.txt", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    let mut ratings = Ratings::load(&path).unwrap();
+    ratings
+        .record("alice", PlayerType::Mouse, rating(1600.0, 300.0))
+        .unwrap();
+    ratings
+        .record("alice", PlayerType::Mouse, rating(1550.0, 280.0))
+        .unwrap();
+    ratings
+        .record("bob", PlayerType::Mouse, rating(1700.0, 300.0))
+        .unwrap();
+    ratings
+        .record("carol", PlayerType::Wall, rating(1400.0, 300.0))
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let top = ratings.top(PlayerType::Mouse, 10);
+    assert_eq!(
+        top.iter()
+            .map(|(name, _, games)| (*name, *games))
+            .collect::<Vec<_>>(),
+        [("bob", 1), ("alice", 2)]
+    );
+    assert_eq!(ratings.top(PlayerType::Mouse, 1).len(), 1);
+    assert_eq!(ratings.top(PlayerType::Wall, 10)[0].0, "carol");
+    assert_eq!(ratings.rated("carol", PlayerType::Mouse), None);
+}
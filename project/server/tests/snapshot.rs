@@ -0,0 +1,144 @@
+use server::grid::Grid;
+use server::room::{PlayerType, Room, RoomSettings, Seats, SettingsErr, TurnResult};
+use server::shape::{BoardShape, Boards, ShapeErr};
+use server::snapshot::{RoomSnapshot, SeatSnapshot, SnapshotErr, load, parse_snapshot, save};
+use uuid::Uuid;
+
+fn free_tile(room: &Room) -> (usize, usize) {
+    let map = room.distance_map().unwrap();
+    map.iter()
+        .enumerate()
+        .flat_map(|(y, line)| line.iter().enumerate().map(move |(x, d)| (y, x, *d)))
+        .find(|(_, _, d)| d.is_some_and(|d| d > 0))
+        .map(|(y, x, _)| (y, x))
+        .unwrap()
+}
+
+/// A practice game where the bot plays the mice, after a few turns.
+fn practice_game(uid: &Uuid) -> Room {
+    let settings = RoomSettings {
+        seed: Some(42),
+        ..Default::default()
+    };
+    let mut room = Room::new(
+        Seats::solo(PlayerType::Wall),
+        true,
+        settings,
+        &Boards::default(),
+    )
+    .unwrap();
+    assert!(room.add_player(uid, &PlayerType::Wall));
+
+    for _ in 0..3 {
+        let (y, x) = free_tile(&room);
+        assert!(room.process_turn(uid, &y, &x, None) == TurnResult::Good);
+        room.ai_turn();
+    }
+    room
+}
+
+fn seat(session: Uuid) -> SeatSnapshot {
+    SeatSnapshot {
+        session,
+        side: PlayerType::Wall,
+        account: Some("alice".to_string()),
+        name: "alice".to_string(),
+    }
+}
+
+#[test]
+fn snapshot_text_round_trip() {
+    let uid = Uuid::new_v4();
+    let mut snapshot = practice_game(&uid).snapshot(Uuid::new_v4(), vec![seat(uid)]);
+    snapshot.daily = Some(20_000);
+    snapshot.settings.shape = BoardShape::Mask("two words".to_string());
+    snapshot.players.push(SeatSnapshot {
+        session: Uuid::new_v4(),
+        side: PlayerType::Mouse,
+        account: None,
+        name: "Guest with spaces".to_string(),
+    });
+
+    let parsed = parse_snapshot(&(snapshot.format() + &snapshot.format())).unwrap();
+    assert_eq!(parsed, vec![snapshot.clone(), snapshot]);
+}
+
+#[test]
+fn restored_room_plays_on_like_the_original() {
+    let uid = Uuid::new_v4();
+    let mut room = practice_game(&uid);
+    let session = Uuid::new_v4();
+
+    let snapshot = room.snapshot(Uuid::new_v4(), vec![seat(session)]);
+    let mut restored = Room::restore(&snapshot, &Boards::default()).unwrap();
+
+    assert_eq!(restored.position(), room.position());
+    assert_eq!(restored.board_hash(), room.board_hash());
+    assert_eq!(restored.history(), room.history());
+    assert_eq!(restored.current_player(), Some(session));
+
+    assert!(restored.replace_player(&session, &uid));
+    assert!(!restored.replace_player(&session, &uid));
+
+    // The bot continues with the same random stream
+    for _ in 0..3 {
+        let (y, x) = free_tile(&room);
+        let expected = room.process_turn(&uid, &y, &x, None);
+        assert!(restored.process_turn(&uid, &y, &x, None) == expected);
+        if expected == TurnResult::Good {
+            room.ai_turn();
+            restored.ai_turn();
+        }
+    }
+    assert_eq!(restored.history(), room.history());
+}
+
+#[test]
+fn illegal_moves_are_refused() {
+    let uid = Uuid::new_v4();
+    let mut snapshot: RoomSnapshot = practice_game(&uid).snapshot(Uuid::new_v4(), vec![seat(uid)]);
+    // The first wall again, on a tile that is already taken
+    let (_, first) = snapshot.moves[0];
+    snapshot.moves.push((0, first));
+
+    assert!(matches!(
+        Room::restore(&snapshot, &Boards::default()),
+        Err(SnapshotErr::IllegalMove(_))
+    ));
+}
+
+#[test]
+fn over_walled_boards_are_refused() {
+    let uid = Uuid::new_v4();
+    let mut snapshot = practice_game(&uid).snapshot(Uuid::new_v4(), vec![seat(uid)]);
+    snapshot.settings.width = 5;
+    snapshot.settings.height = 5;
+    snapshot.settings.walls = 30;
+    snapshot.moves.clear();
+
+    assert!(matches!(
+        Room::restore(&snapshot, &Boards::default()),
+        Err(SnapshotErr::Settings(SettingsErr::TooManyWalls(_)))
+    ));
+    // The generator gives up instead of looking for free tiles forever
+    assert!(matches!(
+        Grid::new(3, 3, 9, 1),
+        Err(ShapeErr::NoWallRoom(9))
+    ));
+}
+
+#[test]
+fn snapshot_file_is_replaced() {
+    let path = std::env::temp_dir().join(format!("snapshot-{}.txt", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    assert!(load(&path).unwrap().is_empty());
+
+    let uid = Uuid::new_v4();
+    let snapshot = practice_game(&uid).snapshot(Uuid::new_v4(), vec![seat(uid)]);
+    save(&path, &[snapshot.clone(), snapshot.clone()]).unwrap();
+    save(&path, std::slice::from_ref(&snapshot)).unwrap();
+
+    let loaded = load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(loaded, vec![snapshot]);
+}
@@ -0,0 +1,108 @@
+use server::grid::Entity;
+use server::room::PlayerType;
+use server::solver::{Outcome, Solver, describe, parse_position};
+
+fn solve(text: &str) -> (Outcome, Option<(usize, usize)>) {
+    let (grid, to_move) = parse_position(text).expect("position parses");
+    let solution = Solver::default()
+        .solve(&grid, to_move)
+        .expect("small positions solve within the budget");
+    (solution.outcome, solution.best_move)
+}
+
+#[test]
+fn trapped_mouse_is_a_win_in_zero() {
+    let position = "wall
+        .....
+        .###.
+        .#M#.
+        .###.
+        .....";
+
+    let (outcome, best_move) = solve(position);
+    assert_eq!(outcome, Outcome::Win(0));
+    assert_eq!(best_move, None);
+    assert_eq!(
+        describe(&outcome, PlayerType::Wall),
+        "Trapper to move wins in 0 plies"
+    );
+}
+
+#[test]
+fn mouse_next_to_the_edge_wins_in_one() {
+    let position = "mouse
+        .....
+        ..M..
+        .....";
+
+    let (outcome, best_move) = solve(position);
+    assert_eq!(outcome, Outcome::Win(1));
+    assert_eq!(best_move.map(|(y, _)| y), Some(0));
+}
+
+#[test]
+fn forced_trap_is_found_at_its_depth() {
+    // The mouse shares a pocket of three tiles, closing it takes two walls
+    let position = "wall
+        .......
+        .#####.
+        .#..##.
+        .#M###.
+        .#####.
+        .......
+        .......";
+
+    let (outcome, best_move) = solve(position);
+    assert_eq!(outcome, Outcome::Win(3));
+    assert!(matches!(best_move, Some((2, 2)) | Some((2, 3))));
+}
+
+#[test]
+fn escaped_mouse_is_refused() {
+    assert!(parse_position("mouse\nM..\n...\n...").is_err());
+    assert!(parse_position("wall\n.-.\n.M.\n...").is_err());
+}
+
+#[test]
+fn transposed_positions_keep_their_distance() {
+    let position = "wall
+        .......
+        .#...#.
+        .#...#.
+        .#.M.#.
+        .#####.
+        .......";
+    let (start, to_move) = parse_position(position).unwrap();
+
+    // Both walls go down around the same mouse step, in either order
+    let play = |first: (usize, usize), second: (usize, usize)| {
+        let mut grid = start.clone();
+        grid.place(&first.0, &first.1, Entity::Wall);
+        grid.move_mouse(&2, &3);
+        grid.place(&second.0, &second.1, Entity::Wall);
+        grid
+    };
+    let left_first = play((3, 2), (3, 4));
+    let right_first = play((3, 4), (3, 2));
+    assert_eq!(left_first.as_bytes(), right_first.as_bytes());
+
+    let fresh = Solver::default()
+        .solve(&right_first, PlayerType::Mouse)
+        .unwrap()
+        .outcome;
+
+    // The table already holds the position from the first order, at other depths
+    let mut solver = Solver::default();
+    assert_eq!(
+        solver.solve(&start, to_move).unwrap().outcome,
+        Outcome::Win(7)
+    );
+    solver.solve(&left_first, PlayerType::Mouse).unwrap();
+    let transposed = solver
+        .solve(&right_first, PlayerType::Mouse)
+        .unwrap()
+        .outcome;
+
+    assert_eq!(transposed, fresh);
+    assert_eq!(fresh, Outcome::Win(5));
+}
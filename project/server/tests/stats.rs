@@ -0,0 +1,83 @@
+use server::archive::{EndReason, GameRecord};
+use server::grid::Grid;
+use server::notation::{Move, format_position};
+use server::room::PlayerType;
+use server::rules::Rules;
+use server::stats::PlayerStats;
+use uuid::Uuid;
+
+fn game(mouse: &str, wall: &str, walls: usize, reason: EndReason) -> GameRecord {
+    GameRecord {
+        id: Uuid::new_v4(),
+        started: 1_700_000_000_000,
+        seed: 7,
+        rules: Rules::default(),
+        position: format_position(&Grid::new(7, 7, 4, 3).unwrap(), PlayerType::Wall),
+        players: vec![
+            (PlayerType::Mouse, mouse.to_string()),
+            (PlayerType::Wall, wall.to_string()),
+        ],
+        moves: (0..walls)
+            .map(|i| (i as u64 * 1_000, Move::Wall((0, i))))
+            .collect(),
+        reason,
+    }
+}
+
+#[test]
+fn counts_games_and_wins_per_role() {
+    let games = [
+        game("alice", "bob", 3, EndReason::Trapped),
+        game("bob", "alice", 5, EndReason::Trapped),
+        game("alice", "bob", 2, EndReason::Escaped),
+        game("carol", "bob", 4, EndReason::Forfeit(PlayerType::Mouse)),
+    ];
+
+    let alice = PlayerStats::collect("alice", &games);
+    assert_eq!(alice.role_games(PlayerType::Mouse), 2);
+    assert_eq!(alice.role_wins(PlayerType::Mouse), 1);
+    assert_eq!(alice.role_games(PlayerType::Wall), 1);
+    assert_eq!(alice.role_wins(PlayerType::Wall), 1);
+
+    let bob = PlayerStats::collect("bob", &games);
+    assert_eq!(bob.role_games(PlayerType::Wall), 3);
+    assert_eq!(bob.role_wins(PlayerType::Wall), 2);
+    assert_eq!(bob.role_wins(PlayerType::Mouse), 0);
+}
+
+#[test]
+fn longest_streak_is_kept_after_a_loss() {
+    let games = [
+        game("alice", "bob", 1, EndReason::Escaped),
+        game("bob", "alice", 1, EndReason::Trapped),
+        game("alice", "bob", 1, EndReason::Escaped),
+        game("alice", "bob", 1, EndReason::Trapped),
+        game("alice", "bob", 1, EndReason::Escaped),
+    ];
+
+    assert_eq!(PlayerStats::collect("alice", &games).longest_streak, 3);
+    assert_eq!(PlayerStats::collect("bob", &games).longest_streak, 1);
+    assert_eq!(
+        PlayerStats::collect("carol", &games),
+        PlayerStats::default()
+    );
+}
+
+#[test]
+fn average_only_counts_trapping_wins() {
+    let games = [
+        game("alice", "bob", 3, EndReason::Trapped),
+        game("alice", "bob", 6, EndReason::Trapped),
+        game("alice", "bob", 9, EndReason::Forfeit(PlayerType::Mouse)),
+        game("alice", "bob", 20, EndReason::Escaped),
+    ];
+
+    assert_eq!(
+        PlayerStats::collect("bob", &games).average_moves_to_trap(),
+        Some(4.5)
+    );
+    assert_eq!(
+        PlayerStats::collect("alice", &games).average_moves_to_trap(),
+        None
+    );
+}
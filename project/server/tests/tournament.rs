@@ -0,0 +1,101 @@
+use server::ai::strategies;
+use server::room::PlayerType;
+use server::tournament::{MatchStats, Options, write_csv, write_json};
+
+fn stats(role: PlayerType, games: u64, wins: u64) -> MatchStats {
+    MatchStats {
+        strategy: "greedy",
+        opponent: "random",
+        role,
+        games,
+        wins,
+        total_plies: games * 9,
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+#[test]
+fn wilson_interval_stays_within_bounds() {
+    assert_eq!(
+        stats(PlayerType::Mouse, 0, 0).confidence_interval(),
+        (0.0, 0.0)
+    );
+
+    // No wins still leaves room above, a clean sweep still leaves room below
+    let (low, high) = stats(PlayerType::Mouse, 10, 0).confidence_interval();
+    assert_eq!(low, 0.0);
+    assert!(close(high, 0.2775));
+    let (low, high) = stats(PlayerType::Mouse, 10, 10).confidence_interval();
+    assert!(close(low, 0.7225));
+    assert_eq!(high, 1.0);
+
+    let (low, high) = stats(PlayerType::Mouse, 100, 50).confidence_interval();
+    assert!(close(low, 0.4038) && close(high, 0.5962));
+
+    for games in [1, 7, 100, 5000] {
+        for wins in [0, games / 3, games] {
+            let stats = stats(PlayerType::Wall, games, wins);
+            let (low, high) = stats.confidence_interval();
+            assert!(0.0 <= low && low <= stats.win_rate());
+            assert!(stats.win_rate() <= high && high <= 1.0);
+        }
+    }
+}
+
+#[test]
+fn reports_have_one_row_per_result() {
+    let results = [
+        stats(PlayerType::Wall, 4, 3),
+        stats(PlayerType::Mouse, 4, 1),
+    ];
+
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &results).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "strategy,opponent,role,games,wins,win_rate,ci_low,ci_high,avg_length",
+            "greedy,random,wall,4,3,0.7500,0.3006,0.9544,9.00",
+            "greedy,random,mouse,4,1,0.2500,0.0456,0.6994,9.00",
+        ]
+    );
+
+    let options = Options {
+        games: 4,
+        seed: 1,
+        width: 11,
+        height: 11,
+        walls: 5,
+        strategies: strategies(),
+        csv: None,
+        json: None,
+    };
+    let mut json = Vec::new();
+    write_json(&mut json, &options, &results).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    let lines: Vec<&str> = json.lines().collect();
+
+    assert_eq!(lines.first(), Some(&"{"));
+    assert_eq!(
+        lines[1..7],
+        [
+            "  \"seed\": 1,",
+            "  \"games\": 4,",
+            "  \"width\": 11,",
+            "  \"height\": 11,",
+            "  \"walls\": 5,",
+            "  \"results\": [",
+        ]
+    );
+    // Every result but the last is followed by a comma
+    assert!(lines[7].starts_with("    {\"strategy\": \"greedy\"") && lines[7].ends_with("},"));
+    assert!(
+        lines[8].contains("\"role\": \"mouse\"") && lines[8].ends_with("\"avg_length\": 9.00}")
+    );
+    assert_eq!(lines[9..], ["  ]", "}"]);
+}
@@ -0,0 +1,71 @@
+use hexgrid::zobrist_hash;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use server::grid::{Entity, Grid};
+use server::notation::parse_position;
+use server::room::TurnResult;
+use server::shape::hexagon;
+
+/// Hash of a fresh grid with the same tiles, built without any incremental updates.
+fn recomputed(grid: &Grid) -> u64 {
+    Grid::from_bytes(&grid.as_bytes()).unwrap().zobrist()
+}
+
+fn play_random_game(mut grid: Grid, rng: &mut ChaCha8Rng) {
+    assert_eq!(grid.zobrist(), recomputed(&grid));
+
+    loop {
+        let Some(&(y, x)) = grid.wall_moves().choose(rng) else {
+            return;
+        };
+        assert!(grid.place(&y, &x, Entity::Wall) == TurnResult::Good);
+        assert_eq!(grid.zobrist(), recomputed(&grid));
+
+        let Some(&(y, x)) = grid.mouse_moves().choose(rng) else {
+            return;
+        };
+        let result = grid.move_mouse(&y, &x);
+        assert_eq!(grid.zobrist(), recomputed(&grid));
+
+        if result == TurnResult::GameOver {
+            return;
+        }
+    }
+}
+
+#[test]
+fn incremental_hash_matches() {
+    let mut rng = ChaCha8Rng::seed_from_u64(5);
+
+    for seed in 0..30 {
+        play_random_game(Grid::new(11, 11, 10, seed).unwrap(), &mut rng);
+        play_random_game(Grid::with_mask(&hexagon(5), 6, 2, seed).unwrap(), &mut rng);
+    }
+}
+
+#[test]
+fn hash_tells_boards_apart() {
+    let mut rng = ChaCha8Rng::seed_from_u64(9);
+    let grid = Grid::new(9, 9, 8, 1).unwrap();
+
+    for _ in 0..50 {
+        let mut other = grid.clone();
+        let free = other.wall_moves();
+        let (y, x) = free[rng.random_range(0..free.len())];
+        other.place(&y, &x, Entity::Wall);
+
+        assert_ne!(other.zobrist(), grid.zobrist());
+    }
+}
+
+#[test]
+fn client_and_server_agree_on_a_fixed_board() {
+    let (grid, _) = parse_position("5x3 5/1#M2/-4 w").unwrap();
+    // The tiles as the client stores them after reading the board, by entity id
+    let client_tiles = [0, 0, 0, 0, 0, 0, 2, 1, 0, 0, 3, 0, 0, 0, 0];
+
+    assert_eq!(grid.zobrist(), zobrist_hash(client_tiles));
+    // Pinned so a change to the shared keys shows up here rather than as endless resyncs
+    assert_eq!(grid.zobrist(), 14_025_706_583_008_963_874);
+}